@@ -0,0 +1,77 @@
+//! Upload throughput benchmark: sequential vs pipelined chunk reading
+//!
+//! Uploads the same data through `upload_reader` with read-ahead disabled and
+//! at several read-ahead depths, and prints the throughput of each run.
+//!
+//! Run with `cargo bench --bench upload_pipeline`. The upload size defaults to
+//! 256 MiB and can be changed with `CODEX_BENCH_MB`.
+
+use codex_bindings::{upload_reader, CodexConfig, CodexNode, Profile, UploadOptions};
+use std::fs::File;
+use std::io::{BufReader, Write};
+use std::time::Instant;
+
+const CHUNK_SIZE: usize = 1024 * 1024;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let size_mb: usize = std::env::var("CODEX_BENCH_MB")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(256);
+
+    let temp_dir = tempfile::tempdir()?;
+    let source = temp_dir.path().join("source.bin");
+    {
+        let mut file = File::create(&source)?;
+        let mut block = vec![0u8; CHUNK_SIZE];
+        for i in 0..size_mb {
+            // Vary the content so every chunk produces distinct blocks
+            block.iter_mut().enumerate().for_each(|(j, b)| {
+                *b = (i.wrapping_mul(31) ^ j) as u8;
+            });
+            file.write_all(&block)?;
+        }
+        file.sync_all()?;
+    }
+
+    let config = CodexConfig::profile(Profile::LocalTest)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota((size_mb as u64 * 6) * 1024 * 1024);
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!(
+        "Uploading {} MiB in {} KiB chunks",
+        size_mb,
+        CHUNK_SIZE / 1024
+    );
+    println!("{:>12} {:>10} {:>10}", "read_ahead", "seconds", "MiB/s");
+
+    for read_ahead in [0, 1, 4, 16] {
+        let reader = BufReader::new(File::open(&source)?);
+        let options = UploadOptions::new()
+            .chunk_size(CHUNK_SIZE)
+            .read_ahead(read_ahead);
+
+        let start = Instant::now();
+        let result = upload_reader(&node, options, reader).await?;
+        let secs = start.elapsed().as_secs_f64();
+
+        assert_eq!(result.size, size_mb * CHUNK_SIZE);
+        println!(
+            "{:>12} {:>10.2} {:>10.1}",
+            if read_ahead == 0 {
+                "sequential".to_string()
+            } else {
+                read_ahead.to_string()
+            },
+            secs,
+            size_mb as f64 / secs
+        );
+    }
+
+    node.stop()?;
+    node.destroy()?;
+    Ok(())
+}
@@ -1,11 +1,15 @@
 use crate::error::{CodexError, Result};
 use crate::ffi::{c_str_to_string, CallbackReturn};
+use bytes::Bytes;
 use libc::{c_char, c_int, c_void, size_t};
 use std::collections::HashMap;
 use std::sync::{Arc, LazyLock, Mutex};
 use std::task::{Context, Poll, Waker};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// How long a single libcodex call may take when no deadline is given
+pub(crate) const CALLBACK_TIMEOUT: Duration = Duration::from_secs(60);
 
 static LIBCODEX_MUTEX: Mutex<()> = Mutex::new(());
 
@@ -18,6 +22,7 @@ pub struct CallbackContext {
     waker: Mutex<Option<Waker>>,
     progress_callback: Mutex<Option<Box<dyn Fn(usize, Option<&[u8]>) + Send>>>,
     completed: Mutex<bool>,
+    held: Mutex<Option<Bytes>>,
     id: u64,
 }
 
@@ -34,6 +39,7 @@ impl CallbackContext {
             waker: Mutex::new(None),
             progress_callback: Mutex::new(None),
             completed: Mutex::new(false),
+            held: Mutex::new(None),
             id,
         }
     }
@@ -49,6 +55,25 @@ impl CallbackContext {
         *self.progress_callback.lock().unwrap() = Some(Box::new(callback));
     }
 
+    /// Drop the progress callback and everything it captured
+    pub fn clear_progress_callback(&self) {
+        *self.progress_callback.lock().unwrap() = None;
+    }
+
+    /// Keep `data` alive until libcodex reports completion
+    ///
+    /// libcodex reaches the context through the registry rather than through
+    /// the future, so data held here outlives a future dropped while the call
+    /// is still running.
+    pub fn hold_until_complete(&self, data: Bytes) {
+        *self.held.lock().unwrap() = Some(data);
+    }
+
+    /// Release held data early, when the call it was held for was never issued
+    pub fn release_held(&self) {
+        self.held.lock().unwrap().take();
+    }
+
     pub fn set_waker(&self, waker: Waker) {
         *self.waker.lock().unwrap() = Some(waker);
     }
@@ -74,6 +99,7 @@ impl CallbackContext {
 
                 *self.result.lock().unwrap() = Some(Ok(message));
                 *self.completed.lock().unwrap() = true;
+                self.release_held();
 
                 if let Some(waker) = self.waker.lock().unwrap().take() {
                     waker.wake();
@@ -91,6 +117,7 @@ impl CallbackContext {
 
                 *self.result.lock().unwrap() = Some(Err(CodexError::library_error(message)));
                 *self.completed.lock().unwrap() = true;
+                self.release_held();
 
                 if let Some(waker) = self.waker.lock().unwrap().take() {
                     waker.wake();
@@ -111,14 +138,25 @@ impl CallbackContext {
     }
 
     pub fn wait(&self) -> Result<String> {
-        for _ in 0..600 {
-            {
-                let completed = self.completed.lock().unwrap();
-                if *completed {
+        self.wait_until(step_deadline())
+    }
+
+    /// Wait for the callback until `deadline`, or for as long as it takes with `None`
+    pub fn wait_until(&self, deadline: Option<Instant>) -> Result<String> {
+        loop {
+            if *self.completed.lock().unwrap() {
+                break;
+            }
+
+            let mut pause = Duration::from_millis(100);
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
                     break;
                 }
+                pause = pause.min(remaining);
             }
-            thread::sleep(Duration::from_millis(100));
+            thread::sleep(pause);
         }
 
         if let Some(result) = self.get_result() {
@@ -167,6 +205,10 @@ impl CallbackFuture {
     pub fn wait(&self) -> Result<String> {
         self.context.wait()
     }
+
+    pub fn wait_until(&self, deadline: Option<Instant>) -> Result<String> {
+        self.context.wait_until(deadline)
+    }
 }
 
 impl std::future::Future for CallbackFuture {
@@ -185,6 +227,34 @@ impl std::future::Future for CallbackFuture {
 
 unsafe impl Send for CallbackFuture {}
 
+/// Deadline for an operation allowed to run for `timeout_secs` from now
+pub(crate) fn deadline_after(timeout_secs: Option<u64>) -> Option<Instant> {
+    timeout_secs.map(|secs| Instant::now() + Duration::from_secs(secs))
+}
+
+/// Deadline for a single libcodex call outside of a timed operation
+pub(crate) fn step_deadline() -> Option<Instant> {
+    Some(Instant::now() + CALLBACK_TIMEOUT)
+}
+
+/// Whether `deadline` has passed
+pub(crate) fn is_expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Await `operation`, giving up with [`CodexError::Timeout`] at `deadline`
+pub(crate) async fn run_until<F, T>(deadline: Option<Instant>, operation: F) -> Result<T>
+where
+    F: std::future::Future<Output = Result<T>>,
+{
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), operation)
+            .await
+            .unwrap_or_else(|_| Err(CodexError::timeout("callback operation"))),
+        None => operation.await,
+    }
+}
+
 pub fn with_libcodex_lock<F, R>(f: F) -> R
 where
     F: FnOnce() -> R,
@@ -291,6 +361,23 @@ mod tests {
         assert!(context.get_result().is_none());
     }
 
+    #[test]
+    fn test_held_data_outlives_future() {
+        let data = Bytes::from(vec![7u8; 16]);
+        let future = CallbackFuture::new();
+        let context_ptr = future.context_ptr() as *mut c_void;
+        future.context.hold_until_complete(data.clone());
+
+        // The data stays shared with the context until the callback fires
+        drop(future);
+        assert!(!data.is_unique());
+
+        unsafe {
+            c_callback(0, std::ptr::null_mut(), 0, context_ptr);
+        }
+        assert!(data.is_unique());
+    }
+
     #[test]
     fn test_callback_future_creation() {
         let future = CallbackFuture::new();
@@ -349,6 +436,29 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_callback_wait_until_deadline() {
+        let context = CallbackContext::new();
+        let start = Instant::now();
+        let result = context.wait_until(Some(start + Duration::from_millis(50)));
+        assert!(matches!(result, Err(CodexError::Timeout { .. })));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        assert!(is_expired(Some(start)));
+        assert!(!is_expired(None));
+        assert!(!is_expired(deadline_after(Some(60))));
+    }
+
+    #[tokio::test]
+    async fn test_run_until_deadline() {
+        let deadline = Some(Instant::now() + Duration::from_millis(20));
+        let result: Result<()> = run_until(deadline, std::future::pending()).await;
+        assert!(matches!(result, Err(CodexError::Timeout { .. })));
+
+        let result = run_until(None, async { Ok(7) }).await;
+        assert_eq!(result.unwrap(), 7);
+    }
+
     #[test]
     fn test_c_callback_null_context() {
         unsafe {
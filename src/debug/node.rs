@@ -2,6 +2,7 @@ use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
 use crate::error::{CodexError, Result};
 use crate::ffi::{codex_debug, codex_log_level, free_c_string, string_to_c_string};
 use crate::node::lifecycle::CodexNode;
+use crate::p2p::multiaddr::{deserialize_reported, Multiaddr};
 use libc::c_void;
 use serde::{Deserialize, Serialize};
 
@@ -34,10 +35,17 @@ impl std::fmt::Display for LogLevel {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DebugInfo {
     pub id: String,
-    pub addrs: Vec<String>,
+    /// Listen addresses; addresses using protocols [`Multiaddr`] cannot
+    /// represent are left out
+    #[serde(deserialize_with = "deserialize_reported")]
+    pub addrs: Vec<Multiaddr>,
     pub spr: String,
-    #[serde(rename = "announceAddresses")]
-    pub announce_addresses: Vec<String>,
+    /// Announced addresses, left out like those in `addrs` when unsupported
+    #[serde(
+        rename = "announceAddresses",
+        deserialize_with = "deserialize_reported"
+    )]
+    pub announce_addresses: Vec<Multiaddr>,
     pub table: DiscoveryTable,
 }
 
@@ -0,0 +1,176 @@
+//! Directory download for Codex
+
+use crate::directory::types::{
+    DirectoryDownloadResult, DirectoryEntry, DirectoryManifest, DirectoryOptions,
+};
+use crate::download::{download_stream, DownloadStreamOptions};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use futures::{StreamExt, TryStreamExt};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Download and parse the directory manifest stored under `cid`
+pub async fn download_dir_manifest(node: &CodexNode, cid: &str) -> Result<DirectoryManifest> {
+    let buffer = SharedBuffer::default();
+    let options = DownloadStreamOptions::new(cid).writer(buffer.clone());
+    download_stream(node, cid, options).await?;
+
+    let json = buffer.0.lock().unwrap();
+    DirectoryManifest::from_json(&json)
+}
+
+/// Recreate a directory uploaded with [`upload_dir`](crate::directory::upload_dir)
+///
+/// Downloads the manifest stored under `cid`, then every file it lists that
+/// passes the include and exclude globs, `options.concurrency` at a time. Each
+/// file is written next to its final path and renamed into place once its
+/// size has been checked, so an interrupted download never leaves a truncated
+/// file under the real name. On Unix the recorded read, write and execute
+/// bits are restored; setuid, setgid and sticky bits in the manifest are
+/// ignored, since it may come from anyone.
+///
+/// # Arguments
+///
+/// * `node` - The Codex node to use for the download
+/// * `cid` - CID of the directory manifest
+/// * `dest` - Directory to write the files into; created if missing
+/// * `options` - Include/exclude globs, concurrency and chunk size
+///
+/// # Returns
+///
+/// A `DirectoryDownloadResult` listing the files that were written
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `cid` does not point at a directory manifest
+/// - The manifest contains paths outside `dest`
+/// - Any file fails to download or has the wrong size
+pub async fn download_dir<P: AsRef<Path>>(
+    node: &CodexNode,
+    cid: &str,
+    dest: P,
+    options: DirectoryOptions,
+) -> Result<DirectoryDownloadResult> {
+    options.validate()?;
+
+    let start_time = std::time::Instant::now();
+    let dest = dest.as_ref().to_path_buf();
+    let filter = options.filter()?;
+
+    let manifest = download_dir_manifest(node, cid).await?;
+    let selected: Vec<DirectoryEntry> = manifest
+        .entries
+        .into_iter()
+        .filter(|entry| filter.matches(&entry.path))
+        .collect();
+
+    tokio::fs::create_dir_all(&dest).await?;
+
+    let mut entries: Vec<DirectoryEntry> = futures::stream::iter(selected)
+        .map(|entry| download_entry(node, &dest, entry, options.chunk_size))
+        .buffer_unordered(options.concurrency)
+        .try_collect()
+        .await?;
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(DirectoryDownloadResult {
+        dest,
+        total_bytes: entries.iter().map(|entry| entry.size).sum(),
+        entries,
+        duration_ms: start_time.elapsed().as_millis() as u64,
+    })
+}
+
+async fn download_entry(
+    node: &CodexNode,
+    dest: &Path,
+    entry: DirectoryEntry,
+    chunk_size: Option<usize>,
+) -> Result<DirectoryEntry> {
+    let target = dest.join(&entry.path);
+    if let Some(parent) = target.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    let partial = partial_path(&target);
+    let mut options = DownloadStreamOptions::new(&entry.cid)
+        .filepath(&partial)
+        .dataset_size(entry.size as usize);
+    options.chunk_size = chunk_size;
+
+    if let Err(e) = download_stream(node, &entry.cid, options).await {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(CodexError::download_error(format!(
+            "Failed to download {}: {}",
+            entry.path, e
+        )));
+    }
+
+    let written = tokio::fs::metadata(&partial).await?.len();
+    if written != entry.size {
+        let _ = tokio::fs::remove_file(&partial).await;
+        return Err(CodexError::download_error(format!(
+            "Size mismatch for {}: expected {} bytes, got {}",
+            entry.path, entry.size, written
+        )));
+    }
+
+    set_mode(&partial, entry.mode).await?;
+    tokio::fs::rename(&partial, &target).await?;
+
+    Ok(entry)
+}
+
+fn partial_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_owned();
+    path.push(".part");
+    PathBuf::from(path)
+}
+
+#[cfg(unix)]
+async fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = std::fs::Permissions::from_mode(mode & 0o777);
+    tokio::fs::set_permissions(path, permissions).await?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn set_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// In-memory writer that can be read back after the download has finished
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn test_set_mode_drops_special_bits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.sh");
+        std::fs::write(&path, "#!/bin/sh").unwrap();
+
+        set_mode(&path, 0o4755).await.unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o755);
+    }
+}
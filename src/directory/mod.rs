@@ -0,0 +1,30 @@
+//! Directory operations for Codex
+//!
+//! Codex stores individual datasets. This module publishes a whole directory
+//! tree by uploading each file and then a JSON [`types::DirectoryManifest`]
+//! listing their paths, sizes, permission bits and CIDs. The CID of the
+//! manifest identifies the tree.
+//!
+//! ## Operations
+//!
+//! - [`upload::upload_dir()`] - Upload every file under a directory and its manifest
+//! - [`download::download_dir()`] - Recreate a directory from its manifest CID
+//! - [`download::download_dir_manifest()`] - Fetch and parse a directory manifest
+//!
+//! ## Configuration
+//!
+//! - [`types::DirectoryOptions`] - Include/exclude globs, concurrency and chunk size
+
+pub mod download;
+pub mod types;
+pub mod upload;
+
+// Re-export types
+pub use types::{
+    DirectoryDownloadResult, DirectoryEntry, DirectoryManifest, DirectoryOptions,
+    DirectoryUploadResult,
+};
+
+// Re-export operations
+pub use download::{download_dir, download_dir_manifest};
+pub use upload::upload_dir;
@@ -0,0 +1,330 @@
+//! Types for directory operations
+
+use crate::error::{CodexError, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Value of [`DirectoryManifest::format`]
+pub const DIRECTORY_FORMAT: &str = "codex-directory";
+
+/// Current version of the directory manifest format
+pub const DIRECTORY_FORMAT_VERSION: u32 = 1;
+
+/// Filename the directory manifest is uploaded under
+pub const DIRECTORY_INDEX_FILENAME: &str = "codex-directory.json";
+
+/// Default number of files transferred at the same time
+pub const DEFAULT_DIRECTORY_CONCURRENCY: usize = 4;
+
+/// A file inside an uploaded directory
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryEntry {
+    /// Path relative to the directory root, with `/` separators
+    pub path: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// Unix permission bits of the file, without setuid, setgid and sticky
+    pub mode: u32,
+    /// CID of the file content
+    pub cid: String,
+}
+
+/// Index of an uploaded directory
+///
+/// The manifest is uploaded as JSON after every file it lists, and its CID
+/// stands for the whole tree. Entries are sorted by path, so uploading the same
+/// tree twice yields the same manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryManifest {
+    /// Always [`DIRECTORY_FORMAT`]
+    pub format: String,
+    /// Format version, currently [`DIRECTORY_FORMAT_VERSION`]
+    pub version: u32,
+    /// Files in the directory, sorted by path
+    pub entries: Vec<DirectoryEntry>,
+}
+
+impl DirectoryManifest {
+    /// Create a manifest from a list of entries
+    pub fn new(mut entries: Vec<DirectoryEntry>) -> Self {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Self {
+            format: DIRECTORY_FORMAT.to_string(),
+            version: DIRECTORY_FORMAT_VERSION,
+            entries,
+        }
+    }
+
+    /// Parse a manifest and check that it is safe to extract
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(json).map_err(|e| {
+            CodexError::download_error(format!("Invalid directory manifest: {}", e))
+        })?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Serialize the manifest to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Total size of all files in bytes
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Check the format marker and that every path stays inside the root
+    pub fn validate(&self) -> Result<()> {
+        if self.format != DIRECTORY_FORMAT {
+            return Err(CodexError::download_error(format!(
+                "Not a directory manifest: format is '{}'",
+                self.format
+            )));
+        }
+
+        if self.version > DIRECTORY_FORMAT_VERSION {
+            return Err(CodexError::download_error(format!(
+                "Unsupported directory manifest version {}",
+                self.version
+            )));
+        }
+
+        for entry in &self.entries {
+            if !is_safe_relative_path(&entry.path) {
+                return Err(CodexError::download_error(format!(
+                    "Directory manifest contains an unsafe path: {}",
+                    entry.path
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn is_safe_relative_path(path: &str) -> bool {
+    !path.is_empty()
+        && Path::new(path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+/// Options for directory uploads and downloads
+#[derive(Debug, Clone)]
+pub struct DirectoryOptions {
+    /// Only transfer files matching one of these globs; empty means all files
+    pub include: Vec<String>,
+    /// Skip files matching any of these globs
+    pub exclude: Vec<String>,
+    /// Number of files transferred at the same time
+    pub concurrency: usize,
+    /// Chunk size used for each file (in bytes)
+    pub chunk_size: Option<usize>,
+}
+
+impl Default for DirectoryOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            concurrency: DEFAULT_DIRECTORY_CONCURRENCY,
+            chunk_size: Some(1024 * 1024),
+        }
+    }
+}
+
+impl DirectoryOptions {
+    /// Create new directory options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only transfer files whose relative path matches `pattern`
+    ///
+    /// Patterns are matched against `/`-separated paths relative to the
+    /// directory root, so `*.csv` matches CSV files at any depth and
+    /// `images/**` matches everything under `images`.
+    pub fn include(mut self, pattern: impl Into<String>) -> Self {
+        self.include.push(pattern.into());
+        self
+    }
+
+    /// Skip files whose relative path matches `pattern`
+    pub fn exclude(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude.push(pattern.into());
+        self
+    }
+
+    /// Set the number of files transferred at the same time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the chunk size used for each file
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = Some(size);
+        self
+    }
+
+    /// Validate the directory options
+    pub fn validate(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            return Err(CodexError::invalid_parameter(
+                "concurrency",
+                "Concurrency must be greater than 0",
+            ));
+        }
+
+        if self.chunk_size == Some(0) {
+            return Err(CodexError::invalid_parameter(
+                "chunk_size",
+                "Chunk size must be greater than 0",
+            ));
+        }
+
+        self.filter()?;
+        Ok(())
+    }
+
+    pub(crate) fn filter(&self) -> Result<PathFilter> {
+        Ok(PathFilter {
+            include: build_globs("include", &self.include)?,
+            exclude: build_globs("exclude", &self.exclude)?,
+        })
+    }
+}
+
+fn build_globs(parameter: &str, patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            CodexError::invalid_parameter(parameter, format!("Invalid glob '{}': {}", pattern, e))
+        })?;
+        builder.add(glob);
+    }
+
+    builder
+        .build()
+        .map(Some)
+        .map_err(|e| CodexError::invalid_parameter(parameter, e.to_string()))
+}
+
+/// Compiled include and exclude globs
+pub(crate) struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl PathFilter {
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        let included = self.include.as_ref().is_none_or(|set| set.is_match(path));
+        let excluded = self.exclude.as_ref().is_some_and(|set| set.is_match(path));
+        included && !excluded
+    }
+}
+
+/// Result of a directory upload
+#[derive(Debug, Clone)]
+pub struct DirectoryUploadResult {
+    /// CID of the directory manifest, which identifies the tree
+    pub cid: String,
+    /// The manifest that was uploaded
+    pub manifest: DirectoryManifest,
+    /// Duration of the upload in milliseconds
+    pub duration_ms: u64,
+}
+
+/// Result of a directory download
+#[derive(Debug, Clone)]
+pub struct DirectoryDownloadResult {
+    /// Directory the files were written to
+    pub dest: PathBuf,
+    /// Entries that were downloaded
+    pub entries: Vec<DirectoryEntry>,
+    /// Total bytes written
+    pub total_bytes: u64,
+    /// Duration of the download in milliseconds
+    pub duration_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> DirectoryEntry {
+        DirectoryEntry {
+            path: path.to_string(),
+            size,
+            mode: 0o644,
+            cid: format!("cid-{}", path),
+        }
+    }
+
+    #[test]
+    fn test_manifest_roundtrip_sorted() {
+        let manifest = DirectoryManifest::new(vec![entry("b.txt", 2), entry("a/c.txt", 3)]);
+        assert_eq!(manifest.entries[0].path, "a/c.txt");
+        assert_eq!(manifest.total_size(), 5);
+
+        let parsed = DirectoryManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+
+    #[test]
+    fn test_manifest_rejects_unsafe_paths() {
+        for path in ["../etc/passwd", "/etc/passwd", "a/../../b", ""] {
+            let manifest = DirectoryManifest::new(vec![entry(path, 1)]);
+            let json = manifest.to_json().unwrap();
+            assert!(
+                DirectoryManifest::from_json(&json).is_err(),
+                "accepted {:?}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_manifest_rejects_other_json() {
+        assert!(DirectoryManifest::from_json(
+            b"{\"format\":\"other\",\"version\":1,\"entries\":[]}"
+        )
+        .is_err());
+        assert!(DirectoryManifest::from_json(b"hello").is_err());
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let filter = DirectoryOptions::new()
+            .include("*.csv")
+            .include("images/**")
+            .exclude("**/tmp/**")
+            .filter()
+            .unwrap();
+
+        assert!(filter.matches("data.csv"));
+        assert!(filter.matches("nested/data.csv"));
+        assert!(filter.matches("images/a.png"));
+        assert!(!filter.matches("readme.md"));
+        assert!(!filter.matches("nested/tmp/data.csv"));
+
+        let all = DirectoryOptions::new().filter().unwrap();
+        assert!(all.matches("anything/at/all"));
+    }
+
+    #[test]
+    fn test_options_validate() {
+        assert!(DirectoryOptions::new().validate().is_ok());
+        assert!(DirectoryOptions::new().concurrency(0).validate().is_err());
+        assert!(matches!(
+            DirectoryOptions::new().include("a[").validate(),
+            Err(CodexError::InvalidParameter { .. })
+        ));
+    }
+}
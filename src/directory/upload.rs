@@ -0,0 +1,212 @@
+//! Directory upload for Codex
+
+use crate::directory::types::{
+    DirectoryEntry, DirectoryManifest, DirectoryOptions, DirectoryUploadResult,
+    DIRECTORY_INDEX_FILENAME,
+};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::upload::{upload_file, upload_reader, UploadOptions};
+use futures::{StreamExt, TryStreamExt};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A file selected for upload
+struct LocalFile {
+    path: PathBuf,
+    relative: String,
+    size: u64,
+    mode: u32,
+}
+
+/// Upload every file under a directory and then its manifest
+///
+/// Files are uploaded `options.concurrency` at a time. Once all of them have a
+/// CID, a [`DirectoryManifest`] listing their relative paths, sizes, modes and
+/// CIDs is uploaded; the CID of that manifest identifies the tree and can be
+/// passed to [`download_dir`](crate::directory::download_dir).
+///
+/// Symbolic links are not followed and empty directories are not recorded.
+///
+/// # Arguments
+///
+/// * `node` - The Codex node to use for the upload
+/// * `path` - Root of the directory to upload
+/// * `options` - Include/exclude globs, concurrency and chunk size
+///
+/// # Returns
+///
+/// A `DirectoryUploadResult` with the manifest CID and the manifest itself
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `path` is not a directory
+/// - The options are invalid
+/// - Any file fails to upload
+pub async fn upload_dir<P: AsRef<Path>>(
+    node: &CodexNode,
+    path: P,
+    options: DirectoryOptions,
+) -> Result<DirectoryUploadResult> {
+    options.validate()?;
+
+    let root = path.as_ref().to_path_buf();
+    if !root.is_dir() {
+        return Err(CodexError::invalid_parameter(
+            "path",
+            format!("Not a directory: {}", root.display()),
+        ));
+    }
+
+    let start_time = std::time::Instant::now();
+
+    let files = {
+        let root = root.clone();
+        let options = options.clone();
+        tokio::task::spawn_blocking(move || collect_files(&root, &options)).await??
+    };
+
+    let entries: Vec<DirectoryEntry> = futures::stream::iter(files)
+        .map(|file| upload_entry(node, file, options.chunk_size))
+        .buffer_unordered(options.concurrency)
+        .try_collect()
+        .await?;
+
+    let manifest = DirectoryManifest::new(entries);
+    let index_options = UploadOptions::new().filename(DIRECTORY_INDEX_FILENAME);
+    let index = upload_reader(node, index_options, Cursor::new(manifest.to_json()?)).await?;
+
+    Ok(DirectoryUploadResult {
+        cid: index.cid,
+        manifest,
+        duration_ms: start_time.elapsed().as_millis() as u64,
+    })
+}
+
+async fn upload_entry(
+    node: &CodexNode,
+    file: LocalFile,
+    chunk_size: Option<usize>,
+) -> Result<DirectoryEntry> {
+    let mut options = UploadOptions::new().filepath(&file.path);
+    options.chunk_size = chunk_size;
+
+    let result = upload_file(node, options).await.map_err(|e| {
+        CodexError::upload_error(format!("Failed to upload {}: {}", file.relative, e))
+    })?;
+
+    Ok(DirectoryEntry {
+        path: file.relative,
+        size: file.size,
+        mode: file.mode,
+        cid: result.cid,
+    })
+}
+
+fn collect_files(root: &Path, options: &DirectoryOptions) -> Result<Vec<LocalFile>> {
+    let filter = options.filter()?;
+    let mut files = Vec::new();
+
+    for entry in WalkDir::new(root).follow_links(false).sort_by_file_name() {
+        let entry = entry.map_err(|e| match e.into_io_error() {
+            Some(e) => CodexError::Io(e),
+            None => CodexError::upload_error("Filesystem loop while walking directory"),
+        })?;
+
+        if !entry.file_type().is_file() {
+            continue;
+        }
+
+        let relative = relative_path(root, entry.path())?;
+        if !filter.matches(&relative) {
+            continue;
+        }
+
+        let metadata = entry
+            .metadata()
+            .map_err(|e| CodexError::upload_error(format!("Failed to stat {}: {}", relative, e)))?;
+
+        files.push(LocalFile {
+            path: entry.path().to_path_buf(),
+            relative,
+            size: metadata.len(),
+            mode: file_mode(&metadata),
+        });
+    }
+
+    Ok(files)
+}
+
+/// `path` relative to `root`, joined with `/` on every platform
+fn relative_path(root: &Path, path: &Path) -> Result<String> {
+    let relative = path.strip_prefix(root).map_err(|_| {
+        CodexError::upload_error(format!("{} is outside {}", path.display(), root.display()))
+    })?;
+
+    let parts: Option<Vec<&str>> = relative
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect();
+    parts.map(|parts| parts.join("/")).ok_or_else(|| {
+        CodexError::upload_error(format!("Path is not valid UTF-8: {}", path.display()))
+    })
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o777
+}
+
+#[cfg(not(unix))]
+fn file_mode(metadata: &std::fs::Metadata) -> u32 {
+    if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_collect_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("data/tmp")).unwrap();
+        fs::create_dir_all(dir.path().join("empty")).unwrap();
+        fs::write(dir.path().join("readme.md"), "hello").unwrap();
+        fs::write(dir.path().join("data/a.csv"), "1,2,3").unwrap();
+        fs::write(dir.path().join("data/tmp/b.csv"), "4").unwrap();
+
+        let all = collect_files(dir.path(), &DirectoryOptions::new()).unwrap();
+        let paths: Vec<&str> = all.iter().map(|f| f.relative.as_str()).collect();
+        assert_eq!(paths, vec!["data/a.csv", "data/tmp/b.csv", "readme.md"]);
+        assert_eq!(all[0].size, 5);
+
+        let options = DirectoryOptions::new()
+            .include("*.csv")
+            .exclude("**/tmp/**");
+        let csv = collect_files(dir.path(), &options).unwrap();
+        assert_eq!(csv.len(), 1);
+        assert_eq!(csv[0].relative, "data/a.csv");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_files_records_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("run.sh");
+        fs::write(&script, "#!/bin/sh").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let files = collect_files(dir.path(), &DirectoryOptions::new()).unwrap();
+        assert_eq!(files[0].mode, 0o755);
+    }
+}
@@ -8,12 +8,15 @@
 //! - [`stream::download_stream()`] - Download content to file or writer with progress tracking
 //! - [`stream::download_to_file()`] - Download content directly to a file
 //! - [`stream::download_to_writer()`] - Download content to any Write implementation
+//! - [`reader::download_reader()`] - Read content through AsyncRead or a Stream with bounded memory
+//! - [`segmented::download_segmented()`] - Reassemble the content of a segmented upload
 //!
 //! ## Low-Level Operations
 //!
 //! - [`session::download_init()`] - Initialize a download session
 //! - [`chunks::download_chunk()`] - Download a specific chunk of data
 //! - [`session::download_cancel()`] - Cancel a download session
+//! - [`session::DownloadSession`] - Owned handle over the same steps that cancels on drop
 //!
 //! ## Manifest Operations
 //!
@@ -23,27 +26,37 @@
 //!
 //! - [`types::DownloadOptions`] - Configure download behavior including chunk size and timeout
 //! - [`types::DownloadStreamOptions`] - Configure streaming downloads with output destinations
+//! - [`types::DownloadReaderOptions`] - Configure downloads read through a [`reader::DownloadReader`]
 //! - [`types::Manifest`] - Manifest structure for metadata and content information
 
 pub mod chunks;
 pub mod manifest;
+pub mod reader;
+pub mod segmented;
 pub mod session;
 pub mod stream;
 pub mod types;
 
 // Re-export types
 pub use types::{
-    DownloadOptions, DownloadProgress, DownloadResult, DownloadStreamOptions, Manifest,
+    DownloadOptions, DownloadProgress, DownloadReaderOptions, DownloadResult,
+    DownloadStreamOptions, Manifest,
 };
 
 // Re-export manifest operations
 pub use manifest::download_manifest;
 
 // Re-export session management operations
-pub use session::{download_cancel, download_init};
+pub use session::{download_cancel, download_init, DownloadSession};
 
 // Re-export chunk operations
 pub use chunks::{download_chunk, download_chunk_with_progress, download_chunks};
 
 // Re-export stream operations
 pub use stream::{download_stream, download_to_file, download_to_writer};
+
+// Re-export reader operations
+pub use reader::{download_reader, DownloadReader};
+
+// Re-export segmented download operations
+pub use segmented::{download_segment_manifest, download_segmented};
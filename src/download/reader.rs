@@ -0,0 +1,317 @@
+//! Downloads read at the pace of the consumer
+//!
+//! [`download_reader`] returns a [`DownloadReader`], which is both an
+//! [`AsyncRead`] and a [`Stream`] of [`Bytes`]. A background task pulls chunks
+//! through a [`DownloadSession`] into a bounded channel. When the consumer
+//! falls behind, the channel fills up and the task stops asking the node for
+//! more, so memory use depends on the buffer size rather than on the size of
+//! the content.
+
+use crate::callback::{deadline_after, run_until};
+use crate::download::session::DownloadSession;
+use crate::download::types::{DownloadOptions, DownloadProgress, DownloadReaderOptions};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::storage::fetch;
+use crate::throttle::Throttle;
+use crate::upload::writer::to_io_error;
+use bytes::Bytes;
+use futures::channel::mpsc;
+use futures::{SinkExt, Stream, StreamExt};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+type ProgressCallback = Box<dyn Fn(DownloadProgress) + Send + Sync>;
+
+/// Download content as an AsyncRead and a Stream
+///
+/// The manifest is fetched and the download session opened before this
+/// returns, so an unknown CID fails here rather than on the first read.
+/// Chunks are then fetched in the background, at most
+/// [`DownloadReaderOptions::buffer_chunks`] ahead of the consumer. Dropping
+/// the reader stops the download and cancels the session.
+///
+/// Chunks are held back to respect [`DownloadReaderOptions::rate_limit`] and
+/// the node-wide [`CodexNode::download_limiter`].
+///
+/// # Arguments
+///
+/// * `node` - The Codex node to use for the download
+/// * `cid` - The content ID to download
+/// * `options` - Chunk size, buffer size and other reader options
+///
+/// # Returns
+///
+/// A [`DownloadReader`] yielding the content
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The CID is empty
+/// - The options are invalid
+/// - The manifest cannot be fetched or the session cannot be opened
+///
+/// Failures after that, including the node delivering less content than
+/// the manifest records and [`DownloadReaderOptions::timeout`] passing, are
+/// reported by the reader.
+pub async fn download_reader(
+    node: &CodexNode,
+    cid: &str,
+    options: DownloadReaderOptions,
+) -> Result<DownloadReader> {
+    if cid.is_empty() {
+        return Err(CodexError::invalid_parameter("cid", "CID cannot be empty"));
+    }
+    options.validate()?;
+
+    let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+    let size = run_until(deadline_after(options.timeout), fetch(node, cid))
+        .await?
+        .dataset_size;
+
+    let mut download_options = DownloadOptions::new(cid)
+        .chunk_size(chunk_size)
+        .verify(options.verify);
+    download_options.timeout = options.timeout;
+    let session = DownloadSession::start(node, &download_options).await?;
+
+    // The sender adds one slot of its own to the channel
+    let (tx, rx) = mpsc::channel(options.buffer_chunks - 1);
+    tokio::spawn(fetch_chunks(
+        session,
+        size,
+        chunk_size,
+        Throttle::download(node, options.rate_limit.as_ref()),
+        options.on_progress,
+        tx,
+    ));
+
+    Ok(DownloadReader::new(rx, size))
+}
+
+/// Pull chunks from `session` into `tx` until `size` bytes were sent
+async fn fetch_chunks(
+    mut session: DownloadSession,
+    size: usize,
+    chunk_size: usize,
+    throttle: Throttle,
+    on_progress: Option<ProgressCallback>,
+    mut tx: mpsc::Sender<Result<Bytes>>,
+) {
+    let mut received = 0;
+    while received < size {
+        throttle.acquire(chunk_size.min(size - received)).await;
+
+        let mut chunk = match session.download_chunk().await {
+            Ok(chunk) if chunk.is_empty() => break,
+            Ok(chunk) => Bytes::from(chunk),
+            Err(e) => {
+                let _ = tx.send(Err(e)).await;
+                return;
+            }
+        };
+        chunk.truncate(size - received);
+        received += chunk.len();
+
+        if let Some(ref callback) = on_progress {
+            callback(DownloadProgress::new(received, Some(size)));
+        }
+
+        // The reader was dropped; dropping the session cancels it
+        if tx.send(Ok(chunk)).await.is_err() {
+            return;
+        }
+    }
+
+    if received < size {
+        let _ = tx
+            .send(Err(CodexError::download_error(format!(
+                "Download ended after {} of {} bytes",
+                received, size
+            ))))
+            .await;
+    }
+
+    // Let the reader see the end before waiting on the node
+    drop(tx);
+    let _ = session.cancel().await;
+}
+
+/// Content of a download, created by [`download_reader`]
+///
+/// Read it either through [`AsyncRead`] or as a [`Stream`] of chunks; both
+/// can be mixed, the stream then yields what is left of a partly read chunk
+/// first. An error ends the content.
+pub struct DownloadReader {
+    chunks: mpsc::Receiver<Result<Bytes>>,
+    current: Bytes,
+    size: usize,
+    bytes_read: usize,
+}
+
+impl DownloadReader {
+    fn new(chunks: mpsc::Receiver<Result<Bytes>>, size: usize) -> Self {
+        Self {
+            chunks,
+            current: Bytes::new(),
+            size,
+            bytes_read: 0,
+        }
+    }
+
+    /// Size of the content as recorded in its manifest
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Bytes handed to the consumer so far
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
+    }
+}
+
+impl std::fmt::Debug for DownloadReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadReader")
+            .field("size", &self.size)
+            .field("bytes_read", &self.bytes_read)
+            .finish()
+    }
+}
+
+impl Stream for DownloadReader {
+    type Item = Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        let chunk = if this.current.is_empty() {
+            match ready!(this.chunks.poll_next_unpin(cx)) {
+                Some(Ok(chunk)) => chunk,
+                other => return Poll::Ready(other),
+            }
+        } else {
+            std::mem::take(&mut this.current)
+        };
+
+        this.bytes_read += chunk.len();
+        Poll::Ready(Some(Ok(chunk)))
+    }
+}
+
+impl AsyncRead for DownloadReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        while this.current.is_empty() {
+            match ready!(this.chunks.poll_next_unpin(cx)) {
+                Some(Ok(chunk)) => this.current = chunk,
+                Some(Err(e)) => return Poll::Ready(Err(to_io_error(e))),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+
+        let n = buf.remaining().min(this.current.len());
+        buf.put_slice(&this.current.split_to(n));
+        this.bytes_read += n;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn reader_with(chunks: Vec<Result<Bytes>>) -> DownloadReader {
+        let size = chunks
+            .iter()
+            .map(|chunk| chunk.as_ref().map_or(0, Bytes::len))
+            .sum();
+        let (mut tx, rx) = mpsc::channel(chunks.len());
+        for chunk in chunks {
+            tx.try_send(chunk).unwrap();
+        }
+        DownloadReader::new(rx, size)
+    }
+
+    #[tokio::test]
+    async fn test_read_across_chunks() {
+        let mut reader = reader_with(vec![
+            Ok(Bytes::from_static(b"Hello, ")),
+            Ok(Bytes::from_static(b"reader")),
+        ]);
+
+        let mut head = [0u8; 3];
+        reader.read_exact(&mut head).await.unwrap();
+        assert_eq!(&head, b"Hel");
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"lo, reader");
+        assert_eq!(reader.bytes_read(), reader.size());
+    }
+
+    #[tokio::test]
+    async fn test_stream_after_partial_read() {
+        let mut reader = reader_with(vec![
+            Ok(Bytes::from_static(b"abcdef")),
+            Ok(Bytes::from_static(b"ghi")),
+        ]);
+
+        let mut head = [0u8; 2];
+        reader.read_exact(&mut head).await.unwrap();
+
+        let rest: Vec<Bytes> = reader.by_ref().map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(
+            rest,
+            vec![Bytes::from_static(b"cdef"), Bytes::from_static(b"ghi")]
+        );
+        assert_eq!(reader.bytes_read(), 9);
+    }
+
+    #[tokio::test]
+    async fn test_error_ends_content() {
+        let mut reader = reader_with(vec![
+            Ok(Bytes::from_static(b"partial")),
+            Err(CodexError::download_error("node went away")),
+        ]);
+
+        let mut content = Vec::new();
+        assert!(reader.read_to_end(&mut content).await.is_err());
+        assert_eq!(content, b"partial");
+    }
+
+    #[tokio::test]
+    async fn test_bounded_buffer() {
+        let (mut tx, rx) = mpsc::channel::<Result<Bytes>>(1);
+        let mut reader = DownloadReader::new(rx, 3);
+
+        // One slot for the sender and one for the buffer
+        tx.try_send(Ok(Bytes::from_static(b"a"))).unwrap();
+        tx.try_send(Ok(Bytes::from_static(b"b"))).unwrap();
+        assert!(tx
+            .try_send(Ok(Bytes::from_static(b"c")))
+            .unwrap_err()
+            .is_full());
+
+        assert_eq!(
+            reader.next().await.unwrap().unwrap(),
+            Bytes::from_static(b"a")
+        );
+        tx.try_send(Ok(Bytes::from_static(b"c"))).unwrap();
+        drop(tx);
+
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(rest, b"bc");
+    }
+}
@@ -0,0 +1,134 @@
+//! Download of segmented uploads
+
+use crate::download::stream::download_stream;
+use crate::download::types::{DownloadResult, DownloadStreamOptions};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::upload::segmented::SegmentManifest;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Download and parse the segment manifest stored under `cid`
+pub async fn download_segment_manifest(node: &CodexNode, cid: &str) -> Result<SegmentManifest> {
+    let buffer = SharedWriter::<Vec<u8>>::default();
+    let options = DownloadStreamOptions::new(cid).writer(buffer.clone());
+    download_stream(node, cid, options).await?;
+
+    let json = buffer.0.lock().unwrap();
+    SegmentManifest::from_json(&json)
+}
+
+/// Download the content of a segmented upload to `filepath`
+///
+/// Downloads the [`SegmentManifest`] stored under `cid`, the CID returned by
+/// [`resume_upload`](crate::upload::resume_upload) with
+/// [`UploadOptions::segment_size`](crate::upload::UploadOptions::segment_size)
+/// set, then each segment in turn. The content is written next to `filepath`
+/// and renamed into place once its size has been checked, so an interrupted
+/// download never leaves a truncated file under the real name.
+///
+/// # Arguments
+///
+/// * `node` - The Codex node to use for the download
+/// * `cid` - CID of the segment manifest
+/// * `filepath` - Where to write the content
+///
+/// # Returns
+///
+/// A `DownloadResult` with the size of the content and one chunk per segment
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `cid` does not point at a segment manifest
+/// - Any segment fails to download or has the wrong size
+pub async fn download_segmented<P: AsRef<Path>>(
+    node: &CodexNode,
+    cid: &str,
+    filepath: P,
+) -> Result<DownloadResult> {
+    let start_time = std::time::Instant::now();
+    let filepath = filepath.as_ref().to_path_buf();
+    let manifest = download_segment_manifest(node, cid).await?;
+
+    let partial = partial_path(&filepath);
+    let file = SharedWriter(Arc::new(Mutex::new(File::create(&partial)?)));
+
+    for (i, segment) in manifest.segments.iter().enumerate() {
+        let options = DownloadStreamOptions::new(&segment.cid)
+            .writer(file.clone())
+            .dataset_size(segment.size as usize);
+
+        let written = match download_stream(node, &segment.cid, options).await {
+            Ok(result) => result.size as u64,
+            Err(e) => {
+                let _ = tokio::fs::remove_file(&partial).await;
+                return Err(CodexError::download_error(format!(
+                    "Failed to download segment {} ({}): {}",
+                    i, segment.cid, e
+                )));
+            }
+        };
+
+        if written != segment.size {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(CodexError::download_error(format!(
+                "Size mismatch for segment {} ({}): expected {} bytes, got {}",
+                i, segment.cid, segment.size, written
+            )));
+        }
+    }
+
+    file.0.lock().unwrap().sync_all()?;
+    tokio::fs::rename(&partial, &filepath).await?;
+
+    Ok(
+        DownloadResult::new(cid.to_string(), manifest.total_size() as usize)
+            .chunks(manifest.segments.len())
+            .duration_ms(start_time.elapsed().as_millis() as u64)
+            .filepath(filepath),
+    )
+}
+
+fn partial_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_owned();
+    path.push(".part");
+    PathBuf::from(path)
+}
+
+/// Writer shared by the downloads of all segments
+#[derive(Default)]
+struct SharedWriter<W>(Arc<Mutex<W>>);
+
+impl<W> Clone for SharedWriter<W> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<W: Write> Write for SharedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_writer_appends() {
+        let writer = SharedWriter::<Vec<u8>>::default();
+        let mut first = writer.clone();
+        let mut second = writer.clone();
+        first.write_all(b"seg").unwrap();
+        second.write_all(b"ments").unwrap();
+        assert_eq!(*writer.0.lock().unwrap(), b"segments");
+    }
+}
@@ -3,13 +3,18 @@
 //! This module provides low-level session management operations for downloads.
 //! These functions handle the lifecycle of download sessions including initialization
 //! and cancellation.
+//!
+//! [`DownloadSession`] wraps the same operations in a handle that owns the CID
+//! of the session and cancels it when dropped.
 
-use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
+use crate::callback::{c_callback, deadline_after, run_until, with_libcodex_lock, CallbackFuture};
+use crate::download::chunks::download_chunk;
 use crate::download::types::DownloadOptions;
 use crate::error::{CodexError, Result};
 use crate::ffi::{codex_download_cancel, codex_download_init, free_c_string, string_to_c_string};
 use crate::node::lifecycle::CodexNode;
 use libc::c_void;
+use std::time::Instant;
 
 /// Initialize a download session
 ///
@@ -102,40 +107,51 @@ pub async fn download_cancel(node: &CodexNode, cid: &str) -> Result<()> {
     let cid = cid.to_string();
 
     tokio::task::spawn_blocking(move || {
-        if cid.is_empty() {
-            return Err(CodexError::invalid_parameter("cid", "CID cannot be empty"));
-        }
-
         let future = CallbackFuture::new();
+        start_download_cancel(&node, &cid, &future)?;
 
-        let context_ptr = future.context_ptr() as *mut c_void;
+        future.wait()?;
 
-        let result = with_libcodex_lock(|| unsafe {
-            let ctx = node.ctx();
-            let c_cid = string_to_c_string(&cid);
-            let result = codex_download_cancel(ctx as *mut _, c_cid, Some(c_callback), context_ptr);
+        Ok(())
+    })
+    .await?
+}
 
-            free_c_string(c_cid);
+/// Issue `codex_download_cancel`; completion is reported through `future`
+pub(crate) fn start_download_cancel(
+    node: &CodexNode,
+    cid: &str,
+    future: &CallbackFuture,
+) -> Result<()> {
+    if cid.is_empty() {
+        return Err(CodexError::invalid_parameter("cid", "CID cannot be empty"));
+    }
 
-            result
-        });
+    let context_ptr = future.context_ptr() as *mut c_void;
 
-        if result != 0 {
-            return Err(CodexError::download_error("Failed to cancel download"));
-        }
+    let result = with_libcodex_lock(|| unsafe {
+        let ctx = node.ctx();
+        let c_cid = string_to_c_string(cid);
+        let result = codex_download_cancel(ctx as *mut _, c_cid, Some(c_callback), context_ptr);
 
-        future.wait()?;
+        free_c_string(c_cid);
 
-        Ok(())
-    })
-    .await?
+        result
+    });
+
+    if result != 0 {
+        return Err(CodexError::download_error("Failed to cancel download"));
+    }
+
+    Ok(())
 }
 
-/// Synchronous version of download_init for internal use
+/// Synchronous version of download_init for internal use, waiting until `deadline`
 pub(crate) fn download_init_sync(
     node: &CodexNode,
     cid: &str,
     options: &DownloadOptions,
+    deadline: Option<Instant>,
 ) -> Result<()> {
     if cid.is_empty() {
         return Err(CodexError::invalid_parameter("cid", "CID cannot be empty"));
@@ -170,7 +186,112 @@ pub(crate) fn download_init_sync(
         return Err(CodexError::download_error("Failed to initialize download"));
     }
 
-    future.wait()?;
+    future.wait_until(deadline)?;
 
     Ok(())
 }
+
+/// An open download session
+///
+/// Created by [`DownloadSession::start`] for the CID in the options. Chunks
+/// are fetched in order with [`download_chunk`](DownloadSession::download_chunk).
+/// [`cancel`](DownloadSession::cancel) consumes the handle; a session dropped
+/// without being cancelled is cancelled in the background.
+///
+/// [`DownloadOptions::timeout`] bounds the whole session. A call still
+/// running when it passes fails with [`CodexError::Timeout`] and cancels the
+/// session; the handle cannot be used after that.
+pub struct DownloadSession {
+    node: CodexNode,
+    cid: String,
+    bytes_downloaded: usize,
+    chunks: usize,
+    deadline: Option<Instant>,
+    closed: bool,
+}
+
+impl DownloadSession {
+    /// Initialize a download session for `options.cid`
+    pub async fn start(node: &CodexNode, options: &DownloadOptions) -> Result<Self> {
+        let deadline = deadline_after(options.timeout);
+        run_until(deadline, download_init(node, &options.cid, options))
+            .await
+            .map_err(|e| e.with_timeout_progress("download", Some(0), None))?;
+        Ok(Self {
+            node: node.clone(),
+            cid: options.cid.clone(),
+            bytes_downloaded: 0,
+            chunks: 0,
+            deadline,
+            closed: false,
+        })
+    }
+
+    /// The CID being downloaded
+    pub fn cid(&self) -> &str {
+        &self.cid
+    }
+
+    /// Bytes received so far
+    pub fn bytes_downloaded(&self) -> usize {
+        self.bytes_downloaded
+    }
+
+    /// Chunks received so far
+    pub fn chunks_downloaded(&self) -> usize {
+        self.chunks
+    }
+
+    /// Download the next chunk of the content
+    pub async fn download_chunk(&mut self) -> Result<Vec<u8>> {
+        if self.closed {
+            return Err(CodexError::download_error("Download session is closed"));
+        }
+
+        let chunk = match run_until(self.deadline, download_chunk(&self.node, &self.cid)).await {
+            Err(e @ CodexError::Timeout { .. }) => {
+                self.cancel_in_background();
+                let bytes = self.bytes_downloaded;
+                return Err(e.with_timeout_progress("download", Some(bytes), None));
+            }
+            chunk => chunk?,
+        };
+        self.bytes_downloaded += chunk.len();
+        self.chunks += 1;
+        Ok(chunk)
+    }
+
+    /// Cancel the session and wait for the node to confirm
+    pub async fn cancel(mut self) -> Result<()> {
+        self.closed = true;
+        download_cancel(&self.node, &self.cid).await
+    }
+
+    fn cancel_in_background(&mut self) {
+        self.closed = true;
+
+        // Fire and forget: the callback of a dropped future is ignored
+        let future = CallbackFuture::new();
+        if let Err(e) = start_download_cancel(&self.node, &self.cid, &future) {
+            log::warn!("Failed to cancel download session {}: {}", self.cid, e);
+        }
+    }
+}
+
+impl std::fmt::Debug for DownloadSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadSession")
+            .field("cid", &self.cid)
+            .field("bytes_downloaded", &self.bytes_downloaded)
+            .field("chunks", &self.chunks)
+            .finish()
+    }
+}
+
+impl Drop for DownloadSession {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.cancel_in_background();
+        }
+    }
+}
@@ -4,12 +4,18 @@
 //! It supports downloading content directly to files, writers, or custom destinations
 //! with progress tracking and verification.
 
-use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
-use crate::download::session::download_init_sync;
-use crate::download::types::{DownloadOptions, DownloadResult, DownloadStreamOptions};
+use crate::callback::{c_callback, deadline_after, run_until, with_libcodex_lock, CallbackFuture};
+use crate::download::session::DownloadSession;
+use crate::download::session::{download_init_sync, start_download_cancel};
+use crate::download::types::{
+    DownloadOptions, DownloadProgress, DownloadResult, DownloadStreamOptions,
+};
 use crate::error::{CodexError, Result};
 use crate::ffi::{codex_download_stream, free_c_string, string_to_c_string};
 use crate::node::lifecycle::CodexNode;
+use crate::storage::fetch;
+use crate::throttle::Throttle;
+use crate::upload::compression::{Compression, DecompressingWriter};
 use libc::c_void;
 use std::io::Write;
 use std::sync::{Arc, Mutex};
@@ -20,6 +26,22 @@ use std::sync::{Arc, Mutex};
 /// to a file, writer, or custom callback. This function handles the complete download
 /// process including session management, progress tracking, and error handling.
 ///
+/// Downloads are held back to respect [`DownloadStreamOptions::rate_limit`]
+/// and the node-wide [`CodexNode::download_limiter`]; while either has a rate
+/// set, content is pulled chunk by chunk through a
+/// [`DownloadSession`] instead of being streamed by the node. Downloads with
+/// [`DownloadStreamOptions::local`] set are not limited.
+///
+/// With [`DownloadStreamOptions::decompress`] set, content uploaded with
+/// [`UploadOptions::compression`](crate::upload::UploadOptions::compression)
+/// is decompressed before it reaches the file or writer, and the size in the
+/// result is the decompressed size.
+///
+/// The node delivers content as fast as it receives it and a slow writer
+/// does not hold it back, so content waiting for the writer is kept in
+/// memory. Consumers that read at their own pace should use
+/// [`download_reader`](crate::download::download_reader) instead.
+///
 /// # Arguments
 ///
 /// * `node` - The Codex node to use for the download
@@ -36,14 +58,37 @@ use std::sync::{Arc, Mutex};
 /// - The CID is empty
 /// - The options are invalid
 /// - The download fails for any reason
+/// - The download does not finish within `options.timeout` seconds; the
+///   session is cancelled and [`CodexError::Timeout`] reports the bytes
+///   received so far
+/// - With [`DownloadStreamOptions::decompress`], both a file path and a
+///   writer are set, or the compressed content is corrupt or truncated
 pub async fn download_stream(
     node: &CodexNode,
     cid: &str,
     options: DownloadStreamOptions,
 ) -> Result<DownloadResult> {
+    if options.decompress {
+        return download_decompressed(node, cid, options).await;
+    }
+
+    download_raw(node, cid, options).await
+}
+
+/// Download content to the destinations in `options` as it is stored
+async fn download_raw(
+    node: &CodexNode,
+    cid: &str,
+    options: DownloadStreamOptions,
+) -> Result<DownloadResult> {
+    // Local content does not use the network, so it is never held back
+    let throttle = Throttle::download(node, options.rate_limit.as_ref());
+    if !options.local && throttle.is_limited() {
+        return download_throttled(node, cid, options, throttle).await;
+    }
+
     let node = node.clone();
     let cid = cid.to_string();
-    let options = options.clone();
 
     tokio::task::spawn_blocking(move || {
         if cid.is_empty() {
@@ -53,6 +98,7 @@ pub async fn download_stream(
         options.validate()?;
 
         let start_time = std::time::Instant::now();
+        let deadline = deadline_after(options.timeout);
         let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
 
         let total_bytes = Arc::new(Mutex::new(0usize));
@@ -114,7 +160,8 @@ pub async fn download_stream(
             .timeout(options.timeout.unwrap_or(300))
             .verify(options.verify);
 
-        download_init_sync(&node, &cid, &download_options)?;
+        download_init_sync(&node, &cid, &download_options, deadline)
+            .map_err(|e| e.with_timeout_progress("download", Some(0), options.dataset_size))?;
 
         let context_ptr = future.context_ptr() as *mut c_void;
         let cid_str = &cid;
@@ -152,8 +199,17 @@ pub async fn download_stream(
             return Err(CodexError::download_error("Failed to download stream"));
         }
 
-        future.wait()?;
+        let waited = future.wait_until(deadline);
+        if let Err(CodexError::Timeout { .. }) = waited {
+            // Fire and forget: the callback of a dropped future is ignored
+            if let Err(e) = start_download_cancel(&node, &cid, &CallbackFuture::new()) {
+                log::warn!("Failed to cancel download of {}: {}", cid, e);
+            }
+        }
 
+        // The callback holds a sender; the writer thread only stops once
+        // every sender is gone.
+        context.clear_progress_callback();
         drop(tx);
 
         if let Some(handle) = writer_task {
@@ -162,6 +218,11 @@ pub async fn download_stream(
             }
         }
 
+        waited.map_err(|e| {
+            let bytes = *total_bytes.lock().unwrap();
+            e.with_timeout_progress("download", Some(bytes), options.dataset_size)
+        })?;
+
         if let Some(file_handle) = file_handle {
             if let Some(ref mut file) = file_handle.lock().unwrap().as_mut() {
                 if let Err(e) = file.flush() {
@@ -186,6 +247,210 @@ pub async fn download_stream(
     .await?
 }
 
+/// Download content through a session, one chunk at a time as the limiters allow
+///
+/// `codex_download_stream` delivers content as fast as the node receives it,
+/// so rate limited downloads pull chunks instead. The size of the content is
+/// taken from its manifest.
+async fn download_throttled(
+    node: &CodexNode,
+    cid: &str,
+    options: DownloadStreamOptions,
+    throttle: Throttle,
+) -> Result<DownloadResult> {
+    if cid.is_empty() {
+        return Err(CodexError::invalid_parameter("cid", "CID cannot be empty"));
+    }
+    options.validate()?;
+
+    let start_time = std::time::Instant::now();
+    let deadline = deadline_after(options.timeout);
+    let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+
+    let DownloadStreamOptions {
+        filepath,
+        writer,
+        on_progress,
+        timeout,
+        verify,
+        ..
+    } = options;
+
+    let mut file = match filepath {
+        Some(ref path) => Some(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => None,
+    };
+    let mut writer = writer;
+
+    let mut received = 0;
+    let mut chunks = 0;
+    let pulled = async {
+        let total = fetch(node, cid).await?.dataset_size;
+
+        let mut download_options = DownloadOptions::new(cid)
+            .chunk_size(chunk_size)
+            .verify(verify);
+        download_options.timeout = timeout;
+        let mut session = DownloadSession::start(node, &download_options).await?;
+
+        while received < total {
+            throttle.acquire(chunk_size.min(total - received)).await;
+
+            let chunk = session.download_chunk().await?;
+            if chunk.is_empty() {
+                break;
+            }
+            let chunk = &chunk[..chunk.len().min(total - received)];
+
+            if let Some(ref mut file) = file {
+                file.write_all(chunk)?;
+            }
+            if let Some(ref mut writer) = writer {
+                writer.write_all(chunk)?;
+            }
+
+            received += chunk.len();
+            chunks += 1;
+            if let Some(ref callback) = on_progress {
+                callback(DownloadProgress::new(received, Some(total)));
+            }
+        }
+
+        let _ = session.cancel().await;
+
+        if received < total {
+            return Err(CodexError::download_error(format!(
+                "Download ended after {} of {} bytes",
+                received, total
+            )));
+        }
+
+        if let Some(ref mut file) = file {
+            file.flush()?;
+        }
+        if let Some(ref mut writer) = writer {
+            writer.flush()?;
+        }
+        Ok(())
+    };
+
+    run_until(deadline, pulled)
+        .await
+        .map_err(|e| e.with_timeout_progress("download", Some(received), None))?;
+
+    let mut result = DownloadResult::new(cid.to_string(), received)
+        .chunks(chunks)
+        .duration_ms(start_time.elapsed().as_millis() as u64)
+        .verified(verify);
+    if let Some(path) = filepath {
+        result = result.filepath(path);
+    }
+    Ok(result)
+}
+
+/// Download content, decompressing it if its manifest records a compression
+///
+/// The destination is wrapped in a decompressor handed to [`download_raw`] as
+/// its only writer, so the compressed form never reaches a file.
+async fn download_decompressed(
+    node: &CodexNode,
+    cid: &str,
+    mut options: DownloadStreamOptions,
+) -> Result<DownloadResult> {
+    if cid.is_empty() {
+        return Err(CodexError::invalid_parameter("cid", "CID cannot be empty"));
+    }
+    options.validate()?;
+
+    let manifest = run_until(deadline_after(options.timeout), fetch(node, cid)).await?;
+    let compression = Compression::from_manifest(&manifest.filename, &manifest.mimetype);
+    if !compression.is_enabled() {
+        return download_raw(node, cid, options).await;
+    }
+
+    let filepath = options.filepath.take();
+    let destination: Box<dyn Write + Send> = match (options.writer.take(), &filepath) {
+        (Some(writer), None) => writer,
+        (None, Some(path)) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        _ => {
+            return Err(CodexError::invalid_parameter(
+                "writer",
+                "Set either a file path or a writer for decompressed content",
+            ))
+        }
+    };
+
+    let state = Arc::new(Mutex::new(DecompressState {
+        writer: DecompressingWriter::new(destination)?,
+        error: None,
+    }));
+    let options = options.writer(SharedDecompressor(state.clone()));
+
+    let downloaded = match download_raw(node, cid, options).await {
+        Ok(result) => finish_decompressor(&state).map(|size| (result, size)),
+        Err(e) => Err(e),
+    };
+
+    match downloaded {
+        Ok((result, size)) => {
+            let mut result = DownloadResult::new(result.cid, size)
+                .duration_ms(result.duration_ms)
+                .verified(result.verified)
+                .compression(compression);
+            if let Some(path) = filepath {
+                result = result.filepath(path);
+            }
+            Ok(result)
+        }
+        Err(e) => {
+            if let Some(ref path) = filepath {
+                let _ = std::fs::remove_file(path);
+            }
+            Err(e)
+        }
+    }
+}
+
+struct DecompressState {
+    writer: DecompressingWriter<Box<dyn Write + Send>>,
+    error: Option<CodexError>,
+}
+
+/// Writer handed to [`download_raw`], keeping the first error for later
+///
+/// The download only logs writer errors, so they are recorded here and the
+/// rest of the content is ignored.
+struct SharedDecompressor(Arc<Mutex<DecompressState>>);
+
+impl Write for SharedDecompressor {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.0.lock().unwrap();
+        if state.error.is_none() {
+            if let Err(e) = state.writer.write_all(buf) {
+                state.error = Some(CodexError::download_error(format!(
+                    "Failed to decompress content: {}",
+                    e
+                )));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Finish the shared decompressor, returning the decompressed size
+fn finish_decompressor(state: &Mutex<DecompressState>) -> Result<usize> {
+    let mut state = state.lock().unwrap();
+    if let Some(e) = state.error.take() {
+        return Err(e);
+    }
+    state.writer.finish()?;
+    Ok(state.writer.bytes_written())
+}
+
 /// Download content directly to a file
 ///
 /// Convenience function that downloads content directly to a file.
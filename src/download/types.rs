@@ -1,6 +1,8 @@
 //! Types for download operations
 
 use crate::error::{CodexError, Result};
+use crate::throttle::RateLimiter;
+use crate::upload::compression::Compression;
 use serde::{Deserialize, Serialize};
 use std::io::Write;
 use std::path::PathBuf;
@@ -207,6 +209,10 @@ pub struct DownloadStreamOptions {
     pub timeout: Option<u64>,
     /// Whether to verify the download after completion
     pub verify: bool,
+    /// Whether to decompress content uploaded with compression
+    pub decompress: bool,
+    /// Limit on the rate at which content is received, on top of the node-wide limit
+    pub rate_limit: Option<RateLimiter>,
 }
 
 impl std::fmt::Debug for DownloadStreamOptions {
@@ -222,6 +228,8 @@ impl std::fmt::Debug for DownloadStreamOptions {
             .field("dataset_size_auto", &self.dataset_size_auto)
             .field("timeout", &self.timeout)
             .field("verify", &self.verify)
+            .field("decompress", &self.decompress)
+            .field("rate_limit", &self.rate_limit)
             .finish()
     }
 }
@@ -239,6 +247,8 @@ impl Clone for DownloadStreamOptions {
             dataset_size_auto: self.dataset_size_auto,
             timeout: self.timeout,
             verify: self.verify,
+            decompress: self.decompress,
+            rate_limit: self.rate_limit.clone(),
         }
     }
 }
@@ -257,6 +267,8 @@ impl DownloadStreamOptions {
             dataset_size_auto: true,
             timeout: Some(300), // 5 minutes default
             verify: true,
+            decompress: false,
+            rate_limit: None,
         }
     }
 
@@ -317,6 +329,26 @@ impl DownloadStreamOptions {
         self
     }
 
+    /// Set whether to decompress the content
+    ///
+    /// The manifest is fetched first; content recorded as compressed is
+    /// decompressed on its way to the destination, anything else is written
+    /// as it is.
+    pub fn decompress(mut self, decompress: bool) -> Self {
+        self.decompress = decompress;
+        self
+    }
+
+    /// Limit the rate at which this download receives content
+    ///
+    /// The limiter may be shared with other transfers and its rate changed
+    /// while they run. The node-wide [`CodexNode::download_limiter`](crate::CodexNode::download_limiter)
+    /// applies as well.
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limit = Some(limiter);
+        self
+    }
+
     /// Validate the download stream options
     pub fn validate(&self) -> Result<()> {
         if self.cid.is_empty() {
@@ -352,6 +384,133 @@ impl DownloadStreamOptions {
     }
 }
 
+/// Options for [`download_reader`](crate::download::download_reader)
+pub struct DownloadReaderOptions {
+    /// Chunk size requested from the node (in bytes)
+    pub chunk_size: Option<usize>,
+    /// Number of chunks fetched ahead of the consumer
+    pub buffer_chunks: usize,
+    /// Progress callback function, called as chunks are fetched
+    pub on_progress: Option<Box<dyn Fn(DownloadProgress) + Send + Sync>>,
+    /// Timeout for the whole download (in seconds)
+    pub timeout: Option<u64>,
+    /// Whether to verify the download
+    pub verify: bool,
+    /// Limit on the rate at which content is received, on top of the node-wide limit
+    pub rate_limit: Option<RateLimiter>,
+}
+
+impl std::fmt::Debug for DownloadReaderOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DownloadReaderOptions")
+            .field("chunk_size", &self.chunk_size)
+            .field("buffer_chunks", &self.buffer_chunks)
+            .field("on_progress", &self.on_progress.is_some())
+            .field("timeout", &self.timeout)
+            .field("verify", &self.verify)
+            .field("rate_limit", &self.rate_limit)
+            .finish()
+    }
+}
+
+impl Default for DownloadReaderOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DownloadReaderOptions {
+    /// Create new download reader options
+    ///
+    /// There is no timeout by default, since a reader lives as long as its
+    /// consumer takes to read it.
+    pub fn new() -> Self {
+        Self {
+            chunk_size: Some(1024 * 1024), // 1 MB default
+            buffer_chunks: 4,
+            on_progress: None,
+            timeout: None,
+            verify: true,
+            rate_limit: None,
+        }
+    }
+
+    /// Set the chunk size
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = Some(size);
+        self
+    }
+
+    /// Set how many chunks may be fetched ahead of the consumer
+    ///
+    /// At most this many chunks wait in memory; once they are all there,
+    /// fetching pauses until the consumer reads.
+    pub fn buffer_chunks(mut self, chunks: usize) -> Self {
+        self.buffer_chunks = chunks;
+        self
+    }
+
+    /// Set the progress callback
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(DownloadProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Set the timeout
+    pub fn timeout(mut self, timeout: u64) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set whether to verify the download
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Limit the rate at which this download receives content
+    ///
+    /// The node-wide [`CodexNode::download_limiter`](crate::CodexNode::download_limiter)
+    /// applies as well.
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limit = Some(limiter);
+        self
+    }
+
+    /// Validate the download reader options
+    pub fn validate(&self) -> Result<()> {
+        if let Some(chunk_size) = self.chunk_size {
+            if chunk_size == 0 {
+                return Err(CodexError::invalid_parameter(
+                    "chunk_size",
+                    "Chunk size must be greater than 0",
+                ));
+            }
+        }
+
+        if self.buffer_chunks == 0 {
+            return Err(CodexError::invalid_parameter(
+                "buffer_chunks",
+                "Buffer must hold at least one chunk",
+            ));
+        }
+
+        if let Some(timeout) = self.timeout {
+            if timeout == 0 {
+                return Err(CodexError::invalid_parameter(
+                    "timeout",
+                    "Timeout must be greater than 0",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// Result of a download operation
 #[derive(Debug, Clone)]
 pub struct DownloadResult {
@@ -367,6 +526,8 @@ pub struct DownloadResult {
     pub verified: bool,
     /// Path where the file was saved (if applicable)
     pub filepath: Option<PathBuf>,
+    /// Compression removed from the content; `size` is the decompressed size
+    pub compression: Compression,
 }
 
 impl DownloadResult {
@@ -379,6 +540,7 @@ impl DownloadResult {
             duration_ms: 0,
             verified: false,
             filepath: None,
+            compression: Compression::None,
         }
     }
 
@@ -405,6 +567,12 @@ impl DownloadResult {
         self.filepath = Some(path.into());
         self
     }
+
+    /// Set the compression removed from the content
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
 }
 
 /// Manifest information for a stored content
@@ -512,6 +680,23 @@ mod tests {
         assert!(options.validate().is_err());
     }
 
+    #[test]
+    fn test_download_reader_options_validation() {
+        let options = DownloadReaderOptions::new();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.timeout, None);
+
+        assert!(DownloadReaderOptions::new()
+            .buffer_chunks(0)
+            .validate()
+            .is_err());
+        assert!(DownloadReaderOptions::new()
+            .chunk_size(0)
+            .validate()
+            .is_err());
+        assert!(DownloadReaderOptions::new().timeout(0).validate().is_err());
+    }
+
     #[test]
     fn test_download_result() {
         let result = DownloadResult::new("QmExample".to_string(), 1024)
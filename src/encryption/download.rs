@@ -0,0 +1,221 @@
+//! Encrypted downloads
+
+use crate::download::{download_stream, DownloadResult, DownloadStreamOptions};
+use crate::encryption::format::{EncryptionKey, Opener};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// Verifies and decrypts content sealed by
+/// [`EncryptedUpload`](crate::encryption::EncryptedUpload)
+///
+/// Holds every key content may have been sealed with; the key ID in the
+/// header of the content picks the one used. Each segment is authenticated
+/// before its plaintext is released, so only verified data ever reaches the
+/// destination.
+#[derive(Debug, Clone)]
+pub struct EncryptedDownload {
+    keys: Vec<EncryptionKey>,
+}
+
+impl EncryptedDownload {
+    /// Create an encrypted download opening content sealed with `key`
+    pub fn new(key: EncryptionKey) -> Self {
+        Self { keys: vec![key] }
+    }
+
+    /// Also accept content sealed with `key`
+    pub fn key(mut self, key: EncryptionKey) -> Self {
+        self.keys.push(key);
+        self
+    }
+
+    /// Wrap `writer` so that encrypted content written to it arrives decrypted
+    ///
+    /// Call [`OpeningWriter::finish`] once all content has been written;
+    /// otherwise truncation goes unnoticed.
+    pub fn writer<W: Write>(&self, writer: W) -> OpeningWriter<W> {
+        OpeningWriter {
+            inner: writer,
+            opener: Opener::new(self.keys.clone()),
+            plaintext: Vec::new(),
+            bytes_written: 0,
+        }
+    }
+
+    /// Download, verify and decrypt content
+    ///
+    /// The plaintext goes to `options.filepath` or `options.writer`, or is
+    /// discarded after verification if neither is set. A file left behind by
+    /// a failed download is removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The Codex node to download from
+    /// * `cid` - CID of the encrypted content
+    /// * `options` - Download options; progress reports encrypted bytes
+    ///
+    /// # Returns
+    ///
+    /// A `DownloadResult` whose size is the size of the plaintext
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Both a file path and a writer are set
+    /// - The content is not encrypted, or none of the keys has its key ID
+    /// - Any segment fails authentication or the content is truncated
+    /// - The download fails for any reason
+    pub async fn download_stream(
+        &self,
+        node: &CodexNode,
+        cid: &str,
+        mut options: DownloadStreamOptions,
+    ) -> Result<DownloadResult> {
+        let filepath = options.filepath.take();
+        let destination: Box<dyn Write + Send> = match (options.writer.take(), &filepath) {
+            (Some(_), Some(_)) => {
+                return Err(CodexError::invalid_parameter(
+                    "writer",
+                    "Set either a file path or a writer for the plaintext",
+                ))
+            }
+            (Some(writer), None) => writer,
+            (None, Some(path)) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+            (None, None) => Box::new(std::io::sink()),
+        };
+
+        let state = Arc::new(Mutex::new(SharedState {
+            writer: Some(self.writer(destination)),
+            error: None,
+        }));
+        let options = options.writer(SharedWriter(state.clone()));
+
+        let opened = match download_stream(node, cid, options).await {
+            Ok(result) => finish_shared(&state).map(|size| (result, size)),
+            Err(e) => Err(e),
+        };
+
+        match opened {
+            Ok((result, size)) => {
+                let mut result = DownloadResult::new(result.cid, size)
+                    .duration_ms(result.duration_ms)
+                    .verified(true);
+                if let Some(path) = filepath {
+                    result = result.filepath(path);
+                }
+                Ok(result)
+            }
+            Err(e) => {
+                if let Some(ref path) = filepath {
+                    let _ = std::fs::remove_file(path);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Writer decrypting the encrypted content written to it
+///
+/// Created by [`EncryptedDownload::writer`].
+pub struct OpeningWriter<W> {
+    inner: W,
+    opener: Opener,
+    plaintext: Vec<u8>,
+    bytes_written: usize,
+}
+
+impl<W: Write> OpeningWriter<W> {
+    fn push(&mut self, data: &[u8]) -> Result<()> {
+        self.opener.push(data, &mut self.plaintext)?;
+        self.drain()
+    }
+
+    fn drain(&mut self) -> Result<()> {
+        self.inner.write_all(&self.plaintext)?;
+        self.bytes_written += self.plaintext.len();
+        self.plaintext.clear();
+        Ok(())
+    }
+
+    /// Number of plaintext bytes written so far
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    /// Open the last segment and return the inner writer
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the content is truncated or the last segment fails
+    /// authentication
+    pub fn finish(mut self) -> Result<W> {
+        self.close()?;
+        Ok(self.inner)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.opener.finish(&mut self.plaintext)?;
+        self.drain()?;
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for OpeningWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.push(buf).map_err(|e| match e {
+            CodexError::Io(e) => e,
+            e => std::io::Error::new(std::io::ErrorKind::InvalidData, e),
+        })?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+struct SharedState {
+    writer: Option<OpeningWriter<Box<dyn Write + Send>>>,
+    error: Option<CodexError>,
+}
+
+/// Writer handed to `download_stream`, keeping the first error for later
+///
+/// The download only logs writer errors, so they are recorded here and the
+/// rest of the content is ignored.
+struct SharedWriter(Arc<Mutex<SharedState>>);
+
+impl Write for SharedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut state = self.0.lock().unwrap();
+        if state.error.is_none() {
+            if let Some(Err(e)) = state.writer.as_mut().map(|writer| writer.push(buf)) {
+                state.error = Some(e);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Finish the shared writer, returning the plaintext size
+fn finish_shared(state: &Mutex<SharedState>) -> Result<usize> {
+    let mut state = state.lock().unwrap();
+    if let Some(e) = state.error.take() {
+        return Err(e);
+    }
+
+    let mut writer = state
+        .writer
+        .take()
+        .ok_or_else(|| CodexError::encryption_error("Content was already opened"))?;
+    writer.close()?;
+    Ok(writer.bytes_written())
+}
@@ -0,0 +1,486 @@
+//! Encrypted content format
+//!
+//! Encrypted content starts with a header followed by the sealed segments:
+//!
+//! | Field          | Size        | Content                                  |
+//! |----------------|-------------|------------------------------------------|
+//! | magic          | 6           | `CDXENC`                                 |
+//! | version        | 1           | [`FORMAT_VERSION`]                       |
+//! | algorithm      | 1           | 1 = XChaCha20-Poly1305, STREAM BE32      |
+//! | segment size   | 4           | plaintext bytes per segment, big-endian  |
+//! | key ID length  | 1           | length of the key ID in bytes            |
+//! | key ID         | 0 to 255    | UTF-8 identifier of the key              |
+//! | nonce prefix   | 19          | random, drawn for every file             |
+//!
+//! The plaintext is cut into segments of the recorded size; only the last one
+//! may be shorter, and it may be empty. Each segment is sealed with the STREAM
+//! construction, which ties it to its position and marks the last segment, so
+//! reordered, dropped or truncated segments fail to open. Every segment
+//! carries the header as associated data and adds a 16-byte tag.
+
+use crate::error::{CodexError, Result};
+use chacha20poly1305::aead::stream::{DecryptorBE32, EncryptorBE32};
+use chacha20poly1305::aead::{rand_core::RngCore, KeyInit, OsRng};
+use chacha20poly1305::XChaCha20Poly1305;
+
+/// Version of the header written by this crate
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Plaintext bytes per segment when none is given
+pub const DEFAULT_SEGMENT_SIZE: usize = 64 * 1024;
+
+/// Largest segment size written or accepted
+///
+/// A segment is buffered whole before it is opened, so the header of
+/// untrusted content must not be able to request gigabytes of memory.
+pub const MAX_SEGMENT_SIZE: usize = 16 * 1024 * 1024;
+
+const MAGIC: &[u8; 6] = b"CDXENC";
+const ALGORITHM_XCHACHA20_POLY1305: u8 = 1;
+const NONCE_PREFIX_LEN: usize = 19;
+pub(crate) const TAG_LEN: usize = 16;
+const FIXED_HEADER_LEN: usize = MAGIC.len() + 1 + 1 + 4 + 1;
+
+type NoncePrefix = [u8; NONCE_PREFIX_LEN];
+
+/// A 256-bit key and the ID recorded with content sealed by it
+#[derive(Clone)]
+pub struct EncryptionKey {
+    id: String,
+    key: [u8; 32],
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+impl EncryptionKey {
+    /// Create a key from raw bytes
+    pub fn new(id: impl Into<String>, key: [u8; 32]) -> Self {
+        Self { id: id.into(), key }
+    }
+
+    /// Generate a random key
+    pub fn generate(id: impl Into<String>) -> Self {
+        let mut key = [0u8; 32];
+        OsRng.fill_bytes(&mut key);
+        Self::new(id, key)
+    }
+
+    /// ID recorded in the header of content sealed with this key
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Raw key bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(&self.key.into())
+    }
+}
+
+/// Header of a piece of encrypted content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionHeader {
+    pub version: u8,
+    pub key_id: String,
+    pub segment_size: usize,
+    nonce_prefix: NoncePrefix,
+}
+
+impl EncryptionHeader {
+    fn new(key_id: &str, segment_size: usize) -> Result<Self> {
+        if key_id.len() > u8::MAX as usize {
+            return Err(CodexError::invalid_parameter(
+                "key_id",
+                "Key ID must be at most 255 bytes",
+            ));
+        }
+
+        if segment_size == 0 || segment_size > MAX_SEGMENT_SIZE {
+            return Err(CodexError::invalid_parameter(
+                "segment_size",
+                "Segment size must be between 1 byte and 16 MiB",
+            ));
+        }
+
+        let mut nonce_prefix = [0u8; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut nonce_prefix);
+
+        Ok(Self {
+            version: FORMAT_VERSION,
+            key_id: key_id.to_string(),
+            segment_size,
+            nonce_prefix,
+        })
+    }
+
+    /// Encoded header
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.encoded_len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(self.version);
+        bytes.push(ALGORITHM_XCHACHA20_POLY1305);
+        bytes.extend_from_slice(&(self.segment_size as u32).to_be_bytes());
+        bytes.push(self.key_id.len() as u8);
+        bytes.extend_from_slice(self.key_id.as_bytes());
+        bytes.extend_from_slice(&self.nonce_prefix);
+        bytes
+    }
+
+    /// Parse a header from the start of `bytes`
+    ///
+    /// Returns `None` while `bytes` is too short to hold the whole header.
+    pub fn parse(bytes: &[u8]) -> Result<Option<Self>> {
+        let invalid = |message: &str| Err(CodexError::encryption_error(message));
+
+        if bytes.len() < FIXED_HEADER_LEN {
+            return if MAGIC.starts_with(&bytes[..bytes.len().min(MAGIC.len())]) {
+                Ok(None)
+            } else {
+                invalid("Content is not encrypted")
+            };
+        }
+
+        if &bytes[..MAGIC.len()] != MAGIC {
+            return invalid("Content is not encrypted");
+        }
+
+        let version = bytes[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(CodexError::encryption_error(format!(
+                "Unsupported format version {}",
+                version
+            )));
+        }
+
+        let algorithm = bytes[MAGIC.len() + 1];
+        if algorithm != ALGORITHM_XCHACHA20_POLY1305 {
+            return Err(CodexError::encryption_error(format!(
+                "Unsupported algorithm {}",
+                algorithm
+            )));
+        }
+
+        let size_at = MAGIC.len() + 2;
+        let segment_size =
+            u32::from_be_bytes(bytes[size_at..size_at + 4].try_into().unwrap()) as usize;
+        if segment_size == 0 {
+            return invalid("Segment size is 0");
+        }
+        if segment_size > MAX_SEGMENT_SIZE {
+            return Err(CodexError::encryption_error(format!(
+                "Segment size {} exceeds the maximum of {}",
+                segment_size, MAX_SEGMENT_SIZE
+            )));
+        }
+
+        let key_id_len = bytes[FIXED_HEADER_LEN - 1] as usize;
+        let total_len = FIXED_HEADER_LEN + key_id_len + NONCE_PREFIX_LEN;
+        if bytes.len() < total_len {
+            return Ok(None);
+        }
+
+        let key_id = std::str::from_utf8(&bytes[FIXED_HEADER_LEN..FIXED_HEADER_LEN + key_id_len])
+            .map_err(|_| CodexError::encryption_error("Key ID is not valid UTF-8"))?;
+        let nonce_prefix = bytes[total_len - NONCE_PREFIX_LEN..total_len]
+            .try_into()
+            .unwrap();
+
+        Ok(Some(Self {
+            version,
+            key_id: key_id.to_string(),
+            segment_size,
+            nonce_prefix,
+        }))
+    }
+
+    /// Length of the encoded header
+    pub fn encoded_len(&self) -> usize {
+        FIXED_HEADER_LEN + self.key_id.len() + NONCE_PREFIX_LEN
+    }
+}
+
+/// Size of the encrypted form of `plaintext_len` bytes
+///
+/// Empty content still takes one (empty) segment.
+pub fn encrypted_len(plaintext_len: usize, key_id: &str, segment_size: usize) -> usize {
+    let segments = plaintext_len.div_ceil(segment_size).max(1);
+    FIXED_HEADER_LEN + key_id.len() + NONCE_PREFIX_LEN + plaintext_len + segments * TAG_LEN
+}
+
+/// Seals plaintext segment by segment
+pub(crate) struct Sealer {
+    header: Vec<u8>,
+    segment_size: usize,
+    encryptor: Option<EncryptorBE32<XChaCha20Poly1305>>,
+}
+
+impl Sealer {
+    pub fn new(key: &EncryptionKey, segment_size: usize) -> Result<Self> {
+        let header = EncryptionHeader::new(key.id(), segment_size)?;
+        let encryptor = EncryptorBE32::from_aead(key.cipher(), header.nonce_prefix.as_ref().into());
+
+        Ok(Self {
+            header: header.to_bytes(),
+            segment_size,
+            encryptor: Some(encryptor),
+        })
+    }
+
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    pub fn segment_size(&self) -> usize {
+        self.segment_size
+    }
+
+    /// Seal `segment` in place; `last` must be set for the final segment only
+    pub fn seal(&mut self, segment: &mut Vec<u8>, last: bool) -> Result<()> {
+        let sealed = match (last, self.encryptor.take()) {
+            (false, Some(mut encryptor)) => {
+                let sealed = encryptor.encrypt_next_in_place(&self.header, segment);
+                self.encryptor = Some(encryptor);
+                sealed
+            }
+            (true, Some(encryptor)) => encryptor.encrypt_last_in_place(&self.header, segment),
+            (_, None) => return Err(CodexError::encryption_error("Stream is already sealed")),
+        };
+
+        sealed.map_err(|_| CodexError::encryption_error("Too many segments"))
+    }
+}
+
+/// Opens sealed content pushed to it in pieces of any size
+pub(crate) struct Opener {
+    keys: Vec<EncryptionKey>,
+    buffer: Vec<u8>,
+    state: OpenerState,
+}
+
+enum OpenerState {
+    Header,
+    Segments {
+        header: Vec<u8>,
+        segment_size: usize,
+        decryptor: DecryptorBE32<XChaCha20Poly1305>,
+    },
+    Done,
+}
+
+impl Opener {
+    pub fn new(keys: Vec<EncryptionKey>) -> Self {
+        Self {
+            keys,
+            buffer: Vec::new(),
+            state: OpenerState::Header,
+        }
+    }
+
+    /// Accept more sealed bytes, returning the plaintext of every segment completed
+    ///
+    /// A segment is only opened once the bytes after it have arrived, since
+    /// the last segment is opened differently.
+    pub fn push(&mut self, data: &[u8], plaintext: &mut Vec<u8>) -> Result<()> {
+        self.buffer.extend_from_slice(data);
+
+        if let OpenerState::Header = self.state {
+            let header = match EncryptionHeader::parse(&self.buffer)? {
+                Some(header) => header,
+                None => return Ok(()),
+            };
+
+            let key = self
+                .keys
+                .iter()
+                .find(|key| key.id() == header.key_id)
+                .ok_or_else(|| {
+                    CodexError::encryption_error(format!("No key with ID '{}'", header.key_id))
+                })?;
+
+            let decryptor =
+                DecryptorBE32::from_aead(key.cipher(), header.nonce_prefix.as_ref().into());
+            let encoded = self.buffer.drain(..header.encoded_len()).collect();
+
+            self.state = OpenerState::Segments {
+                header: encoded,
+                segment_size: header.segment_size,
+                decryptor,
+            };
+        }
+
+        if let OpenerState::Segments {
+            ref header,
+            segment_size,
+            ref mut decryptor,
+        } = self.state
+        {
+            let sealed_size = segment_size + TAG_LEN;
+            while self.buffer.len() > sealed_size {
+                let mut segment: Vec<u8> = self.buffer.drain(..sealed_size).collect();
+                decryptor
+                    .decrypt_next_in_place(header, &mut segment)
+                    .map_err(|_| CodexError::encryption_error("Segment failed authentication"))?;
+                plaintext.extend_from_slice(&segment);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Open the last segment once all sealed bytes have been pushed
+    pub fn finish(&mut self, plaintext: &mut Vec<u8>) -> Result<()> {
+        match std::mem::replace(&mut self.state, OpenerState::Done) {
+            OpenerState::Header => Err(CodexError::encryption_error(
+                "Content ended inside the encryption header",
+            )),
+            OpenerState::Segments {
+                header, decryptor, ..
+            } => {
+                if self.buffer.len() < TAG_LEN {
+                    return Err(CodexError::encryption_error("Content is truncated"));
+                }
+
+                let mut segment = std::mem::take(&mut self.buffer);
+                decryptor
+                    .decrypt_last_in_place(&header, &mut segment)
+                    .map_err(|_| {
+                        CodexError::encryption_error(
+                            "Last segment failed authentication; content may be truncated",
+                        )
+                    })?;
+                plaintext.extend_from_slice(&segment);
+                Ok(())
+            }
+            OpenerState::Done => Err(CodexError::encryption_error("Content was already opened")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seal_all(key: &EncryptionKey, segment_size: usize, data: &[u8]) -> Vec<u8> {
+        let mut sealer = Sealer::new(key, segment_size).unwrap();
+        let mut out = sealer.header().to_vec();
+        let mut segments = data.chunks(segment_size).peekable();
+
+        if segments.peek().is_none() {
+            let mut empty = Vec::new();
+            sealer.seal(&mut empty, true).unwrap();
+            out.extend_from_slice(&empty);
+        }
+
+        while let Some(segment) = segments.next() {
+            let mut segment = segment.to_vec();
+            sealer
+                .seal(&mut segment, segments.peek().is_none())
+                .unwrap();
+            out.extend_from_slice(&segment);
+        }
+        out
+    }
+
+    fn open_all(keys: Vec<EncryptionKey>, sealed: &[u8], piece: usize) -> Result<Vec<u8>> {
+        let mut opener = Opener::new(keys);
+        let mut plaintext = Vec::new();
+        for part in sealed.chunks(piece) {
+            opener.push(part, &mut plaintext)?;
+        }
+        opener.finish(&mut plaintext)?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_header_roundtrip() {
+        let header = EncryptionHeader::new("team-key", 4096).unwrap();
+        let bytes = header.to_bytes();
+        assert_eq!(bytes.len(), header.encoded_len());
+        assert_eq!(&bytes[..6], b"CDXENC");
+
+        assert_eq!(EncryptionHeader::parse(&bytes[..10]).unwrap(), None);
+        assert_eq!(EncryptionHeader::parse(&bytes).unwrap(), Some(header));
+        assert!(EncryptionHeader::parse(b"plain text").is_err());
+        assert!(EncryptionHeader::new(&"k".repeat(256), 4096).is_err());
+        assert!(EncryptionHeader::new("k", 0).is_err());
+        assert!(EncryptionHeader::new("k", MAX_SEGMENT_SIZE).is_ok());
+        assert!(EncryptionHeader::new("k", MAX_SEGMENT_SIZE + 1).is_err());
+
+        let mut oversized = bytes.clone();
+        oversized[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(EncryptionHeader::parse(&oversized).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let key = EncryptionKey::generate("k1");
+        for len in [0, 1, 99, 100, 101, 1000] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let sealed = seal_all(&key, 100, &data);
+            assert_eq!(sealed.len(), encrypted_len(len, "k1", 100));
+
+            for piece in [1, 7, 116, sealed.len()] {
+                let opened = open_all(vec![key.clone()], &sealed, piece).unwrap();
+                assert_eq!(opened, data, "{} bytes in pieces of {}", len, piece);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let key = EncryptionKey::generate("k1");
+        let data = vec![7u8; 350];
+        let sealed = seal_all(&key, 100, &data);
+        let header_len = FIXED_HEADER_LEN + "k1".len() + NONCE_PREFIX_LEN;
+
+        let mut flipped = sealed.clone();
+        flipped[header_len + 5] ^= 1;
+        assert!(open_all(vec![key.clone()], &flipped, 64).is_err());
+
+        // Dropping the last segment leaves a full segment that was not sealed as last
+        let truncated = &sealed[..sealed.len() - (50 + TAG_LEN)];
+        assert!(open_all(vec![key.clone()], truncated, 64).is_err());
+
+        let mut swapped = sealed.clone();
+        let segment = 100 + TAG_LEN;
+        let (first, second) = (header_len, header_len + segment);
+        let copy = swapped[first..first + segment].to_vec();
+        swapped.copy_within(second..second + segment, first);
+        swapped[second..second + segment].copy_from_slice(&copy);
+        assert!(open_all(vec![key.clone()], &swapped, 64).is_err());
+
+        let mut relabeled = sealed.clone();
+        relabeled[FIXED_HEADER_LEN] = b'x';
+        let other = EncryptionKey::new("x1", *key.as_bytes());
+        assert!(open_all(vec![other], &relabeled, 64).is_err());
+    }
+
+    #[test]
+    fn test_key_selection() {
+        let first = EncryptionKey::generate("first");
+        let second = EncryptionKey::generate("second");
+        let sealed = seal_all(&second, 64, b"secret");
+
+        let opened = open_all(vec![first.clone(), second], &sealed, 16).unwrap();
+        assert_eq!(opened, b"secret");
+        assert!(open_all(vec![first], &sealed, 16).is_err());
+    }
+
+    #[test]
+    fn test_key_debug_is_redacted() {
+        let key = EncryptionKey::new("k1", [42u8; 32]);
+        let debug = format!("{:?}", key);
+        assert!(debug.contains("k1"));
+        assert!(!debug.contains("42"));
+    }
+}
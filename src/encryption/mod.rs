@@ -0,0 +1,27 @@
+//! Client-side encryption for Codex content
+//!
+//! Content stored in Codex can be read by anyone who knows its CID. With the
+//! `encryption` feature enabled, this module seals content with
+//! XChaCha20-Poly1305 before it leaves the process and opens it again after
+//! download. Content is processed one segment at a time in both directions;
+//! see [`format`] for the layout of encrypted content.
+//!
+//! ## Operations
+//!
+//! - [`upload::EncryptedUpload`] - Encrypt readers and files while uploading them
+//! - [`download::EncryptedDownload`] - Verify and decrypt content while downloading it
+//!
+//! ## Keys
+//!
+//! - [`format::EncryptionKey`] - A 256-bit key and the ID recorded with content sealed by it
+
+pub mod download;
+pub mod format;
+pub mod upload;
+
+// Re-export types
+pub use format::{EncryptionHeader, EncryptionKey};
+
+// Re-export operations
+pub use download::{EncryptedDownload, OpeningWriter};
+pub use upload::{EncryptedUpload, SealingReader};
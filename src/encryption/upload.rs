@@ -0,0 +1,226 @@
+//! Encrypted uploads
+
+use crate::encryption::format::{EncryptionKey, Sealer, DEFAULT_SEGMENT_SIZE, TAG_LEN};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::upload::{upload_reader, UploadOptions, UploadResult};
+use std::io::Read;
+
+/// Encrypts content on its way to the node
+///
+/// Content is read, sealed and uploaded one segment at a time, so it is never
+/// held in memory as a whole. The CID and size in the result refer to the
+/// encrypted content; download it with
+/// [`EncryptedDownload`](crate::encryption::EncryptedDownload).
+///
+/// Only the content is encrypted. A filename or mimetype set in the options
+/// is recorded in the manifest as given.
+#[derive(Debug, Clone)]
+pub struct EncryptedUpload {
+    key: EncryptionKey,
+    segment_size: usize,
+}
+
+impl EncryptedUpload {
+    /// Create an encrypted upload sealing content with `key`
+    pub fn new(key: EncryptionKey) -> Self {
+        Self {
+            key,
+            segment_size: DEFAULT_SEGMENT_SIZE,
+        }
+    }
+
+    /// Set the number of plaintext bytes sealed together
+    ///
+    /// At most [`MAX_SEGMENT_SIZE`](crate::encryption::format::MAX_SEGMENT_SIZE).
+    pub fn segment_size(mut self, size: usize) -> Self {
+        self.segment_size = size;
+        self
+    }
+
+    /// Wrap `reader` so that it yields the encrypted form of its content
+    ///
+    /// Useful to feed encrypted content to [`UploadWriter`](crate::upload::UploadWriter)
+    /// or [`upload_chunk`](crate::upload::upload_chunk) directly.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key ID is longer than 255 bytes or the segment
+    /// size is not between 1 byte and
+    /// [`MAX_SEGMENT_SIZE`](crate::encryption::format::MAX_SEGMENT_SIZE).
+    pub fn reader<R: Read>(&self, reader: R) -> Result<SealingReader<R>> {
+        Ok(SealingReader::new(
+            reader,
+            Sealer::new(&self.key, self.segment_size)?,
+        ))
+    }
+
+    /// Encrypt and upload data from any Read implementation
+    ///
+    /// See [`upload_reader`] for the options that apply.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The Codex node to use for the upload
+    /// * `options` - Upload options including chunk size and progress callbacks
+    /// * `reader` - The plaintext to upload
+    ///
+    /// # Returns
+    ///
+    /// An `UploadResult` for the encrypted content
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key or segment size is invalid, or if the
+    /// upload fails for any reason
+    pub async fn upload_reader<R>(
+        &self,
+        node: &CodexNode,
+        options: UploadOptions,
+        reader: R,
+    ) -> Result<UploadResult>
+    where
+        R: Read + Send + 'static,
+    {
+        let reader = self.reader(reader)?;
+        upload_reader(node, options, reader).await
+    }
+
+    /// Encrypt and upload the file at `options.filepath`
+    ///
+    /// The file is always read in process and sent in chunks. Its name is not
+    /// recorded unless [`UploadOptions::filename`] is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - No file path is specified in options
+    /// - The file cannot be opened
+    /// - The upload fails for any reason
+    pub async fn upload_file(
+        &self,
+        node: &CodexNode,
+        mut options: UploadOptions,
+    ) -> Result<UploadResult> {
+        let filepath = options.filepath.take().ok_or_else(|| {
+            CodexError::invalid_parameter("filepath", "File path must be specified for file upload")
+        })?;
+
+        let file = std::fs::File::open(&filepath).map_err(|e| {
+            CodexError::invalid_parameter(
+                "filepath",
+                format!("Cannot open {}: {}", filepath.display(), e),
+            )
+        })?;
+
+        self.upload_reader(node, options, file).await
+    }
+}
+
+/// Reader yielding the encrypted form of another reader's content
+///
+/// Created by [`EncryptedUpload::reader`].
+pub struct SealingReader<R> {
+    inner: R,
+    sealer: Sealer,
+    output: Vec<u8>,
+    position: usize,
+    carry: Option<u8>,
+    finished: bool,
+}
+
+impl<R: Read> SealingReader<R> {
+    fn new(inner: R, sealer: Sealer) -> Self {
+        let output = sealer.header().to_vec();
+        Self {
+            inner,
+            sealer,
+            output,
+            position: 0,
+            carry: None,
+            finished: false,
+        }
+    }
+
+    /// Seal the next segment into `output`
+    ///
+    /// One byte past the segment is read to find out whether it is the last.
+    fn seal_next(&mut self) -> std::io::Result<()> {
+        let segment_size = self.sealer.segment_size();
+        let mut segment = std::mem::take(&mut self.output);
+        segment.clear();
+        segment.reserve(segment_size + TAG_LEN + 1);
+        segment.extend(self.carry.take());
+
+        let wanted = (segment_size + 1 - segment.len()) as u64;
+        self.inner.by_ref().take(wanted).read_to_end(&mut segment)?;
+
+        let last = segment.len() <= segment_size;
+        if !last {
+            self.carry = segment.pop();
+        }
+
+        self.sealer
+            .seal(&mut segment, last)
+            .map_err(std::io::Error::other)?;
+
+        self.finished = last;
+        self.output = segment;
+        self.position = 0;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for SealingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while self.position == self.output.len() {
+            if self.finished {
+                return Ok(0);
+            }
+            self.seal_next()?;
+        }
+
+        let n = buf.len().min(self.output.len() - self.position);
+        buf[..n].copy_from_slice(&self.output[self.position..self.position + n]);
+        self.position += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::format::encrypted_len;
+    use crate::encryption::EncryptedDownload;
+    use std::io::{Cursor, Write};
+
+    #[test]
+    fn test_sealing_reader_roundtrip() {
+        let key = EncryptionKey::generate("docs");
+        let upload = EncryptedUpload::new(key.clone()).segment_size(1000);
+
+        for len in [0, 1, 999, 1000, 1001, 5000] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+
+            let mut sealed = Vec::new();
+            upload
+                .reader(Cursor::new(&data))
+                .unwrap()
+                .read_to_end(&mut sealed)
+                .unwrap();
+            assert_eq!(sealed.len(), encrypted_len(len, "docs", 1000));
+
+            let mut writer = EncryptedDownload::new(key.clone()).writer(Vec::new());
+            for piece in sealed.chunks(333) {
+                writer.write_all(piece).unwrap();
+            }
+            assert_eq!(writer.finish().unwrap(), data, "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_invalid_segment_size() {
+        let upload = EncryptedUpload::new(EncryptionKey::generate("k")).segment_size(0);
+        assert!(upload.reader(Cursor::new(b"data")).is_err());
+    }
+}
@@ -28,8 +28,23 @@ pub enum CodexError {
     #[error("Invalid parameter: {parameter} - {message}")]
     InvalidParameter { parameter: String, message: String },
 
-    #[error("Operation timed out: {operation}")]
-    Timeout { operation: String },
+    #[error("Upload verification failed for {cid}: {failure}")]
+    VerificationFailed {
+        cid: String,
+        failure: VerificationFailure,
+    },
+
+    #[error("Encryption error: {message}")]
+    EncryptionError { message: String },
+
+    #[error("Operation timed out: {operation}{}", progress_suffix(.bytes_transferred, .total_bytes))]
+    Timeout {
+        operation: String,
+        /// Bytes transferred before the deadline, if the operation tracks them
+        bytes_transferred: Option<usize>,
+        /// Total size of the transfer, if known
+        total_bytes: Option<usize>,
+    },
 
     #[error("Operation cancelled: {operation}")]
     Cancelled { operation: String },
@@ -50,6 +65,22 @@ pub enum CodexError {
     JoinError(#[from] tokio::task::JoinError),
 }
 
+/// What [`CodexError::VerificationFailed`] found wrong with an upload
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerificationFailure {
+    #[error("content is not stored on the node")]
+    Missing,
+
+    #[error("dataset size is {actual} bytes, expected {expected}")]
+    SizeMismatch { expected: usize, actual: usize },
+
+    #[error("block size is {actual} bytes, expected {expected}")]
+    BlockSizeMismatch { expected: usize, actual: usize },
+
+    #[error("stored content does not match the uploaded data")]
+    ContentMismatch,
+}
+
 impl CodexError {
     pub fn library_error(message: impl Into<String>) -> Self {
         CodexError::LibraryError {
@@ -102,9 +133,55 @@ impl CodexError {
         }
     }
 
+    pub fn verification_failed(cid: impl Into<String>, failure: VerificationFailure) -> Self {
+        CodexError::VerificationFailed {
+            cid: cid.into(),
+            failure,
+        }
+    }
+
+    pub fn encryption_error(message: impl Into<String>) -> Self {
+        CodexError::EncryptionError {
+            message: message.into(),
+        }
+    }
+
     pub fn timeout(operation: impl Into<String>) -> Self {
         CodexError::Timeout {
             operation: operation.into(),
+            bytes_transferred: None,
+            total_bytes: None,
+        }
+    }
+
+    pub fn timeout_with_progress(
+        operation: impl Into<String>,
+        bytes_transferred: usize,
+        total_bytes: Option<usize>,
+    ) -> Self {
+        CodexError::Timeout {
+            operation: operation.into(),
+            bytes_transferred: Some(bytes_transferred),
+            total_bytes,
+        }
+    }
+
+    /// Name the operation that timed out and attach its progress
+    ///
+    /// Errors other than [`CodexError::Timeout`] are returned unchanged.
+    pub(crate) fn with_timeout_progress(
+        self,
+        operation: impl Into<String>,
+        bytes_transferred: Option<usize>,
+        total_bytes: Option<usize>,
+    ) -> Self {
+        match self {
+            CodexError::Timeout { .. } => CodexError::Timeout {
+                operation: operation.into(),
+                bytes_transferred,
+                total_bytes,
+            },
+            error => error,
         }
     }
 
@@ -121,6 +198,15 @@ impl CodexError {
     }
 }
 
+fn progress_suffix(bytes_transferred: &Option<usize>, total_bytes: &Option<usize>) -> String {
+    match (bytes_transferred, total_bytes) {
+        (Some(bytes), Some(total)) => format!(" after {} of {} bytes", bytes, total),
+        (Some(bytes), None) => format!(" after {} bytes", bytes),
+        (None, Some(total)) => format!(" ({} bytes)", total),
+        (None, None) => String::new(),
+    }
+}
+
 pub fn from_c_error(code: i32, message: &str) -> CodexError {
     match code {
         0 => CodexError::library_error(format!("Unexpected success with message: {}", message)),
@@ -155,6 +241,27 @@ mod tests {
             err.to_string(),
             "Node operation failed: start - Failed to start"
         );
+
+        let err = CodexError::verification_failed(
+            "zDvExample",
+            VerificationFailure::SizeMismatch {
+                expected: 10,
+                actual: 8,
+            },
+        );
+        assert_eq!(
+            err.to_string(),
+            "Upload verification failed for zDvExample: dataset size is 8 bytes, expected 10"
+        );
+
+        let err = CodexError::timeout("callback operation");
+        assert_eq!(err.to_string(), "Operation timed out: callback operation");
+
+        let err = CodexError::timeout_with_progress("upload", 4096, Some(10000));
+        assert_eq!(
+            err.to_string(),
+            "Operation timed out: upload after 4096 of 10000 bytes"
+        );
     }
 }
 
@@ -188,8 +295,21 @@ impl Clone for CodexError {
                 parameter: parameter.clone(),
                 message: message.clone(),
             },
-            CodexError::Timeout { operation } => CodexError::Timeout {
+            CodexError::VerificationFailed { cid, failure } => CodexError::VerificationFailed {
+                cid: cid.clone(),
+                failure: failure.clone(),
+            },
+            CodexError::EncryptionError { message } => CodexError::EncryptionError {
+                message: message.clone(),
+            },
+            CodexError::Timeout {
+                operation,
+                bytes_transferred,
+                total_bytes,
+            } => CodexError::Timeout {
                 operation: operation.clone(),
+                bytes_transferred: *bytes_transferred,
+                total_bytes: *total_bytes,
             },
             CodexError::Cancelled { operation } => CodexError::Cancelled {
                 operation: operation.clone(),
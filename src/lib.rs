@@ -3,34 +3,54 @@ pub mod error;
 pub mod ffi;
 
 pub mod debug;
+pub mod directory;
 pub mod download;
+#[cfg(feature = "encryption")]
+pub mod encryption;
 pub mod node;
 pub mod p2p;
 pub mod storage;
+pub mod throttle;
 pub mod upload;
 
 // Debug operations and types
 pub use debug::{debug, peer_debug, update_log_level, DebugInfo};
 
+pub use directory::{
+    download_dir, upload_dir, DirectoryManifest, DirectoryOptions, DirectoryUploadResult,
+};
+
 pub use download::{
-    download_cancel, download_chunk, download_init, download_manifest, download_stream,
-    DownloadOptions, DownloadProgress, DownloadResult, DownloadStreamOptions,
+    download_cancel, download_chunk, download_init, download_manifest, download_reader,
+    download_segmented, download_stream, DownloadOptions, DownloadProgress, DownloadReader,
+    DownloadReaderOptions, DownloadResult, DownloadSession, DownloadStreamOptions,
 };
 
-pub use error::{CodexError, Result};
+#[cfg(feature = "encryption")]
+pub use encryption::{EncryptedDownload, EncryptedUpload, EncryptionKey};
 
-pub use node::{CodexConfig, CodexNode, LogFormat, LogLevel};
+pub use error::{CodexError, Result, VerificationFailure};
+
+pub use node::{
+    ByteSize, CodexConfig, CodexNode, LogFormat, LogLevel, NatConfig, Profile, ReconfigureReport,
+    RestartPolicy,
+};
 
 pub use p2p::{
     connect, connect_to_multiple, get_peer_id, get_peer_info, validate_addresses, validate_peer_id,
-    ConnectionQuality, PeerInfo, PeerRecord,
+    ConnectionQuality, Multiaddr, PeerInfo, PeerRecord, Protocol,
 };
 
+pub use throttle::RateLimiter;
+
 pub use storage::{delete, exists, fetch, manifests, space, Manifest as StorageManifest, Space};
 
 pub use upload::{
-    upload_cancel, upload_chunk, upload_file, upload_finalize, upload_init, upload_reader,
-    UploadOptions, UploadProgress, UploadResult, UploadStrategy,
+    compute_cid, compute_manifest, mimetype_for_filename, resume_upload, sniff_mimetype,
+    upload_async_reader, upload_bytes, upload_cancel, upload_chunk, upload_file, upload_finalize,
+    upload_init, upload_reader, ActiveUpload, BatchItemResult, BatchUploader, CidOptions,
+    Compression, ComputedManifest, SegmentManifest, UploadJournal, UploadOptions, UploadProgress,
+    UploadReaper, UploadResult, UploadSession, UploadStrategy, UploadWriter,
 };
 
 pub use upload::{
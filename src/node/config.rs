@@ -1,8 +1,15 @@
 //! Node configuration structures for Codex
 
 use crate::error::{CodexError, Result};
+use crate::node::units::{
+    self, parse_byte_size, parse_duration, ByteSize, IntoByteSize, IntoDuration,
+};
+use crate::p2p::multiaddr::Multiaddr;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 
 /// Log level for the Codex node
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -89,6 +96,84 @@ impl std::fmt::Display for RepoKind {
     }
 }
 
+/// Method used by the node to determine its public address
+///
+/// Serializes to the strings libcodex accepts: `any`, `none`, `upnp`, `pmp`
+/// and `extip:<IP>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NatConfig {
+    /// Try UPnP, then NAT-PMP, then fall back to the local address
+    #[default]
+    Any,
+    /// Announce the local listen address as is
+    None,
+    /// Use UPnP to map the listen port
+    Upnp,
+    /// Use NAT-PMP to map the listen port
+    Pmp,
+    /// Announce a fixed external IP address
+    ExtIp(IpAddr),
+}
+
+impl std::fmt::Display for NatConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NatConfig::Any => write!(f, "any"),
+            NatConfig::None => write!(f, "none"),
+            NatConfig::Upnp => write!(f, "upnp"),
+            NatConfig::Pmp => write!(f, "pmp"),
+            NatConfig::ExtIp(ip) => write!(f, "extip:{}", ip),
+        }
+    }
+}
+
+impl FromStr for NatConfig {
+    type Err = CodexError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "any" => Ok(NatConfig::Any),
+            "none" => Ok(NatConfig::None),
+            "upnp" => Ok(NatConfig::Upnp),
+            "pmp" => Ok(NatConfig::Pmp),
+            other => match other.strip_prefix("extip:") {
+                Some(ip) => ip.parse().map(NatConfig::ExtIp).map_err(|_| {
+                    CodexError::invalid_parameter(
+                        "nat",
+                        format!("Invalid external IP address '{}'", ip),
+                    )
+                }),
+                None => Err(CodexError::invalid_parameter(
+                    "nat",
+                    format!(
+                        "Unknown NAT method '{}' (expected any, none, upnp, pmp or extip:<IP>)",
+                        s
+                    ),
+                )),
+            },
+        }
+    }
+}
+
+impl Serialize for NatConfig {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for NatConfig {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 /// Configuration for a Codex node
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodexConfig {
@@ -134,11 +219,11 @@ pub struct CodexConfig {
         default,
         skip_serializing_if = "Vec::is_empty"
     )]
-    pub listen_addrs: Vec<String>,
+    pub listen_addrs: Vec<Multiaddr>,
 
-    /// Specify method to use for determining public address
+    /// Specify method to use for determining public address (default: any)
     #[serde(rename = "nat", default, skip_serializing_if = "Option::is_none")]
-    pub nat: Option<String>,
+    pub nat: Option<NatConfig>,
 
     /// Discovery (UDP) port (default: 8090)
     #[serde(rename = "disc-port", default, skip_serializing_if = "Option::is_none")]
@@ -153,6 +238,8 @@ pub struct CodexConfig {
     pub net_priv_key_file: Option<PathBuf>,
 
     /// Specifies one or more bootstrap nodes to use when connecting to the network
+    ///
+    /// Bootstrap nodes are given as signed peer records (`spr:...`), not multiaddresses.
     #[serde(
         rename = "bootstrap-node",
         default,
@@ -185,20 +272,37 @@ pub struct CodexConfig {
     pub repo_kind: Option<RepoKind>,
 
     /// The size of the total storage quota dedicated to the node (default: 20 GiBs)
+    ///
+    /// Written as bytes; read from bytes or a size string such as `"20GiB"`.
     #[serde(
         rename = "storage-quota",
         default,
-        skip_serializing_if = "Option::is_none"
+        skip_serializing_if = "Option::is_none",
+        with = "units::byte_size_opt"
     )]
-    pub storage_quota: Option<u64>,
+    pub storage_quota: Option<ByteSize>,
 
-    /// Default block timeout in seconds - 0 disables the ttl (default: 30 days)
-    #[serde(rename = "block-ttl", default, skip_serializing_if = "Option::is_none")]
-    pub block_ttl: Option<u32>,
+    /// Default block timeout - 0 disables the ttl (default: 30 days)
+    ///
+    /// Written as seconds; read from seconds or a duration string such as `"30d"`.
+    #[serde(
+        rename = "block-ttl",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "units::duration_secs_opt"
+    )]
+    pub block_ttl: Option<Duration>,
 
-    /// Time interval in seconds - determines frequency of block maintenance cycle (default: 10 minutes)
-    #[serde(rename = "block-mi", default, skip_serializing_if = "Option::is_none")]
-    pub block_maintenance_interval: Option<u32>,
+    /// Time interval that determines frequency of block maintenance cycle (default: 10 minutes)
+    ///
+    /// Written as seconds; read from seconds or a duration string such as `"10m"`.
+    #[serde(
+        rename = "block-mi",
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "units::duration_secs_opt"
+    )]
+    pub block_maintenance_interval: Option<Duration>,
 
     /// Number of blocks to check every maintenance cycle (default: 1000)
     #[serde(rename = "block-mn", default, skip_serializing_if = "Option::is_none")]
@@ -213,12 +317,15 @@ pub struct CodexConfig {
     pub block_retries: Option<u32>,
 
     /// The size of the block cache, 0 disables the cache (default: 0)
+    ///
+    /// Written as bytes; read from bytes or a size string such as `"512MiB"`.
     #[serde(
         rename = "cache-size",
         default,
-        skip_serializing_if = "Option::is_none"
+        skip_serializing_if = "Option::is_none",
+        with = "units::byte_size_opt"
     )]
-    pub cache_size: Option<u64>,
+    pub cache_size: Option<ByteSize>,
 
     /// Log file path (default: "" - no log file)
     #[serde(rename = "log-file", default, skip_serializing_if = "Option::is_none")]
@@ -234,8 +341,8 @@ impl Default for CodexConfig {
             metrics_address: Some("127.0.0.1".to_string()),
             metrics_port: Some(8008),
             data_dir: None,
-            listen_addrs: vec!["/ip4/0.0.0.0/tcp/0".to_string()],
-            nat: Some("any".to_string()),
+            listen_addrs: vec![Multiaddr::from(SocketAddr::from(([0, 0, 0, 0], 0)))],
+            nat: Some(NatConfig::Any),
             discovery_port: Some(8090),
             net_priv_key_file: None,
             bootstrap_nodes: vec![],
@@ -243,12 +350,12 @@ impl Default for CodexConfig {
             num_threads: Some(0),
             agent_string: Some("Codex".to_string()),
             repo_kind: Some(RepoKind::Fs),
-            storage_quota: Some(20 * 1024 * 1024 * 1024), // 20 GiB
-            block_ttl: Some(30 * 24 * 60 * 60),           // 30 days in seconds
-            block_maintenance_interval: Some(10 * 60),    // 10 minutes in seconds
+            storage_quota: Some(ByteSize::gib(20)),
+            block_ttl: Some(Duration::from_secs(30 * 24 * 60 * 60)), // 30 days
+            block_maintenance_interval: Some(Duration::from_secs(10 * 60)), // 10 minutes
             block_maintenance_number_of_blocks: Some(1000),
             block_retries: Some(3000),
-            cache_size: Some(0),
+            cache_size: Some(ByteSize::b(0)),
             log_file: None,
         }
     }
@@ -300,12 +407,17 @@ impl CodexConfig {
         self
     }
 
-    /// Set the storage quota in bytes
-    pub fn storage_quota(mut self, quota: u64) -> Self {
-        self.storage_quota = Some(quota);
+    /// Set the storage quota (plain integers are bytes)
+    pub fn storage_quota<B: IntoByteSize>(mut self, quota: B) -> Self {
+        self.storage_quota = Some(quota.into_byte_size());
         self
     }
 
+    /// Set the storage quota from a size such as `"20GiB"`
+    pub fn try_storage_quota(self, quota: &str) -> Result<Self> {
+        Ok(self.storage_quota(parse_byte_size(quota)?))
+    }
+
     /// Add a bootstrap node
     pub fn add_bootstrap_node<S: Into<String>>(mut self, node: S) -> Self {
         self.bootstrap_nodes.push(node.into());
@@ -331,14 +443,14 @@ impl CodexConfig {
     }
 
     /// Set the listen addresses
-    pub fn listen_addrs(mut self, addrs: Vec<String>) -> Self {
+    pub fn listen_addrs(mut self, addrs: Vec<Multiaddr>) -> Self {
         self.listen_addrs = addrs;
         self
     }
 
     /// Add a listen address
-    pub fn add_listen_addr<S: Into<String>>(mut self, addr: S) -> Self {
-        self.listen_addrs.push(addr.into());
+    pub fn add_listen_addr(mut self, addr: Multiaddr) -> Self {
+        self.listen_addrs.push(addr);
         self
     }
 
@@ -367,8 +479,14 @@ impl CodexConfig {
     }
 
     /// Set the NAT configuration
-    pub fn nat<S: Into<String>>(mut self, nat: S) -> Self {
-        self.nat = Some(nat.into());
+    pub fn nat(mut self, nat: NatConfig) -> Self {
+        self.nat = Some(nat);
+        self
+    }
+
+    /// Announce a fixed external IP address instead of detecting one
+    pub fn nat_extip<I: Into<IpAddr>>(mut self, ip: I) -> Self {
+        self.nat = Some(NatConfig::ExtIp(ip.into()));
         self
     }
 
@@ -390,18 +508,32 @@ impl CodexConfig {
         self
     }
 
-    /// Set the block timeout in seconds
-    pub fn block_ttl(mut self, ttl: u32) -> Self {
-        self.block_ttl = Some(ttl);
+    /// Set the block timeout (plain integers are seconds)
+    ///
+    /// libcodex takes whole seconds, so a sub-second part is rounded up.
+    pub fn block_ttl<D: IntoDuration>(mut self, ttl: D) -> Self {
+        self.block_ttl = Some(ttl.into_duration());
         self
     }
 
-    /// Set the block maintenance interval in seconds
-    pub fn block_maintenance_interval(mut self, interval: u32) -> Self {
-        self.block_maintenance_interval = Some(interval);
+    /// Set the block timeout from a duration such as `"30d"`
+    pub fn try_block_ttl(self, ttl: &str) -> Result<Self> {
+        Ok(self.block_ttl(parse_duration(ttl)?))
+    }
+
+    /// Set the block maintenance interval (plain integers are seconds)
+    ///
+    /// libcodex takes whole seconds, so a sub-second part is rounded up.
+    pub fn block_maintenance_interval<D: IntoDuration>(mut self, interval: D) -> Self {
+        self.block_maintenance_interval = Some(interval.into_duration());
         self
     }
 
+    /// Set the block maintenance interval from a duration such as `"10m"`
+    pub fn try_block_maintenance_interval(self, interval: &str) -> Result<Self> {
+        Ok(self.block_maintenance_interval(parse_duration(interval)?))
+    }
+
     /// Set the block maintenance number of blocks
     pub fn block_maintenance_number_of_blocks(mut self, num: u32) -> Self {
         self.block_maintenance_number_of_blocks = Some(num);
@@ -414,12 +546,17 @@ impl CodexConfig {
         self
     }
 
-    /// Set the cache size
-    pub fn cache_size(mut self, size: u64) -> Self {
-        self.cache_size = Some(size);
+    /// Set the cache size (plain integers are bytes)
+    pub fn cache_size<B: IntoByteSize>(mut self, size: B) -> Self {
+        self.cache_size = Some(size.into_byte_size());
         self
     }
 
+    /// Set the cache size from a size such as `"512MB"`
+    pub fn try_cache_size(self, size: &str) -> Result<Self> {
+        Ok(self.cache_size(parse_byte_size(size)?))
+    }
+
     /// Set the log file path
     pub fn log_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
         self.log_file = Some(path.into());
@@ -431,10 +568,35 @@ impl CodexConfig {
         serde_json::to_string(self).map_err(CodexError::from)
     }
 
+    /// Serialize the configuration to a JSON object, leaving out unset keys
+    pub(crate) fn to_json_map(&self) -> Result<serde_json::Map<String, serde_json::Value>> {
+        match serde_json::to_value(self)? {
+            serde_json::Value::Object(map) => Ok(map
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .collect()),
+            _ => Err(CodexError::config_error(
+                "Configuration did not serialize to an object",
+            )),
+        }
+    }
+
     /// Create a configuration from a JSON string
     pub fn from_json(json: &str) -> Result<Self> {
         serde_json::from_str(json).map_err(CodexError::from)
     }
+
+    /// Load a configuration from a JSON file
+    ///
+    /// Sizes and durations may be given as integers (bytes and seconds) or as
+    /// human-readable strings such as `"20GiB"` and `"30d"`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|e| {
+            CodexError::config_error(format!("Failed to load {}: {}", path.display(), e))
+        })
+    }
 }
 
 #[cfg(test)]
@@ -461,7 +623,7 @@ mod tests {
 
         assert_eq!(config.log_level, Some(LogLevel::Debug));
         assert_eq!(config.data_dir, Some(PathBuf::from("/tmp/codex")));
-        assert_eq!(config.storage_quota, Some(1024 * 1024));
+        assert_eq!(config.storage_quota, Some(ByteSize::mib(1)));
         assert_eq!(config.max_peers, Some(100));
         assert_eq!(config.repo_kind, Some(RepoKind::Sqlite));
     }
@@ -501,7 +663,7 @@ mod tests {
             .data_dir("/tmp/codex")
             .storage_quota(1024 * 1024)
             .max_peers(50)
-            .add_listen_addr("/ip4/127.0.0.1/tcp/8080")
+            .add_listen_addr("/ip4/127.0.0.1/tcp/8080".parse().unwrap())
             .add_bootstrap_node("/ip4/127.0.0.1/tcp/8081");
 
         let json_str = config.to_json().expect("Failed to serialize to JSON");
@@ -526,7 +688,7 @@ mod tests {
 
         // Minimal JSON
         assert_eq!(config.log_level, Some(LogLevel::Info));
-        assert_eq!(config.listen_addrs, Vec::<String>::new()); // Default empty
+        assert_eq!(config.listen_addrs, Vec::<Multiaddr>::new()); // Default empty
         assert_eq!(config.bootstrap_nodes, Vec::<String>::new()); // Default empty
     }
 
@@ -537,7 +699,7 @@ mod tests {
 
         // JSON with empty vectors
         assert_eq!(config.log_level, Some(LogLevel::Debug));
-        assert_eq!(config.listen_addrs, Vec::<String>::new());
+        assert_eq!(config.listen_addrs, Vec::<Multiaddr>::new());
         assert_eq!(config.bootstrap_nodes, Vec::<String>::new());
     }
 
@@ -576,23 +738,89 @@ mod tests {
         assert_eq!(config.metrics_address, Some("192.168.1.100".to_string()));
         assert_eq!(config.metrics_port, Some(9000));
         assert_eq!(config.data_dir, Some(PathBuf::from("/tmp/codex")));
-        assert_eq!(config.listen_addrs, vec!["/ip4/127.0.0.1/tcp/8080"]);
-        assert_eq!(config.nat, Some("any".to_string()));
+        assert_eq!(config.listen_addrs.len(), 1);
+        assert_eq!(config.listen_addrs[0], "/ip4/127.0.0.1/tcp/8080");
+        assert_eq!(config.nat, Some(NatConfig::Any));
         assert_eq!(config.discovery_port, Some(8090));
         assert_eq!(config.bootstrap_nodes, vec!["/ip4/127.0.0.1/tcp/8081"]);
         assert_eq!(config.max_peers, Some(100));
         assert_eq!(config.num_threads, Some(4));
         assert_eq!(config.agent_string, Some("TestAgent/1.0".to_string()));
         assert_eq!(config.repo_kind, Some(RepoKind::Sqlite));
-        assert_eq!(config.storage_quota, Some(2147483648));
-        assert_eq!(config.block_ttl, Some(86400));
-        assert_eq!(config.block_maintenance_interval, Some(600));
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(2)));
+        assert_eq!(config.block_ttl, Some(Duration::from_secs(86400)));
+        assert_eq!(
+            config.block_maintenance_interval,
+            Some(Duration::from_secs(600))
+        );
         assert_eq!(config.block_maintenance_number_of_blocks, Some(500));
         assert_eq!(config.block_retries, Some(1000));
-        assert_eq!(config.cache_size, Some(1048576));
+        assert_eq!(config.cache_size, Some(ByteSize::mib(1)));
         assert_eq!(config.log_file, Some(PathBuf::from("/var/log/codex.log")));
     }
 
+    #[test]
+    fn test_json_deserialization_human_readable_units() {
+        let json_str = r#"{
+            "storage-quota":"20GiB",
+            "cache-size":"512MB",
+            "block-ttl":"30d",
+            "block-mi":"10m"
+        }"#;
+
+        let config = CodexConfig::from_json(json_str).expect("Failed to deserialize from JSON");
+
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(20)));
+        assert_eq!(config.cache_size, Some(ByteSize::mb(512)));
+        assert_eq!(config.block_ttl, Some(Duration::from_secs(30 * 86400)));
+        assert_eq!(
+            config.block_maintenance_interval,
+            Some(Duration::from_secs(600))
+        );
+
+        // Serialized back to the integer form libcodex expects
+        let parsed: serde_json::Value = serde_json::from_str(&config.to_json().unwrap()).unwrap();
+        assert_eq!(parsed["storage-quota"], 21474836480u64);
+        assert_eq!(parsed["cache-size"], 512000000u64);
+        assert_eq!(parsed["block-ttl"], 2592000u64);
+        assert_eq!(parsed["block-mi"], 600u64);
+    }
+
+    #[test]
+    fn test_json_deserialization_rejects_invalid_units() {
+        assert!(CodexConfig::from_json(r#"{"storage-quota":"20 furlongs"}"#).is_err());
+        assert!(CodexConfig::from_json(r#"{"block-ttl":"30 fortnights"}"#).is_err());
+    }
+
+    #[test]
+    fn test_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("codex.json");
+        std::fs::write(&path, r#"{"storage-quota":"8GiB","block-ttl":"1d"}"#).unwrap();
+
+        let config = CodexConfig::from_file(&path).expect("Failed to load config file");
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(8)));
+        assert_eq!(config.block_ttl, Some(Duration::from_secs(86400)));
+
+        std::fs::write(&path, r#"{"storage-quota":"lots"}"#).unwrap();
+        assert!(matches!(
+            CodexConfig::from_file(&path),
+            Err(CodexError::ConfigError { .. })
+        ));
+    }
+
+    #[test]
+    fn test_json_deserialization_rejects_invalid_listen_addr() {
+        let json_str = r#"{"listen-addrs":["/foo/ip4"]}"#;
+        assert!(CodexConfig::from_json(json_str).is_err());
+    }
+
+    #[test]
+    fn test_default_listen_addr() {
+        let config = CodexConfig::default();
+        assert_eq!(config.listen_addrs, vec!["/ip4/0.0.0.0/tcp/0"]);
+    }
+
     #[test]
     fn test_log_level_display() {
         assert_eq!(LogLevel::Info.to_string(), "info");
@@ -617,8 +845,8 @@ mod tests {
     #[test]
     fn test_listen_addrs_builder() {
         let config = CodexConfig::new().listen_addrs(vec![
-            "/ip4/127.0.0.1/tcp/8080".to_string(),
-            "/ip4/0.0.0.0/tcp/8080".to_string(),
+            "/ip4/127.0.0.1/tcp/8080".parse().unwrap(),
+            "/ip4/0.0.0.0/tcp/8080".parse().unwrap(),
         ]);
 
         assert_eq!(config.listen_addrs.len(), 2);
@@ -629,8 +857,8 @@ mod tests {
     #[test]
     fn test_add_listen_addr_builder() {
         let config = CodexConfig::new()
-            .add_listen_addr("/ip4/127.0.0.1/tcp/8080")
-            .add_listen_addr("/ip4/0.0.0.0/tcp/8080");
+            .add_listen_addr("/ip4/127.0.0.1/tcp/8080".parse().unwrap())
+            .add_listen_addr("/ip4/0.0.0.0/tcp/8080".parse().unwrap());
 
         assert_eq!(config.listen_addrs.len(), 2);
         assert_eq!(config.listen_addrs[0], "/ip4/127.0.0.1/tcp/8080");
@@ -657,8 +885,53 @@ mod tests {
 
     #[test]
     fn test_nat_builder() {
-        let config = CodexConfig::new().nat("any");
-        assert_eq!(config.nat, Some("any".to_string()));
+        let config = CodexConfig::new().nat(NatConfig::Any);
+        assert_eq!(config.nat, Some(NatConfig::Any));
+
+        let config = CodexConfig::new().nat_extip([203, 0, 113, 7]);
+        assert_eq!(
+            config.nat,
+            Some(NatConfig::ExtIp(IpAddr::from([203, 0, 113, 7])))
+        );
+    }
+
+    #[test]
+    fn test_nat_config_strings() {
+        let cases = [
+            (NatConfig::Any, "any"),
+            (NatConfig::None, "none"),
+            (NatConfig::Upnp, "upnp"),
+            (NatConfig::Pmp, "pmp"),
+            (
+                NatConfig::ExtIp(IpAddr::from([203, 0, 113, 7])),
+                "extip:203.0.113.7",
+            ),
+            (
+                NatConfig::ExtIp("2001:db8::1".parse().unwrap()),
+                "extip:2001:db8::1",
+            ),
+        ];
+
+        for (nat, expected) in cases {
+            assert_eq!(nat.to_string(), expected);
+            assert_eq!(expected.parse::<NatConfig>().unwrap(), nat);
+        }
+
+        assert!("upnpp".parse::<NatConfig>().is_err());
+        assert!("extip:".parse::<NatConfig>().is_err());
+        assert!("extip:not-an-ip".parse::<NatConfig>().is_err());
+    }
+
+    #[test]
+    fn test_nat_config_json() {
+        let config = CodexConfig::new().nat_extip([203, 0, 113, 7]);
+        let parsed: serde_json::Value = serde_json::from_str(&config.to_json().unwrap()).unwrap();
+        assert_eq!(parsed["nat"], "extip:203.0.113.7");
+
+        let config = CodexConfig::from_json(r#"{"nat":"pmp"}"#).unwrap();
+        assert_eq!(config.nat, Some(NatConfig::Pmp));
+
+        assert!(CodexConfig::from_json(r#"{"nat":"upnpp"}"#).is_err());
     }
 
     #[test]
@@ -690,16 +963,54 @@ mod tests {
             .block_maintenance_number_of_blocks(500)
             .block_retries(1000);
 
-        assert_eq!(config.block_ttl, Some(86400));
-        assert_eq!(config.block_maintenance_interval, Some(600));
+        assert_eq!(config.block_ttl, Some(Duration::from_secs(86400)));
+        assert_eq!(
+            config.block_maintenance_interval,
+            Some(Duration::from_secs(600))
+        );
         assert_eq!(config.block_maintenance_number_of_blocks, Some(500));
         assert_eq!(config.block_retries, Some(1000));
     }
 
+    #[test]
+    fn test_block_config_builders_with_duration() {
+        let config = CodexConfig::new()
+            .block_ttl(Duration::from_secs(7 * 24 * 60 * 60))
+            .try_block_maintenance_interval("10m")
+            .unwrap();
+
+        assert_eq!(config.block_ttl, Some(Duration::from_secs(604800)));
+        assert_eq!(
+            config.block_maintenance_interval,
+            Some(Duration::from_secs(600))
+        );
+
+        assert!(CodexConfig::new().try_block_ttl("10x").is_err());
+    }
+
+    #[test]
+    fn test_sub_second_durations_round_up() {
+        let config = CodexConfig::new().block_ttl(Duration::from_millis(500));
+        let json: serde_json::Value = serde_json::from_str(&config.to_json().unwrap()).unwrap();
+        assert_eq!(json["block-ttl"], 1);
+    }
+
     #[test]
     fn test_cache_size_builder() {
         let config = CodexConfig::new().cache_size(1024 * 1024); // 1 MB
-        assert_eq!(config.cache_size, Some(1024 * 1024));
+        assert_eq!(config.cache_size, Some(ByteSize::mib(1)));
+
+        let config = CodexConfig::new().cache_size(ByteSize::mib(512));
+        assert_eq!(config.cache_size, Some(ByteSize::mib(512)));
+
+        let config = CodexConfig::new()
+            .try_storage_quota("20GiB")
+            .and_then(|config| config.try_cache_size("512MB"))
+            .unwrap();
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(20)));
+        assert_eq!(config.cache_size, Some(ByteSize::mb(512)));
+
+        assert!(CodexConfig::new().try_storage_quota("lots").is_err());
     }
 
     #[test]
@@ -714,7 +1025,7 @@ mod tests {
             .log_level(LogLevel::Debug)
             .log_format(LogFormat::Json)
             .data_dir("/tmp/codex")
-            .listen_addrs(vec!["/ip4/127.0.0.1/tcp/8080".to_string()])
+            .listen_addrs(vec!["/ip4/127.0.0.1/tcp/8080".parse().unwrap()])
             .enable_metrics(true)
             .metrics_address("127.0.0.1")
             .metrics_port(8080)
@@ -722,7 +1033,7 @@ mod tests {
             .max_peers(50)
             .storage_quota(1024 * 1024 * 1024) // 1 GB
             .repo_kind(RepoKind::Sqlite)
-            .nat("any")
+            .nat(NatConfig::Any)
             .agent_string("TestAgent/1.0")
             .block_ttl(86400)
             .cache_size(1024 * 1024);
@@ -737,11 +1048,11 @@ mod tests {
         assert_eq!(config.metrics_port, Some(8080));
         assert_eq!(config.discovery_port, Some(8090));
         assert_eq!(config.max_peers, Some(50));
-        assert_eq!(config.storage_quota, Some(1024 * 1024 * 1024));
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(1)));
         assert_eq!(config.repo_kind, Some(RepoKind::Sqlite));
-        assert_eq!(config.nat, Some("any".to_string()));
+        assert_eq!(config.nat, Some(NatConfig::Any));
         assert_eq!(config.agent_string, Some("TestAgent/1.0".to_string()));
-        assert_eq!(config.block_ttl, Some(86400));
-        assert_eq!(config.cache_size, Some(1024 * 1024));
+        assert_eq!(config.block_ttl, Some(Duration::from_secs(86400)));
+        assert_eq!(config.cache_size, Some(ByteSize::mib(1)));
     }
 }
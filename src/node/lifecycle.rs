@@ -1,22 +1,49 @@
 use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
 use crate::error::{CodexError, Result};
 use crate::ffi::{
-    codex_close, codex_destroy, codex_new, codex_peer_id, codex_repo, codex_revision, codex_spr,
-    codex_start, codex_stop, codex_version, free_c_string, string_to_c_string,
+    codex_close, codex_destroy, codex_log_level, codex_new, codex_peer_id, codex_repo,
+    codex_revision, codex_spr, codex_start, codex_stop, codex_version, free_c_string,
+    string_to_c_string,
 };
-use crate::node::config::CodexConfig;
+use crate::node::config::{CodexConfig, LogLevel};
+use crate::throttle::RateLimiter;
+use crate::upload::registry::UploadRegistry;
 use libc::c_void;
 use std::ptr;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, Weak};
 
 #[derive(Clone)]
 pub struct CodexNode {
     inner: Arc<Mutex<CodexNodeInner>>,
+    upload_limiter: RateLimiter,
+    download_limiter: RateLimiter,
+    uploads: UploadRegistry,
+}
+
+/// Reference to a node that does not keep it alive
+pub(crate) struct WeakCodexNode {
+    inner: Weak<Mutex<CodexNodeInner>>,
+    upload_limiter: RateLimiter,
+    download_limiter: RateLimiter,
+    uploads: UploadRegistry,
+}
+
+impl WeakCodexNode {
+    /// The node, unless every handle to it has been dropped
+    pub fn upgrade(&self) -> Option<CodexNode> {
+        Some(CodexNode {
+            inner: self.inner.upgrade()?,
+            upload_limiter: self.upload_limiter.clone(),
+            download_limiter: self.download_limiter.clone(),
+            uploads: self.uploads.clone(),
+        })
+    }
 }
 
 struct CodexNodeInner {
     ctx: *mut c_void,
     started: bool,
+    config: CodexConfig,
 }
 
 unsafe impl Send for CodexNodeInner {}
@@ -28,34 +55,17 @@ unsafe impl Sync for CodexNode {}
 impl CodexNode {
     pub fn new(config: CodexConfig) -> Result<Self> {
         with_libcodex_lock(|| {
-            let json_config = config.to_json()?;
-            let c_json_config = string_to_c_string(&json_config);
-
-            let future = CallbackFuture::new();
-
-            let node_ctx = unsafe {
-                let node_ctx = codex_new(
-                    c_json_config,
-                    Some(c_callback),
-                    future.context_ptr() as *mut c_void,
-                );
-
-                free_c_string(c_json_config);
-
-                if node_ctx.is_null() {
-                    return Err(CodexError::node_error("new", "Failed to create node"));
-                }
-
-                node_ctx
-            };
-
-            let _result = future.wait()?;
+            let node_ctx = create_ctx(&config)?;
 
             Ok(CodexNode {
                 inner: Arc::new(Mutex::new(CodexNodeInner {
                     ctx: node_ctx,
                     started: false,
+                    config,
                 })),
+                upload_limiter: RateLimiter::unlimited(),
+                download_limiter: RateLimiter::unlimited(),
+                uploads: UploadRegistry::default(),
             })
         })
     }
@@ -150,6 +160,7 @@ impl CodexNode {
         }
 
         inner.started = false;
+        self.uploads.clear();
         Ok(())
     }
 
@@ -190,6 +201,7 @@ impl CodexNode {
                 let mut inner = node.inner.lock().unwrap();
                 inner.started = false;
             }
+            node.uploads.clear();
 
             Ok(())
         })
@@ -346,6 +358,90 @@ impl CodexNode {
         inner.started
     }
 
+    /// Limiter shared by every upload through this node
+    ///
+    /// Unlimited until a rate is set with [`RateLimiter::set_rate`]; the rate
+    /// can be changed at any time and applies to uploads already running.
+    /// Files uploaded with [`UploadStrategy::Stream`](crate::upload::UploadStrategy::Stream)
+    /// are read by the node itself and are not limited.
+    pub fn upload_limiter(&self) -> &RateLimiter {
+        &self.upload_limiter
+    }
+
+    /// Limiter shared by every download through this node
+    ///
+    /// Unlimited until a rate is set with [`RateLimiter::set_rate`]; the rate
+    /// can be changed at any time and applies to downloads already running.
+    pub fn download_limiter(&self) -> &RateLimiter {
+        &self.download_limiter
+    }
+
+    /// Upload sessions opened through this node
+    pub(crate) fn uploads(&self) -> &UploadRegistry {
+        &self.uploads
+    }
+
+    /// Reference to this node that does not keep it alive
+    pub(crate) fn downgrade(&self) -> WeakCodexNode {
+        WeakCodexNode {
+            inner: Arc::downgrade(&self.inner),
+            upload_limiter: self.upload_limiter.clone(),
+            download_limiter: self.download_limiter.clone(),
+            uploads: self.uploads.clone(),
+        }
+    }
+
+    /// Get the configuration the node is currently running with
+    pub fn config(&self) -> CodexConfig {
+        let inner = self.inner.lock().unwrap();
+        inner.config.clone()
+    }
+
+    /// Change the log level of the running node and record it in its configuration
+    pub(crate) fn apply_log_level(&self, log_level: LogLevel) -> Result<()> {
+        with_libcodex_lock(|| {
+            let mut inner = self.inner.lock().unwrap();
+
+            let future = CallbackFuture::new();
+            let c_log_level = string_to_c_string(&log_level.to_string());
+
+            let result = unsafe {
+                let result = codex_log_level(
+                    inner.ctx as *mut _,
+                    c_log_level,
+                    Some(c_callback),
+                    future.context_ptr() as *mut c_void,
+                );
+                free_c_string(c_log_level);
+                result
+            };
+
+            if result != 0 {
+                return Err(CodexError::node_error(
+                    "reconfigure",
+                    "Failed to update log level",
+                ));
+            }
+
+            future.wait()?;
+
+            inner.config.log_level = Some(log_level);
+            Ok(())
+        })
+    }
+
+    /// Tear down the underlying libcodex node and bring it back up with `config`
+    ///
+    /// The node keeps its identity as a `CodexNode`, so every clone sees the new
+    /// instance. If it was started before, it is started again. See [`restart`]
+    /// for the state the node is left in when a step fails.
+    pub(crate) fn restart_with_config(&self, config: CodexConfig) -> Result<()> {
+        with_libcodex_lock(|| {
+            let mut inner = self.inner.lock().unwrap();
+            restart(&mut Libcodex, &mut inner, config, &self.uploads)
+        })
+    }
+
     #[allow(dead_code)]
     pub(crate) fn ctx(&self) -> *mut c_void {
         let inner = self.inner.lock().unwrap();
@@ -371,6 +467,158 @@ impl CodexNode {
     }
 }
 
+fn create_ctx(config: &CodexConfig) -> Result<*mut c_void> {
+    let json_config = config.to_json()?;
+    let c_json_config = string_to_c_string(&json_config);
+
+    let future = CallbackFuture::new();
+
+    let node_ctx = unsafe {
+        let node_ctx = codex_new(
+            c_json_config,
+            Some(c_callback),
+            future.context_ptr() as *mut c_void,
+        );
+
+        free_c_string(c_json_config);
+
+        if node_ctx.is_null() {
+            return Err(CodexError::node_error("new", "Failed to create node"));
+        }
+
+        node_ctx
+    };
+
+    let _result = future.wait()?;
+
+    Ok(node_ctx)
+}
+
+/// The libcodex calls a restart is made of
+///
+/// Kept behind a trait so that the error handling of [`restart`] can be
+/// tested without libcodex.
+trait RestartOps {
+    fn stop(&mut self, ctx: *mut c_void) -> Result<()>;
+    fn close(&mut self, ctx: *mut c_void) -> Result<()>;
+    fn destroy(&mut self, ctx: *mut c_void);
+    fn create(&mut self, config: &CodexConfig) -> Result<*mut c_void>;
+    fn start(&mut self, ctx: *mut c_void) -> Result<()>;
+}
+
+struct Libcodex;
+
+impl RestartOps for Libcodex {
+    fn stop(&mut self, ctx: *mut c_void) -> Result<()> {
+        run_ctx_op("reconfigure", "Failed to stop node", |user_data| unsafe {
+            codex_stop(ctx as *mut _, Some(c_callback), user_data)
+        })
+    }
+
+    fn close(&mut self, ctx: *mut c_void) -> Result<()> {
+        run_ctx_op("reconfigure", "Failed to close node", |user_data| unsafe {
+            codex_close(ctx as *mut _, Some(c_callback), user_data)
+        })
+    }
+
+    fn destroy(&mut self, ctx: *mut c_void) {
+        unsafe { codex_destroy(ctx as *mut _, None, ptr::null_mut()) };
+    }
+
+    fn create(&mut self, config: &CodexConfig) -> Result<*mut c_void> {
+        create_ctx(config)
+    }
+
+    fn start(&mut self, ctx: *mut c_void) -> Result<()> {
+        run_ctx_op("reconfigure", "Failed to start node", |user_data| unsafe {
+            codex_start(ctx as *mut _, Some(c_callback), user_data)
+        })
+    }
+}
+
+/// Replace the instance behind `inner` with one created from `config`
+///
+/// `inner.ctx` always points at a live instance and `inner.config` always
+/// describes it, whichever step fails:
+/// - If stopping fails, nothing changes.
+/// - If closing fails, the old instance is started again if it was running.
+/// - If `config` is rejected, an instance is created from the previous
+///   configuration instead and the rejection is returned. If that fails too,
+///   the closed old instance is kept and the node is left stopped.
+/// - If starting the new instance fails, the node is left stopped with the
+///   new configuration and can be started again with [`CodexNode::start`].
+fn restart(
+    ops: &mut impl RestartOps,
+    inner: &mut CodexNodeInner,
+    config: CodexConfig,
+    uploads: &UploadRegistry,
+) -> Result<()> {
+    let was_started = inner.started;
+
+    if was_started {
+        ops.stop(inner.ctx)?;
+        inner.started = false;
+    }
+
+    if let Err(e) = ops.close(inner.ctx) {
+        if was_started {
+            match ops.start(inner.ctx) {
+                Ok(()) => inner.started = true,
+                Err(start_error) => {
+                    log::warn!(
+                        "Failed to start node again after reconfigure: {}",
+                        start_error
+                    )
+                }
+            }
+        }
+        return Err(e);
+    }
+    uploads.clear();
+
+    // The old instance is only destroyed once a replacement exists
+    let (ctx, config, outcome) = match ops.create(&config) {
+        Ok(ctx) => (ctx, config, Ok(())),
+        Err(e) => match ops.create(&inner.config) {
+            Ok(ctx) => (ctx, inner.config.clone(), Err(e)),
+            Err(previous_error) => {
+                log::warn!(
+                    "Failed to recreate node with its previous configuration: {}",
+                    previous_error
+                );
+                return Err(e);
+            }
+        },
+    };
+    ops.destroy(inner.ctx);
+    inner.ctx = ctx;
+    inner.config = config;
+
+    if was_started {
+        ops.start(inner.ctx)?;
+        inner.started = true;
+    }
+
+    outcome
+}
+
+/// Run a lifecycle call that takes `(ctx, callback, user_data)` and wait for its callback
+fn run_ctx_op<F>(operation: &str, message: &str, op: F) -> Result<()>
+where
+    F: FnOnce(*mut c_void) -> libc::c_int,
+{
+    let future = CallbackFuture::new();
+
+    let result = op(future.context_ptr() as *mut c_void);
+
+    if result != 0 {
+        return Err(CodexError::node_error(operation, message));
+    }
+
+    future.wait()?;
+    Ok(())
+}
+
 impl Drop for CodexNode {
     fn drop(&mut self) {
         if Arc::strong_count(&self.inner) == 1 {
@@ -391,3 +639,195 @@ impl Drop for CodexNode {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::config::LogLevel;
+
+    /// Hands out fake contexts and fails the calls it is told to
+    #[derive(Default)]
+    struct FakeOps {
+        fail_stop: bool,
+        fail_close: bool,
+        fail_creates: usize,
+        fail_start: bool,
+        next_ctx: usize,
+        destroyed: Vec<*mut c_void>,
+        started: Vec<*mut c_void>,
+    }
+
+    impl RestartOps for FakeOps {
+        fn stop(&mut self, _ctx: *mut c_void) -> Result<()> {
+            if self.fail_stop {
+                return Err(CodexError::node_error("reconfigure", "stop"));
+            }
+            Ok(())
+        }
+
+        fn close(&mut self, _ctx: *mut c_void) -> Result<()> {
+            if self.fail_close {
+                return Err(CodexError::node_error("reconfigure", "close"));
+            }
+            Ok(())
+        }
+
+        fn destroy(&mut self, ctx: *mut c_void) {
+            self.destroyed.push(ctx);
+        }
+
+        fn create(&mut self, _config: &CodexConfig) -> Result<*mut c_void> {
+            if self.fail_creates > 0 {
+                self.fail_creates -= 1;
+                return Err(CodexError::node_error("new", "create"));
+            }
+            self.next_ctx += 1;
+            Ok((0x1000 + self.next_ctx) as *mut c_void)
+        }
+
+        fn start(&mut self, ctx: *mut c_void) -> Result<()> {
+            if self.fail_start {
+                return Err(CodexError::node_error("reconfigure", "start"));
+            }
+            self.started.push(ctx);
+            Ok(())
+        }
+    }
+
+    const OLD_CTX: *mut c_void = 0x1000 as *mut c_void;
+
+    fn running_inner() -> CodexNodeInner {
+        CodexNodeInner {
+            ctx: OLD_CTX,
+            started: true,
+            config: CodexConfig::new().log_level(LogLevel::Info),
+        }
+    }
+
+    fn new_config() -> CodexConfig {
+        CodexConfig::new().log_level(LogLevel::Debug)
+    }
+
+    #[test]
+    fn test_restart_replaces_instance() {
+        let mut ops = FakeOps::default();
+        let mut inner = running_inner();
+
+        restart(
+            &mut ops,
+            &mut inner,
+            new_config(),
+            &UploadRegistry::default(),
+        )
+        .unwrap();
+        assert_eq!(ops.destroyed, vec![OLD_CTX]);
+        assert_eq!(ops.started, vec![inner.ctx]);
+        assert_ne!(inner.ctx, OLD_CTX);
+        assert!(inner.started);
+        assert_eq!(inner.config.log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_restart_stop_failure_changes_nothing() {
+        let mut ops = FakeOps {
+            fail_stop: true,
+            ..Default::default()
+        };
+        let mut inner = running_inner();
+
+        assert!(restart(
+            &mut ops,
+            &mut inner,
+            new_config(),
+            &UploadRegistry::default()
+        )
+        .is_err());
+        assert_eq!(inner.ctx, OLD_CTX);
+        assert!(inner.started);
+        assert_eq!(inner.config.log_level, Some(LogLevel::Info));
+    }
+
+    #[test]
+    fn test_restart_close_failure_starts_old_instance() {
+        let mut ops = FakeOps {
+            fail_close: true,
+            ..Default::default()
+        };
+        let mut inner = running_inner();
+
+        assert!(restart(
+            &mut ops,
+            &mut inner,
+            new_config(),
+            &UploadRegistry::default()
+        )
+        .is_err());
+        assert_eq!(inner.ctx, OLD_CTX);
+        assert_eq!(ops.started, vec![OLD_CTX]);
+        assert!(inner.started);
+        assert!(ops.destroyed.is_empty());
+        assert_eq!(inner.config.log_level, Some(LogLevel::Info));
+    }
+
+    #[test]
+    fn test_restart_rejected_config_falls_back() {
+        let mut ops = FakeOps {
+            fail_creates: 1,
+            ..Default::default()
+        };
+        let mut inner = running_inner();
+
+        assert!(restart(
+            &mut ops,
+            &mut inner,
+            new_config(),
+            &UploadRegistry::default()
+        )
+        .is_err());
+        assert!(!inner.ctx.is_null());
+        assert_ne!(inner.ctx, OLD_CTX);
+        assert!(inner.started);
+        assert_eq!(inner.config.log_level, Some(LogLevel::Info));
+    }
+
+    #[test]
+    fn test_restart_keeps_old_instance_when_nothing_can_be_created() {
+        let mut ops = FakeOps {
+            fail_creates: 2,
+            ..Default::default()
+        };
+        let mut inner = running_inner();
+
+        assert!(restart(
+            &mut ops,
+            &mut inner,
+            new_config(),
+            &UploadRegistry::default()
+        )
+        .is_err());
+        assert_eq!(inner.ctx, OLD_CTX);
+        assert!(ops.destroyed.is_empty());
+        assert!(!inner.started);
+        assert_eq!(inner.config.log_level, Some(LogLevel::Info));
+    }
+
+    #[test]
+    fn test_restart_start_failure_keeps_new_config() {
+        let mut ops = FakeOps {
+            fail_start: true,
+            ..Default::default()
+        };
+        let mut inner = running_inner();
+
+        assert!(restart(
+            &mut ops,
+            &mut inner,
+            new_config(),
+            &UploadRegistry::default()
+        )
+        .is_err());
+        assert_ne!(inner.ctx, OLD_CTX);
+        assert!(!inner.started);
+        assert_eq!(inner.config.log_level, Some(LogLevel::Debug));
+    }
+}
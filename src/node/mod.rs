@@ -1,10 +1,17 @@
 //! Node management for Codex
 //!
 //! This module provides functionality for creating, configuring, starting,
-//! stopping, and destroying Codex nodes.
+//! stopping, reconfiguring, and destroying Codex nodes.
 
 pub mod config;
 pub mod lifecycle;
+pub mod profile;
+pub mod reconfigure;
+pub mod schema;
+pub mod units;
 
-pub use config::{CodexConfig, LogFormat, LogLevel, RepoKind};
+pub use config::{CodexConfig, LogFormat, LogLevel, NatConfig, RepoKind};
 pub use lifecycle::CodexNode;
+pub use profile::Profile;
+pub use reconfigure::{diff_configs, ConfigChange, ReconfigureReport, RestartPolicy};
+pub use units::{parse_byte_size, parse_duration, ByteSize, IntoByteSize, IntoDuration};
@@ -0,0 +1,170 @@
+//! Named configuration presets
+//!
+//! [`CodexConfig::default()`] mirrors the defaults of the `codex` binary, which
+//! suit neither tests (public listen address, UPnP, 20 GiB quota) nor servers
+//! (no announce address, no metrics). A [`Profile`] starts from those defaults
+//! and changes only what the use case needs. The result is a regular
+//! [`CodexConfig`], so any builder method can be chained afterwards.
+
+use crate::node::config::{CodexConfig, NatConfig};
+use crate::node::units::ByteSize;
+use crate::p2p::multiaddr::Multiaddr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// TCP port [`Profile::Server`] listens on
+const SERVER_LISTEN_PORT: u16 = 8070;
+
+static TEST_DIR_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A named configuration preset for [`CodexConfig::profile`]
+///
+/// Each variant lists the settings it changes from [`CodexConfig::default()`].
+/// Everything else keeps the default value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    /// A throwaway node for tests and local experiments
+    ///
+    /// - `listen-addrs`: `/ip4/127.0.0.1/tcp/0`, loopback only, on a free port
+    /// - `disc-port`: a free UDP port on loopback
+    /// - `nat`: `none`, so no UPnP or NAT-PMP probing
+    /// - `data-dir`: a fresh directory under the system temp dir, which is
+    ///   not removed automatically
+    /// - `storage-quota`: 64 MiB
+    /// - `max-peers`: 16
+    LocalTest,
+
+    /// A node on a home or office machine behind a router
+    ///
+    /// - `storage-quota`: 10 GiB, half the default, to leave room on laptops
+    /// - `max-peers`: 64
+    ///
+    /// Listens on all interfaces on a random port and uses the default `any`
+    /// NAT strategy, so the router is asked to forward the port.
+    Desktop,
+
+    /// A publicly reachable node with a known address
+    ///
+    /// - `listen-addrs`: `/ip4/0.0.0.0/tcp/8070`, a fixed port for firewall rules
+    /// - `nat`: `extip:<public_ip>`, so the node announces `public_ip` instead
+    ///   of guessing it
+    /// - `metrics`: enabled, still bound to `127.0.0.1:8008`
+    Server {
+        /// The address peers should use to reach this node
+        public_ip: IpAddr,
+    },
+}
+
+impl CodexConfig {
+    /// Create a configuration from a named [`Profile`]
+    pub fn profile(profile: Profile) -> Self {
+        let config = CodexConfig::default();
+
+        match profile {
+            Profile::LocalTest => config
+                .listen_addrs(vec![Multiaddr::from(SocketAddr::from((
+                    Ipv4Addr::LOCALHOST,
+                    0,
+                )))])
+                .discovery_port(free_udp_port())
+                .nat(NatConfig::None)
+                .data_dir(test_data_dir())
+                .storage_quota(ByteSize::mib(64))
+                .max_peers(16),
+            Profile::Desktop => config.storage_quota(ByteSize::gib(10)).max_peers(64),
+            Profile::Server { public_ip } => config
+                .listen_addrs(vec![Multiaddr::from(SocketAddr::from((
+                    Ipv4Addr::UNSPECIFIED,
+                    SERVER_LISTEN_PORT,
+                )))])
+                .nat(NatConfig::ExtIp(public_ip))
+                .enable_metrics(true),
+        }
+    }
+}
+
+/// Ask the OS for a currently unused UDP port on loopback
+///
+/// Falls back to 0 if no socket can be bound.
+fn free_udp_port() -> u16 {
+    UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))
+        .and_then(|socket| socket.local_addr())
+        .map(|addr| addr.port())
+        .unwrap_or(0)
+}
+
+fn test_data_dir() -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    std::env::temp_dir().join(format!(
+        "codex-test-{}-{}-{}",
+        std::process::id(),
+        TEST_DIR_COUNTER.fetch_add(1, Ordering::Relaxed),
+        nanos
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::config::LogLevel;
+
+    #[test]
+    fn test_local_test_profile() {
+        let config = CodexConfig::profile(Profile::LocalTest);
+
+        assert_eq!(config.listen_addrs.len(), 1);
+        assert!(config.listen_addrs[0].is_loopback());
+        assert_eq!(config.listen_addrs[0].port(), Some(0));
+        assert_eq!(config.nat, Some(NatConfig::None));
+        assert_eq!(config.storage_quota, Some(ByteSize::mib(64)));
+        assert!(config
+            .data_dir
+            .as_ref()
+            .unwrap()
+            .starts_with(std::env::temp_dir()));
+
+        let other = CodexConfig::profile(Profile::LocalTest);
+        assert_ne!(config.data_dir, other.data_dir);
+    }
+
+    #[test]
+    fn test_desktop_profile() {
+        let config = CodexConfig::profile(Profile::Desktop);
+        let defaults = CodexConfig::default();
+
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(10)));
+        assert_eq!(config.max_peers, Some(64));
+        assert_eq!(config.nat, defaults.nat);
+        assert_eq!(config.listen_addrs, defaults.listen_addrs);
+    }
+
+    #[test]
+    fn test_server_profile() {
+        let public_ip: IpAddr = "203.0.113.7".parse().unwrap();
+        let config = CodexConfig::profile(Profile::Server { public_ip });
+
+        assert_eq!(config.nat, Some(NatConfig::ExtIp(public_ip)));
+        assert_eq!(config.metrics_enabled, Some(true));
+        assert_eq!(config.listen_addrs[0].to_string(), "/ip4/0.0.0.0/tcp/8070");
+        assert_eq!(config.discovery_port, Some(8090));
+    }
+
+    #[test]
+    fn test_profile_composes_with_builders() {
+        let config = CodexConfig::profile(Profile::LocalTest)
+            .log_level(LogLevel::Debug)
+            .storage_quota(ByteSize::gib(1))
+            .data_dir("/tmp/codex-custom");
+
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(config.storage_quota, Some(ByteSize::gib(1)));
+        assert_eq!(config.data_dir, Some(PathBuf::from("/tmp/codex-custom")));
+        assert_eq!(config.nat, Some(NatConfig::None));
+    }
+}
@@ -0,0 +1,225 @@
+//! Runtime reconfiguration of a Codex node
+//!
+//! [`CodexNode::reconfigure`] compares a new [`CodexConfig`] against the one the
+//! node is running with. Settings libcodex can change on a live node are applied
+//! immediately; everything else either gets reported back as requiring a restart
+//! or, with [`RestartPolicy::IfRequired`], triggers a transparent restart of the
+//! node on the same data directory.
+
+use crate::error::{CodexError, Result};
+use crate::node::config::CodexConfig;
+use crate::node::lifecycle::CodexNode;
+use serde_json::Value;
+
+/// Configuration keys that can be changed without restarting the node
+const LIVE_KEYS: &[&str] = &["log-level"];
+
+/// What [`CodexNode::reconfigure`] may do with settings that need a restart
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Only apply live-changeable settings and report the rest
+    #[default]
+    Never,
+    /// Restart the node on the same data directory when required
+    IfRequired,
+}
+
+/// A single setting that differs between two configurations
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigChange {
+    /// The configuration key, as used in the JSON configuration (e.g. `"log-level"`)
+    pub key: String,
+    /// The value the node was running with, if set
+    pub old: Option<Value>,
+    /// The requested value, if set
+    pub new: Option<Value>,
+}
+
+impl ConfigChange {
+    /// Whether this setting can be changed on a running node
+    pub fn is_live(&self) -> bool {
+        LIVE_KEYS.contains(&self.key.as_str())
+    }
+}
+
+/// Outcome of [`CodexNode::reconfigure`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReconfigureReport {
+    /// Settings that were applied to the running node
+    pub applied: Vec<ConfigChange>,
+    /// Settings that only take effect after a restart
+    pub restart_required: Vec<ConfigChange>,
+    /// Whether the node was restarted to apply `restart_required`
+    pub restarted: bool,
+}
+
+impl ReconfigureReport {
+    /// Whether the new configuration was identical to the running one
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
+    }
+
+    /// Settings that are still waiting for a restart
+    pub fn pending(&self) -> &[ConfigChange] {
+        if self.restarted {
+            &[]
+        } else {
+            &self.restart_required
+        }
+    }
+}
+
+/// Compare two configurations key by key
+///
+/// Changes are returned in key order, using the same keys and value encoding
+/// as the JSON configuration passed to libcodex.
+pub fn diff_configs(old: &CodexConfig, new: &CodexConfig) -> Result<Vec<ConfigChange>> {
+    let old = old.to_json_map()?;
+    let new = new.to_json_map()?;
+
+    let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    Ok(keys
+        .into_iter()
+        .filter(|key| old.get(*key) != new.get(*key))
+        .map(|key| ConfigChange {
+            key: key.clone(),
+            old: old.get(key).cloned(),
+            new: new.get(key).cloned(),
+        })
+        .collect())
+}
+
+impl CodexNode {
+    /// Apply a new configuration to the node
+    ///
+    /// Live-changeable settings (currently only the log level) are applied
+    /// immediately. Other changes are listed in
+    /// [`ReconfigureReport::restart_required`]; with [`RestartPolicy::IfRequired`]
+    /// the node is stopped, recreated with `new_config` and started again if it
+    /// was running. All clones of the node see the restarted instance.
+    ///
+    /// If the restart fails, [`CodexNode::config`] still describes the
+    /// instance the node is left with: a rejected configuration falls back to
+    /// the previous one, and a node that fails to start again is left stopped.
+    ///
+    /// The data directory cannot be changed this way, since the restarted node
+    /// must keep its repository. A new configuration that moves it is rejected
+    /// under either policy, before anything is applied.
+    pub fn reconfigure(
+        &self,
+        new_config: CodexConfig,
+        policy: RestartPolicy,
+    ) -> Result<ReconfigureReport> {
+        let changes = diff_configs(&self.config(), &new_config)?;
+        if changes.iter().any(|c| c.key == "data-dir") {
+            return Err(CodexError::invalid_parameter(
+                "data_dir",
+                "The data directory cannot be changed on a running node",
+            ));
+        }
+
+        let (applied, restart_required): (Vec<_>, Vec<_>) =
+            changes.into_iter().partition(ConfigChange::is_live);
+
+        let mut report = ReconfigureReport {
+            applied,
+            restart_required,
+            restarted: false,
+        };
+
+        if policy == RestartPolicy::IfRequired && !report.restart_required.is_empty() {
+            self.restart_with_config(new_config)?;
+            report.restarted = true;
+            return Ok(report);
+        }
+
+        if !report.applied.is_empty() {
+            self.apply_log_level(new_config.log_level.unwrap_or_default())?;
+        }
+
+        Ok(report)
+    }
+
+    /// Async version of [`CodexNode::reconfigure`]
+    pub async fn reconfigure_async(
+        &self,
+        new_config: CodexConfig,
+        policy: RestartPolicy,
+    ) -> Result<ReconfigureReport> {
+        let node = self.clone();
+        tokio::task::spawn_blocking(move || node.reconfigure(new_config, policy)).await?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::config::LogLevel;
+    use crate::node::units::ByteSize;
+
+    #[test]
+    fn test_diff_identical_configs() {
+        let config = CodexConfig::new().data_dir("/tmp/codex");
+        let changes = diff_configs(&config, &config.clone()).unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_diff_detects_changes() {
+        let old = CodexConfig::new().data_dir("/tmp/codex");
+        let new = old
+            .clone()
+            .log_level(LogLevel::Debug)
+            .max_peers(50)
+            .storage_quota(ByteSize::gib(1));
+
+        let changes = diff_configs(&old, &new).unwrap();
+        let keys: Vec<&str> = changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, vec!["log-level", "max-peers", "storage-quota"]);
+
+        let log_level = &changes[0];
+        assert!(log_level.is_live());
+        assert_eq!(log_level.old, Some(Value::from("info")));
+        assert_eq!(log_level.new, Some(Value::from("debug")));
+
+        assert!(!changes[1].is_live());
+        assert_eq!(changes[1].old, None);
+        assert_eq!(changes[1].new, Some(Value::from(50)));
+    }
+
+    #[test]
+    fn test_diff_detects_removed_settings() {
+        let old = CodexConfig::new().agent_string("TestAgent/1.0");
+        let mut new = old.clone();
+        new.agent_string = None;
+
+        let changes = diff_configs(&old, &new).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].key, "agent-string");
+        assert_eq!(changes[0].new, None);
+    }
+
+    #[test]
+    fn test_report_pending() {
+        let change = ConfigChange {
+            key: "max-peers".to_string(),
+            old: None,
+            new: Some(Value::from(50)),
+        };
+
+        let mut report = ReconfigureReport {
+            applied: Vec::new(),
+            restart_required: vec![change],
+            restarted: false,
+        };
+        assert!(!report.is_empty());
+        assert_eq!(report.pending().len(), 1);
+
+        report.restarted = true;
+        assert!(report.pending().is_empty());
+        assert!(ReconfigureReport::default().is_empty());
+    }
+}
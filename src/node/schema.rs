@@ -0,0 +1,324 @@
+//! Description of the JSON configuration format
+//!
+//! [`CodexConfig`] is serialized to the JSON that `codex_new` consumes. The keys
+//! of that JSON are the long flag names of the `codex` binary, so the same table
+//! drives both [`CodexConfig::json_schema`] and [`CodexConfig::to_cli_args`].
+
+use crate::error::Result;
+use crate::node::config::CodexConfig;
+use serde_json::{json, Map, Value};
+
+/// The kind of value a configuration key holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Bool,
+    Integer,
+    Port,
+    String,
+    Path,
+    /// One of a fixed set of lowercase strings
+    Enum(&'static [&'static str]),
+    /// `any`, `none`, `upnp`, `pmp` or `extip:<IP>`
+    Nat,
+    /// Bytes, or a size string such as `"20GiB"`
+    Size,
+    /// Seconds, or a duration string such as `"30d"`
+    Duration,
+    /// A list of multiaddresses
+    Multiaddrs,
+    /// A list of strings
+    Strings,
+}
+
+/// A single key of the JSON configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfigField {
+    /// JSON key and `codex` flag name, without the leading `--`
+    pub key: &'static str,
+    pub kind: FieldKind,
+    pub description: &'static str,
+}
+
+const fn field(key: &'static str, kind: FieldKind, description: &'static str) -> ConfigField {
+    ConfigField {
+        key,
+        kind,
+        description,
+    }
+}
+
+/// Every key [`CodexConfig`] can serialize, in the order of its fields
+pub const CONFIG_FIELDS: &[ConfigField] = &[
+    field(
+        "log-level",
+        FieldKind::Enum(&["trace", "debug", "info", "notice", "warn", "error", "fatal"]),
+        "Log level",
+    ),
+    field(
+        "log-format",
+        FieldKind::Enum(&["auto", "colors", "nocolors", "json"]),
+        "Log format",
+    ),
+    field("metrics", FieldKind::Bool, "Enable the metrics server"),
+    field(
+        "metrics-address",
+        FieldKind::String,
+        "Listening address of the metrics server",
+    ),
+    field(
+        "metrics-port",
+        FieldKind::Port,
+        "Listening HTTP port of the metrics server",
+    ),
+    field(
+        "data-dir",
+        FieldKind::Path,
+        "The directory where codex will store configuration and data",
+    ),
+    field(
+        "listen-addrs",
+        FieldKind::Multiaddrs,
+        "Multi Addresses to listen on",
+    ),
+    field(
+        "nat",
+        FieldKind::Nat,
+        "Specify method to use for determining public address",
+    ),
+    field("disc-port", FieldKind::Port, "Discovery (UDP) port"),
+    field(
+        "net-privkey",
+        FieldKind::Path,
+        "Source of network (secp256k1) private key file path or name",
+    ),
+    field(
+        "bootstrap-node",
+        FieldKind::Strings,
+        "Signed peer records (spr:...) of bootstrap nodes to use when connecting to the network",
+    ),
+    field(
+        "max-peers",
+        FieldKind::Integer,
+        "The maximum number of peers to connect to",
+    ),
+    field(
+        "num-threads",
+        FieldKind::Integer,
+        "Number of worker threads (0 = use as many threads as there are CPU cores available)",
+    ),
+    field(
+        "agent-string",
+        FieldKind::String,
+        "Node agent string which is used as identifier in network",
+    ),
+    field(
+        "repo-kind",
+        FieldKind::Enum(&["fs", "sqlite", "leveldb"]),
+        "Backend for main repo store",
+    ),
+    field(
+        "storage-quota",
+        FieldKind::Size,
+        "The size of the total storage quota dedicated to the node",
+    ),
+    field(
+        "block-ttl",
+        FieldKind::Duration,
+        "Default block timeout - 0 disables the ttl",
+    ),
+    field(
+        "block-mi",
+        FieldKind::Duration,
+        "Time interval that determines frequency of block maintenance cycle",
+    ),
+    field(
+        "block-mn",
+        FieldKind::Integer,
+        "Number of blocks to check every maintenance cycle",
+    ),
+    field(
+        "block-retries",
+        FieldKind::Integer,
+        "Number of times to retry fetching a block before giving up",
+    ),
+    field(
+        "cache-size",
+        FieldKind::Size,
+        "The size of the block cache, 0 disables the cache",
+    ),
+    field("log-file", FieldKind::Path, "Log file path"),
+];
+
+impl FieldKind {
+    fn schema(&self) -> Value {
+        match self {
+            FieldKind::Bool => json!({ "type": "boolean" }),
+            FieldKind::Integer => json!({ "type": "integer", "minimum": 0 }),
+            FieldKind::Port => json!({ "type": "integer", "minimum": 0, "maximum": 65535 }),
+            FieldKind::String | FieldKind::Path => json!({ "type": "string" }),
+            FieldKind::Enum(values) => json!({ "type": "string", "enum": values }),
+            FieldKind::Nat => json!({
+                "type": "string",
+                "pattern": "^(any|none|upnp|pmp|extip:.+)$"
+            }),
+            FieldKind::Size => json!({
+                "oneOf": [
+                    { "type": "integer", "minimum": 0 },
+                    { "type": "string", "examples": ["20GiB", "512MB"] }
+                ]
+            }),
+            FieldKind::Duration => json!({
+                "oneOf": [
+                    { "type": "integer", "minimum": 0 },
+                    { "type": "string", "pattern": "^([0-9]+|([0-9]+[smhdw])+)$", "examples": ["30d", "1h30m"] }
+                ]
+            }),
+            FieldKind::Multiaddrs => json!({
+                "type": "array",
+                "items": { "type": "string", "pattern": "^/" }
+            }),
+            FieldKind::Strings => json!({ "type": "array", "items": { "type": "string" } }),
+        }
+    }
+}
+
+impl CodexConfig {
+    /// Generate a JSON Schema (draft 2020-12) for the JSON configuration
+    ///
+    /// Each property carries its type, description and, where the `codex`
+    /// binary has one, the value used by [`CodexConfig::default()`].
+    pub fn json_schema() -> Value {
+        let defaults = CodexConfig::default().to_json_map().unwrap_or_default();
+
+        let properties: Map<String, Value> = CONFIG_FIELDS
+            .iter()
+            .map(|field| {
+                let mut schema = field.kind.schema();
+                let object = schema.as_object_mut().expect("field schemas are objects");
+                object.insert("description".into(), field.description.into());
+                if let Some(default) = defaults.get(field.key) {
+                    object.insert("default".into(), default.clone());
+                }
+                (field.key.to_string(), schema)
+            })
+            .collect();
+
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "title": "CodexConfig",
+            "description": "Configuration of a Codex node, as passed to codex_new",
+            "type": "object",
+            "properties": properties
+        })
+    }
+
+    /// Convert the configuration to the equivalent `codex` binary flags
+    ///
+    /// Only keys that are set are emitted. List values repeat their flag, e.g.
+    /// `--listen-addrs=/ip4/0.0.0.0/tcp/0 --listen-addrs=/ip6/::/tcp/0`.
+    pub fn to_cli_args(&self) -> Result<Vec<String>> {
+        let map = self.to_json_map()?;
+        let mut args = Vec::new();
+
+        for field in CONFIG_FIELDS {
+            match map.get(field.key) {
+                Some(Value::Array(values)) => {
+                    for value in values {
+                        args.push(format!("--{}={}", field.key, cli_value(value)));
+                    }
+                }
+                Some(value) => args.push(format!("--{}={}", field.key, cli_value(value))),
+                None => {}
+            }
+        }
+
+        Ok(args)
+    }
+}
+
+fn cli_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::config::NatConfig;
+    use crate::node::units::ByteSize;
+    use crate::p2p::multiaddr::Multiaddr;
+
+    fn full_config() -> CodexConfig {
+        CodexConfig::default()
+            .data_dir("/tmp/codex")
+            .net_priv_key_file("key")
+            .add_bootstrap_node("spr:abc")
+            .log_file("/tmp/codex.log")
+    }
+
+    #[test]
+    fn test_fields_cover_config() {
+        let map = full_config().to_json_map().unwrap();
+        let mut serialized: Vec<&str> = map.keys().map(|k| k.as_str()).collect();
+        let mut described: Vec<&str> = CONFIG_FIELDS.iter().map(|f| f.key).collect();
+        serialized.sort();
+        described.sort();
+        assert_eq!(serialized, described);
+    }
+
+    #[test]
+    fn test_json_schema() {
+        let schema = CodexConfig::json_schema();
+        let properties = schema["properties"].as_object().unwrap();
+
+        assert_eq!(properties.len(), CONFIG_FIELDS.len());
+        assert_eq!(properties["log-level"]["default"], "info");
+        assert_eq!(properties["disc-port"]["type"], "integer");
+        assert_eq!(properties["storage-quota"]["default"], 21474836480u64);
+        assert_eq!(properties["listen-addrs"]["type"], "array");
+        assert!(properties["data-dir"].get("default").is_none());
+        assert!(properties["repo-kind"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&Value::from("sqlite")));
+        assert!(!properties["nat"]["description"]
+            .as_str()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_to_cli_args() {
+        let mut config = CodexConfig::new()
+            .data_dir("/tmp/codex")
+            .nat(NatConfig::None)
+            .storage_quota(ByteSize::gib(1))
+            .enable_metrics(true)
+            .add_listen_addr("/ip4/127.0.0.1/tcp/0".parse::<Multiaddr>().unwrap())
+            .add_listen_addr("/ip6/::1/tcp/0".parse::<Multiaddr>().unwrap());
+        config.log_level = None;
+
+        assert_eq!(
+            config.to_cli_args().unwrap(),
+            vec![
+                "--metrics=true",
+                "--data-dir=/tmp/codex",
+                "--listen-addrs=/ip4/127.0.0.1/tcp/0",
+                "--listen-addrs=/ip6/::1/tcp/0",
+                "--nat=none",
+                "--storage-quota=1073741824",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_cli_args_full_config() {
+        let args = full_config().to_cli_args().unwrap();
+        assert!(args.contains(&"--block-ttl=2592000".to_string()));
+        assert!(args.contains(&"--bootstrap-node=spr:abc".to_string()));
+        assert!(args.contains(&"--log-level=info".to_string()));
+        assert!(args.iter().all(|arg| arg.starts_with("--")));
+    }
+}
@@ -0,0 +1,309 @@
+//! Size and duration values for node configuration
+//!
+//! Sizes reuse [`bytesize::ByteSize`] and durations use [`std::time::Duration`].
+//! Both are written to libcodex as plain integers (bytes and seconds), and can
+//! be read back from either integers or human-readable strings such as
+//! `"20GiB"`, `"512MB"`, `"30d"` or `"1h30m"`. Durations are rounded up to
+//! whole seconds when written, so a sub-second value never becomes `0`.
+
+use crate::error::{CodexError, Result};
+use serde::{Deserialize, Deserializer, Serializer};
+use std::time::Duration;
+
+pub use bytesize::ByteSize;
+
+/// Values accepted by configuration builders that expect a size
+///
+/// Plain integers are interpreted as bytes. Strings such as `"20GiB"` go
+/// through [`parse_byte_size`] or the `try_` builders of
+/// [`CodexConfig`](crate::CodexConfig), which report invalid input.
+pub trait IntoByteSize {
+    fn into_byte_size(self) -> ByteSize;
+}
+
+impl IntoByteSize for u64 {
+    fn into_byte_size(self) -> ByteSize {
+        ByteSize::b(self)
+    }
+}
+
+impl IntoByteSize for ByteSize {
+    fn into_byte_size(self) -> ByteSize {
+        self
+    }
+}
+
+/// Values accepted by configuration builders that expect a duration
+///
+/// Plain integers are interpreted as seconds. Strings such as `"30d"` go
+/// through [`parse_duration`] or the `try_` builders of
+/// [`CodexConfig`](crate::CodexConfig), which report invalid input.
+pub trait IntoDuration {
+    fn into_duration(self) -> Duration;
+}
+
+impl IntoDuration for u32 {
+    fn into_duration(self) -> Duration {
+        Duration::from_secs(self.into())
+    }
+}
+
+impl IntoDuration for Duration {
+    fn into_duration(self) -> Duration {
+        self
+    }
+}
+
+/// Parse a human-readable size such as `"20GiB"`, `"512MB"` or `"1048576"`
+pub fn parse_byte_size(s: &str) -> Result<ByteSize> {
+    s.trim()
+        .parse::<ByteSize>()
+        .map_err(|e| CodexError::invalid_parameter("size", e))
+}
+
+/// Parse a human-readable duration such as `"30d"`, `"10m"`, `"1h30m"` or `"600"`
+///
+/// Supported units are `s`, `m`, `h`, `d` and `w`. A bare number is read as seconds.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(CodexError::invalid_parameter(
+            "duration",
+            "Duration cannot be empty",
+        ));
+    }
+
+    if let Ok(secs) = s.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut rest = s;
+
+    while !rest.is_empty() {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        if digits == 0 {
+            return Err(CodexError::invalid_parameter(
+                "duration",
+                format!("Expected a number in '{}'", s),
+            ));
+        }
+
+        let value: u64 = rest[..digits].parse().map_err(|_| {
+            CodexError::invalid_parameter("duration", format!("Number too large in '{}'", s))
+        })?;
+        rest = &rest[digits..];
+
+        let unit_len = rest.len()
+            - rest
+                .trim_start_matches(|c: char| c.is_ascii_alphabetic())
+                .len();
+        let multiplier = match &rest[..unit_len] {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            "" => {
+                return Err(CodexError::invalid_parameter(
+                    "duration",
+                    format!("Missing unit after {} in '{}'", value, s),
+                ))
+            }
+            unit => {
+                return Err(CodexError::invalid_parameter(
+                    "duration",
+                    format!(
+                        "Unknown unit '{}' in '{}' (expected s, m, h, d or w)",
+                        unit, s
+                    ),
+                ))
+            }
+        };
+        rest = &rest[unit_len..];
+
+        total = value
+            .checked_mul(multiplier)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| {
+                CodexError::invalid_parameter("duration", format!("Duration too large: '{}'", s))
+            })?;
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// Format a duration using the largest units that divide it exactly, e.g. `"30d"` or `"1h30m"`
+pub fn format_duration(duration: Duration) -> String {
+    let mut secs = duration.as_secs();
+    if secs == 0 {
+        return "0s".to_string();
+    }
+
+    let mut out = String::new();
+    for (unit, size) in [
+        ("w", 7 * 24 * 60 * 60),
+        ("d", 24 * 60 * 60),
+        ("h", 60 * 60),
+        ("m", 60),
+        ("s", 1),
+    ] {
+        if secs >= size {
+            out.push_str(&format!("{}{}", secs / size, unit));
+            secs %= size;
+        }
+    }
+    out
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(u64),
+    String(String),
+}
+
+/// Serde helpers for `Option<ByteSize>` fields, written as integer bytes
+pub(crate) mod byte_size_opt {
+    use super::*;
+
+    pub fn serialize<S>(
+        value: &Option<ByteSize>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(size) => serializer.serialize_u64(size.as_u64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Option<ByteSize>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<NumberOrString>::deserialize(deserializer)? {
+            Some(NumberOrString::Number(bytes)) => Ok(Some(ByteSize::b(bytes))),
+            Some(NumberOrString::String(s)) => parse_byte_size(&s)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Whole seconds in `duration`, counting a started second as a full one
+///
+/// Saturates at `u64::MAX` seconds instead of wrapping to 0.
+fn secs_rounded_up(duration: Duration) -> u64 {
+    duration
+        .as_secs()
+        .saturating_add(u64::from(duration.subsec_nanos() > 0))
+}
+
+/// Serde helpers for `Option<Duration>` fields, written as integer seconds
+pub(crate) mod duration_secs_opt {
+    use super::*;
+
+    pub fn serialize<S>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(duration) => serializer.serialize_u64(secs_rounded_up(*duration)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> std::result::Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Option::<NumberOrString>::deserialize(deserializer)? {
+            Some(NumberOrString::Number(secs)) => Ok(Some(Duration::from_secs(secs))),
+            Some(NumberOrString::String(s)) => parse_duration(&s)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("20GiB").unwrap(), ByteSize::gib(20));
+        assert_eq!(parse_byte_size("512MB").unwrap(), ByteSize::mb(512));
+        assert_eq!(parse_byte_size("512 MiB").unwrap(), ByteSize::mib(512));
+        assert_eq!(parse_byte_size("1048576").unwrap(), ByteSize::b(1048576));
+        assert!(parse_byte_size("lots").is_err());
+        assert!(parse_byte_size("20 furlongs").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse_duration("30d").unwrap(),
+            Duration::from_secs(30 * 86400)
+        );
+        assert_eq!(parse_duration("10m").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(
+            parse_duration("2w").unwrap(),
+            Duration::from_secs(14 * 86400)
+        );
+        assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("600").unwrap(), Duration::from_secs(600));
+        assert_eq!(parse_duration("0").unwrap(), Duration::ZERO);
+
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10").is_ok());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("99999999999999999999w").is_err());
+    }
+
+    #[test]
+    fn test_into_conversions() {
+        assert_eq!(1024u64.into_byte_size(), ByteSize::kib(1));
+        assert_eq!(ByteSize::mb(512).into_byte_size(), ByteSize::mb(512));
+        assert_eq!(600u32.into_duration(), Duration::from_secs(600));
+        assert_eq!(
+            Duration::from_secs(3600).into_duration(),
+            Duration::from_secs(3600)
+        );
+    }
+
+    #[test]
+    fn test_secs_rounded_up() {
+        assert_eq!(secs_rounded_up(Duration::ZERO), 0);
+        assert_eq!(secs_rounded_up(Duration::from_millis(500)), 1);
+        assert_eq!(secs_rounded_up(Duration::from_secs(2)), 2);
+        assert_eq!(secs_rounded_up(Duration::from_millis(2001)), 3);
+        assert_eq!(secs_rounded_up(Duration::MAX), u64::MAX);
+        assert_eq!(secs_rounded_up(Duration::new(u64::MAX, 1)), u64::MAX);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_secs(30 * 86400)), "4w2d");
+        assert_eq!(format_duration(Duration::from_secs(600)), "10m");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::ZERO), "0s");
+
+        let duration = Duration::from_secs(93784);
+        assert_eq!(
+            parse_duration(&format_duration(duration)).unwrap(),
+            duration
+        );
+    }
+}
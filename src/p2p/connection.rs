@@ -2,9 +2,10 @@ use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
 use crate::error::{CodexError, Result};
 use crate::ffi::{codex_connect, free_c_string, string_to_c_string};
 use crate::node::lifecycle::CodexNode;
+use crate::p2p::multiaddr::Multiaddr;
 use libc::{c_char, c_void};
 
-pub async fn connect(node: &CodexNode, peer_id: &str, peer_addresses: &[String]) -> Result<()> {
+pub async fn connect(node: &CodexNode, peer_id: &str, peer_addresses: &[Multiaddr]) -> Result<()> {
     let node = node.clone();
     let peer_id = peer_id.to_string();
     let peer_addresses = peer_addresses.to_vec();
@@ -30,7 +31,7 @@ pub async fn connect(node: &CodexNode, peer_id: &str, peer_addresses: &[String])
 
         let c_addresses: Vec<*mut c_char> = peer_addresses
             .iter()
-            .map(|addr| string_to_c_string(addr))
+            .map(|addr| string_to_c_string(&addr.to_string()))
             .collect();
 
         let result = with_libcodex_lock(|| unsafe {
@@ -66,7 +67,7 @@ pub async fn connect(node: &CodexNode, peer_id: &str, peer_addresses: &[String])
 
 pub async fn connect_to_multiple(
     node: &CodexNode,
-    peer_connections: Vec<(String, Vec<String>)>,
+    peer_connections: Vec<(String, Vec<Multiaddr>)>,
 ) -> Vec<Result<()>> {
     let mut results = Vec::with_capacity(peer_connections.len());
 
@@ -132,26 +133,14 @@ pub fn validate_addresses(addresses: &[String]) -> Result<()> {
             ));
         }
 
-        if !address.starts_with('/') {
+        if let Err(e) = address.parse::<Multiaddr>() {
+            let message = match e {
+                CodexError::InvalidParameter { message, .. } => message,
+                other => other.to_string(),
+            };
             return Err(CodexError::invalid_parameter(
-                &format!("addresses[{}]", i),
-                "Address must start with '/'",
-            ));
-        }
-
-        let valid_protocols = vec![
-            "/ip4", "/ip6", "/dns4", "/dns6", "/dnsaddr", "/tcp", "/udp", "/quic", "/ws", "/wss",
-            "/p2p", "/ipfs",
-        ];
-
-        let has_valid_protocol = valid_protocols
-            .iter()
-            .any(|&protocol| address.contains(protocol));
-
-        if !has_valid_protocol {
-            return Err(CodexError::invalid_parameter(
-                &format!("addresses[{}]", i),
-                "Address contains invalid protocol",
+                format!("addresses[{}]", i),
+                message,
             ));
         }
     }
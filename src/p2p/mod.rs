@@ -4,6 +4,7 @@
 
 pub mod connection;
 pub mod discovery;
+pub mod multiaddr;
 pub mod types;
 
 // Re-export connection operations
@@ -13,4 +14,5 @@ pub use connection::{connect, connect_to_multiple, validate_addresses, validate_
 pub use discovery::{get_peer_id, get_peer_info};
 
 // Re-export types
+pub use multiaddr::{Multiaddr, Protocol};
 pub use types::{ConnectionQuality, PeerInfo, PeerRecord};
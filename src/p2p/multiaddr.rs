@@ -0,0 +1,490 @@
+//! Typed multiaddresses for P2P operations
+//!
+//! Listen addresses, announce addresses and peer addresses exchanged with
+//! libcodex are libp2p multiaddresses in their textual form
+//! (`/ip4/127.0.0.1/tcp/8070/p2p/16Uiu2...`). [`Multiaddr`] parses and
+//! encodes that form so that malformed addresses are rejected before they
+//! reach the node.
+
+use crate::error::{CodexError, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+
+/// A single component of a multiaddress
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Ip4(Ipv4Addr),
+    Ip6(Ipv6Addr),
+    Dns(String),
+    Dns4(String),
+    Dns6(String),
+    DnsAddr(String),
+    Tcp(u16),
+    Udp(u16),
+    Quic,
+    QuicV1,
+    Ws,
+    Wss,
+    P2p(String),
+}
+
+impl Protocol {
+    /// Get the protocol name as used in the textual form
+    pub fn name(&self) -> &'static str {
+        match self {
+            Protocol::Ip4(_) => "ip4",
+            Protocol::Ip6(_) => "ip6",
+            Protocol::Dns(_) => "dns",
+            Protocol::Dns4(_) => "dns4",
+            Protocol::Dns6(_) => "dns6",
+            Protocol::DnsAddr(_) => "dnsaddr",
+            Protocol::Tcp(_) => "tcp",
+            Protocol::Udp(_) => "udp",
+            Protocol::Quic => "quic",
+            Protocol::QuicV1 => "quic-v1",
+            Protocol::Ws => "ws",
+            Protocol::Wss => "wss",
+            Protocol::P2p(_) => "p2p",
+        }
+    }
+
+    /// Parse one protocol component, consuming its value from `parts` if it has one
+    fn parse<'a>(name: &str, parts: &mut impl Iterator<Item = &'a str>) -> Result<Self> {
+        let mut value = |name: &str| {
+            parts
+                .next()
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| invalid(format!("Missing value for protocol '{}'", name)))
+        };
+
+        let protocol = match name {
+            "ip4" => {
+                let v = value(name)?;
+                Protocol::Ip4(
+                    v.parse()
+                        .map_err(|_| invalid(format!("Invalid IPv4 address '{}'", v)))?,
+                )
+            }
+            "ip6" => {
+                let v = value(name)?;
+                Protocol::Ip6(
+                    v.parse()
+                        .map_err(|_| invalid(format!("Invalid IPv6 address '{}'", v)))?,
+                )
+            }
+            "dns" => Protocol::Dns(parse_dns_name(value(name)?)?),
+            "dns4" => Protocol::Dns4(parse_dns_name(value(name)?)?),
+            "dns6" => Protocol::Dns6(parse_dns_name(value(name)?)?),
+            "dnsaddr" => Protocol::DnsAddr(parse_dns_name(value(name)?)?),
+            "tcp" => Protocol::Tcp(parse_port(value(name)?)?),
+            "udp" => Protocol::Udp(parse_port(value(name)?)?),
+            "quic" => Protocol::Quic,
+            "quic-v1" => Protocol::QuicV1,
+            "ws" => Protocol::Ws,
+            "wss" => Protocol::Wss,
+            "p2p" | "ipfs" => Protocol::P2p(parse_peer_id(value(name)?)?),
+            other => return Err(invalid(format!("Unknown protocol '{}'", other))),
+        };
+
+        Ok(protocol)
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Protocol::Ip4(ip) => write!(f, "/ip4/{}", ip),
+            Protocol::Ip6(ip) => write!(f, "/ip6/{}", ip),
+            Protocol::Dns(name)
+            | Protocol::Dns4(name)
+            | Protocol::Dns6(name)
+            | Protocol::DnsAddr(name) => write!(f, "/{}/{}", self.name(), name),
+            Protocol::Tcp(port) | Protocol::Udp(port) => write!(f, "/{}/{}", self.name(), port),
+            Protocol::Quic | Protocol::QuicV1 | Protocol::Ws | Protocol::Wss => {
+                write!(f, "/{}", self.name())
+            }
+            Protocol::P2p(peer_id) => write!(f, "/p2p/{}", peer_id),
+        }
+    }
+}
+
+/// A parsed libp2p multiaddress
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Multiaddr {
+    protocols: Vec<Protocol>,
+}
+
+impl Multiaddr {
+    /// Create a multiaddress from its components
+    pub fn new(protocols: Vec<Protocol>) -> Result<Self> {
+        if protocols.is_empty() {
+            return Err(invalid("Multiaddress must contain at least one protocol"));
+        }
+
+        Ok(Self { protocols })
+    }
+
+    /// Parse a multiaddress from its textual form
+    pub fn parse(s: &str) -> Result<Self> {
+        s.parse()
+    }
+
+    /// Append a protocol component
+    pub fn with(mut self, protocol: Protocol) -> Self {
+        self.protocols.push(protocol);
+        self
+    }
+
+    /// Append a protocol component in place
+    pub fn push(&mut self, protocol: Protocol) {
+        self.protocols.push(protocol);
+    }
+
+    /// Get the protocol components
+    pub fn protocols(&self) -> &[Protocol] {
+        &self.protocols
+    }
+
+    /// Iterate over the protocol components
+    pub fn iter(&self) -> std::slice::Iter<'_, Protocol> {
+        self.protocols.iter()
+    }
+
+    /// Get the IP address, if the host component is an IP
+    pub fn ip(&self) -> Option<IpAddr> {
+        self.protocols.iter().find_map(|p| match p {
+            Protocol::Ip4(ip) => Some(IpAddr::V4(*ip)),
+            Protocol::Ip6(ip) => Some(IpAddr::V6(*ip)),
+            _ => None,
+        })
+    }
+
+    /// Get the host component (IP address or DNS name) as a string
+    pub fn host(&self) -> Option<String> {
+        self.protocols.iter().find_map(|p| match p {
+            Protocol::Ip4(ip) => Some(ip.to_string()),
+            Protocol::Ip6(ip) => Some(ip.to_string()),
+            Protocol::Dns(name)
+            | Protocol::Dns4(name)
+            | Protocol::Dns6(name)
+            | Protocol::DnsAddr(name) => Some(name.clone()),
+            _ => None,
+        })
+    }
+
+    /// Get the TCP or UDP port
+    pub fn port(&self) -> Option<u16> {
+        self.protocols.iter().find_map(|p| match p {
+            Protocol::Tcp(port) | Protocol::Udp(port) => Some(*port),
+            _ => None,
+        })
+    }
+
+    /// Get the peer ID from the `/p2p` component
+    pub fn peer_id(&self) -> Option<&str> {
+        self.protocols.iter().find_map(|p| match p {
+            Protocol::P2p(peer_id) => Some(peer_id.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Set the `/p2p` component, replacing any existing one
+    pub fn with_peer_id<S: Into<String>>(self, peer_id: S) -> Self {
+        self.without_peer_id().with(Protocol::P2p(peer_id.into()))
+    }
+
+    /// Remove the `/p2p` component
+    pub fn without_peer_id(mut self) -> Self {
+        self.protocols.retain(|p| !matches!(p, Protocol::P2p(_)));
+        self
+    }
+
+    /// Get the socket address, if the address is an IP with a TCP or UDP port
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        Some(SocketAddr::new(self.ip()?, self.port()?))
+    }
+
+    /// Check if the host is a loopback address
+    pub fn is_loopback(&self) -> bool {
+        self.ip().is_some_and(|ip| ip.is_loopback())
+    }
+}
+
+impl FromStr for Multiaddr {
+    type Err = CodexError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let rest = s
+            .strip_prefix('/')
+            .ok_or_else(|| invalid(format!("Multiaddress must start with '/': '{}'", s)))?;
+
+        let mut parts = rest.split('/');
+        let mut protocols = Vec::new();
+
+        while let Some(name) = parts.next() {
+            if name.is_empty() {
+                return Err(invalid(format!("Empty protocol in multiaddress '{}'", s)));
+            }
+            protocols.push(Protocol::parse(name, &mut parts)?);
+        }
+
+        Multiaddr::new(protocols)
+    }
+}
+
+impl TryFrom<&str> for Multiaddr {
+    type Error = CodexError;
+
+    fn try_from(s: &str) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl TryFrom<String> for Multiaddr {
+    type Error = CodexError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<SocketAddr> for Multiaddr {
+    /// Convert a socket address to a TCP multiaddress
+    fn from(addr: SocketAddr) -> Self {
+        let host = match addr.ip() {
+            IpAddr::V4(ip) => Protocol::Ip4(ip),
+            IpAddr::V6(ip) => Protocol::Ip6(ip),
+        };
+
+        Self {
+            protocols: vec![host, Protocol::Tcp(addr.port())],
+        }
+    }
+}
+
+impl fmt::Display for Multiaddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for protocol in &self.protocols {
+            write!(f, "{}", protocol)?;
+        }
+        Ok(())
+    }
+}
+
+impl PartialEq<str> for Multiaddr {
+    fn eq(&self, other: &str) -> bool {
+        other.parse::<Multiaddr>().is_ok_and(|other| *self == other)
+    }
+}
+
+impl PartialEq<&str> for Multiaddr {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+impl Serialize for Multiaddr {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Multiaddr {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Deserialize a list of addresses reported by the node
+///
+/// libcodex may report addresses with protocols [`Protocol`] does not model,
+/// such as `p2p-circuit` or `webtransport`. Those are skipped with a warning
+/// instead of failing the whole response.
+pub(crate) fn deserialize_reported<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<Multiaddr>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let addrs = Vec::<String>::deserialize(deserializer)?;
+    Ok(addrs
+        .into_iter()
+        .filter_map(|addr| match addr.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                log::warn!("Skipping address {} reported by the node: {}", addr, e);
+                None
+            }
+        })
+        .collect())
+}
+
+fn invalid(message: impl Into<String>) -> CodexError {
+    CodexError::invalid_parameter("multiaddr", message)
+}
+
+fn parse_port(value: &str) -> Result<u16> {
+    value
+        .parse()
+        .map_err(|_| invalid(format!("Invalid port '{}'", value)))
+}
+
+fn parse_dns_name(value: &str) -> Result<String> {
+    let valid = value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+
+    if !valid {
+        return Err(invalid(format!("Invalid DNS name '{}'", value)));
+    }
+
+    Ok(value.to_string())
+}
+
+fn parse_peer_id(value: &str) -> Result<String> {
+    if !value.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(invalid(format!("Invalid peer ID '{}'", value)));
+    }
+
+    Ok(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER_ID: &str = "16Uiu2HAmBDUtkHn7Yvyhqt3QdkDAESsR5zb9RCcwu7UufPVkzj5h";
+
+    #[test]
+    fn test_parse_and_display_roundtrip() {
+        let addrs = [
+            "/ip4/127.0.0.1/tcp/8070",
+            "/ip6/::1/tcp/8080",
+            "/ip4/192.168.1.100/udp/8080/quic",
+            "/ip4/192.168.1.100/udp/8080/quic-v1",
+            "/dns4/bootstrap.codex.storage/tcp/443/wss",
+            "/dnsaddr/codex.storage",
+        ];
+
+        for addr in addrs {
+            let parsed = Multiaddr::parse(addr).unwrap();
+            assert_eq!(parsed.to_string(), addr);
+        }
+    }
+
+    #[test]
+    fn test_parse_normalizes_ipfs_to_p2p() {
+        let addr = Multiaddr::parse(&format!("/ip4/127.0.0.1/tcp/8070/ipfs/{}", PEER_ID)).unwrap();
+        assert_eq!(
+            addr.to_string(),
+            format!("/ip4/127.0.0.1/tcp/8070/p2p/{}", PEER_ID)
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_addresses() {
+        let invalid = [
+            "",
+            "/",
+            "ip4/127.0.0.1",
+            "/foo/ip4",
+            "/ip4",
+            "/ip4/256.0.0.1/tcp/80",
+            "/ip4/127.0.0.1/tcp/70000",
+            "/ip4/127.0.0.1/tcp/",
+            "/ip4/127.0.0.1//tcp/80",
+            "/ip6/127.0.0.1",
+            "/dns4/bad name/tcp/80",
+            "/p2p/not-a-peer",
+        ];
+
+        for addr in invalid {
+            let result = Multiaddr::parse(addr);
+            assert!(
+                matches!(result, Err(CodexError::InvalidParameter { .. })),
+                "'{}' should be rejected",
+                addr
+            );
+        }
+    }
+
+    #[test]
+    fn test_accessors() {
+        let addr =
+            Multiaddr::parse(&format!("/ip4/192.168.1.100/tcp/8080/p2p/{}", PEER_ID)).unwrap();
+
+        assert_eq!(addr.ip(), Some("192.168.1.100".parse().unwrap()));
+        assert_eq!(addr.host(), Some("192.168.1.100".to_string()));
+        assert_eq!(addr.port(), Some(8080));
+        assert_eq!(addr.peer_id(), Some(PEER_ID));
+        assert_eq!(
+            addr.socket_addr(),
+            Some("192.168.1.100:8080".parse().unwrap())
+        );
+        assert!(!addr.is_loopback());
+
+        let dns = Multiaddr::parse("/dns4/example.com/tcp/443").unwrap();
+        assert_eq!(dns.ip(), None);
+        assert_eq!(dns.host(), Some("example.com".to_string()));
+        assert_eq!(dns.socket_addr(), None);
+    }
+
+    #[test]
+    fn test_peer_id_component() {
+        let addr = Multiaddr::parse("/ip4/127.0.0.1/tcp/8070").unwrap();
+        assert_eq!(addr.peer_id(), None);
+
+        let with_peer = addr.clone().with_peer_id(PEER_ID);
+        assert_eq!(with_peer.peer_id(), Some(PEER_ID));
+        assert!(with_peer.is_loopback());
+
+        let replaced = with_peer.clone().with_peer_id("16Uiu2HAmOther");
+        assert_eq!(replaced.peer_id(), Some("16Uiu2HAmOther"));
+        assert_eq!(replaced.protocols().len(), 3);
+
+        assert_eq!(with_peer.without_peer_id(), addr);
+    }
+
+    #[test]
+    fn test_from_socket_addr() {
+        let addr = Multiaddr::from("127.0.0.1:0".parse::<SocketAddr>().unwrap());
+        assert_eq!(addr.to_string(), "/ip4/127.0.0.1/tcp/0");
+    }
+
+    #[test]
+    fn test_serde() {
+        let addr = Multiaddr::parse("/ip4/127.0.0.1/tcp/8070").unwrap();
+        let json = serde_json::to_string(&addr).unwrap();
+        assert_eq!(json, r#""/ip4/127.0.0.1/tcp/8070""#);
+
+        let parsed: Multiaddr = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, addr);
+
+        assert!(serde_json::from_str::<Multiaddr>(r#""/foo/ip4""#).is_err());
+    }
+
+    #[test]
+    fn test_reported_addresses_skip_unknown_protocols() {
+        #[derive(Deserialize)]
+        struct Reported {
+            #[serde(deserialize_with = "deserialize_reported")]
+            addrs: Vec<Multiaddr>,
+        }
+
+        let json = format!(
+            r#"{{"addrs": ["/ip4/127.0.0.1/tcp/8070", "/ip4/1.2.3.4/tcp/4001/p2p/{}/p2p-circuit", "/ip4/1.2.3.4/udp/4001/quic-v1/webtransport"]}}"#,
+            PEER_ID
+        );
+        let reported: Reported = serde_json::from_str(&json).unwrap();
+        assert_eq!(reported.addrs, vec!["/ip4/127.0.0.1/tcp/8070"]);
+    }
+}
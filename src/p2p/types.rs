@@ -1,5 +1,6 @@
 //! Types for P2P operations
 
+use crate::p2p::multiaddr::{deserialize_reported, Multiaddr};
 use serde::{Deserialize, Serialize};
 
 /// Information about a peer in the network
@@ -7,8 +8,9 @@ use serde::{Deserialize, Serialize};
 pub struct PeerInfo {
     /// Peer ID
     pub id: String,
-    /// Multiaddresses of the peer
-    pub addresses: Vec<String>,
+    /// Multiaddresses of the peer; those [`Multiaddr`] cannot represent are left out
+    #[serde(deserialize_with = "deserialize_reported")]
+    pub addresses: Vec<Multiaddr>,
     /// Whether the peer is connected
     pub connected: bool,
     /// Connection direction (inbound/outbound)
@@ -30,7 +32,7 @@ impl PeerInfo {
     }
 
     /// Set the addresses
-    pub fn addresses(mut self, addresses: Vec<String>) -> Self {
+    pub fn addresses(mut self, addresses: Vec<Multiaddr>) -> Self {
         self.addresses = addresses;
         self
     }
@@ -59,7 +61,7 @@ impl PeerInfo {
     }
 
     /// Get the primary address (first one in the list)
-    pub fn primary_address(&self) -> Option<&String> {
+    pub fn primary_address(&self) -> Option<&Multiaddr> {
         self.addresses.first()
     }
 
@@ -87,8 +89,9 @@ impl PeerInfo {
 pub struct PeerRecord {
     /// Peer ID
     pub id: String,
-    /// Multiaddresses of the peer
-    pub addresses: Vec<String>,
+    /// Multiaddresses of the peer; those [`Multiaddr`] cannot represent are left out
+    #[serde(deserialize_with = "deserialize_reported")]
+    pub addresses: Vec<Multiaddr>,
     /// Connection state
     pub connected: bool,
     /// Connection direction
@@ -131,7 +134,7 @@ impl PeerRecord {
     }
 
     /// Set the addresses
-    pub fn addresses(mut self, addresses: Vec<String>) -> Self {
+    pub fn addresses(mut self, addresses: Vec<Multiaddr>) -> Self {
         self.addresses = addresses;
         self
     }
@@ -328,7 +331,7 @@ mod tests {
     #[test]
     fn test_peer_info_creation() {
         let peer_info = PeerInfo::new("12D3KooWExamplePeer".to_string())
-            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".to_string()])
+            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".parse().unwrap()])
             .connected(true)
             .direction("outbound".to_string())
             .latency(50);
@@ -343,16 +346,17 @@ mod tests {
     #[test]
     fn test_peer_info_methods() {
         let peer_info = PeerInfo::new("12D3KooWExamplePeer".to_string())
-            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".to_string()])
+            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".parse().unwrap()])
             .connected(true)
             .direction("outbound".to_string())
             .latency(50);
 
         assert!(peer_info.is_reachable());
         assert_eq!(
-            peer_info.primary_address(),
-            Some(&"/ip4/192.168.1.100/tcp/8080".to_string())
+            peer_info.primary_address().map(|addr| addr.to_string()),
+            Some("/ip4/192.168.1.100/tcp/8080".to_string())
         );
+        assert_eq!(peer_info.primary_address().unwrap().port(), Some(8080));
         assert!(peer_info.is_outbound());
         assert!(!peer_info.is_inbound());
         assert_eq!(peer_info.latency_string(), "50ms");
@@ -361,7 +365,7 @@ mod tests {
     #[test]
     fn test_peer_record_creation() {
         let peer_record = PeerRecord::new("12D3KooWExamplePeer".to_string())
-            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".to_string()])
+            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".parse().unwrap()])
             .connected(true)
             .direction("outbound".to_string())
             .latency(50)
@@ -387,7 +391,7 @@ mod tests {
     #[test]
     fn test_serialization() {
         let peer_info = PeerInfo::new("12D3KooWExamplePeer".to_string())
-            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".to_string()])
+            .addresses(vec!["/ip4/192.168.1.100/tcp/8080".parse().unwrap()])
             .connected(true)
             .direction("outbound".to_string())
             .latency(50);
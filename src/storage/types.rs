@@ -97,6 +97,11 @@ impl Manifest {
         self.filename.is_empty() && self.dataset_size > 0
     }
 
+    /// Check if the manifest is a directory index uploaded by `upload_dir`
+    pub fn is_directory_index(&self) -> bool {
+        self.filename == crate::directory::types::DIRECTORY_INDEX_FILENAME
+    }
+
     /// Get the file extension if this is a file
     pub fn file_extension(&self) -> Option<String> {
         if self.is_file() {
@@ -0,0 +1,303 @@
+//! Bandwidth throttling for transfers
+//!
+//! A [`RateLimiter`] is a token bucket refilled at a configurable number of
+//! bytes per second. Limiters are cheap to clone and every clone shares the
+//! same bucket, so one limiter can cap several transfers together and its rate
+//! can be changed while they run.
+//!
+//! Limits apply per operation through
+//! [`UploadOptions::rate_limit`](crate::upload::UploadOptions::rate_limit) and
+//! [`DownloadStreamOptions::rate_limit`](crate::download::DownloadStreamOptions::rate_limit),
+//! and node-wide through [`CodexNode::upload_limiter`] and
+//! [`CodexNode::download_limiter`]. A transfer subject to both waits for both.
+
+use crate::node::lifecycle::CodexNode;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+/// Longest single wait, so that rate changes take effect promptly
+const MAX_WAIT: Duration = Duration::from_millis(100);
+
+/// Token bucket limiting transfers to a number of bytes per second
+///
+/// The bucket holds up to one second worth of bytes, so a transfer that was
+/// idle may briefly run faster than the rate. A transfer larger than the
+/// bucket waits until the bucket is full and then goes into debt, which later
+/// transfers pay off. A rate of 0 removes the limit.
+#[derive(Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+struct Bucket {
+    rate: u64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate as f64).min(self.rate as f64);
+        self.last_refill = now;
+    }
+}
+
+impl std::fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("bytes_per_second", &self.rate())
+            .finish()
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `bytes_per_second`; 0 means unlimited
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                rate: bytes_per_second,
+                tokens: bytes_per_second as f64,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// Create a limiter that does not limit until a rate is set
+    pub fn unlimited() -> Self {
+        Self::new(0)
+    }
+
+    /// Change the rate of this limiter and all its clones; 0 removes the limit
+    pub fn set_rate(&self, bytes_per_second: u64) {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill();
+        bucket.rate = bytes_per_second;
+        bucket.tokens = bucket.tokens.min(bytes_per_second as f64);
+    }
+
+    /// Current rate in bytes per second; 0 if unlimited
+    pub fn rate(&self) -> u64 {
+        self.bucket.lock().unwrap().rate
+    }
+
+    /// Whether a rate is set
+    pub fn is_limited(&self) -> bool {
+        self.rate() > 0
+    }
+
+    /// Take `bytes` from the bucket, or return how long to wait before trying again
+    fn try_take(&self, bytes: usize) -> Option<Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+        if bucket.rate == 0 {
+            return None;
+        }
+
+        bucket.refill();
+        let needed = (bytes as f64).min(bucket.rate as f64);
+        if bucket.tokens >= needed {
+            bucket.tokens -= bytes as f64;
+            return None;
+        }
+
+        let wait = Duration::from_secs_f64((needed - bucket.tokens) / bucket.rate as f64);
+        Some(wait.min(MAX_WAIT))
+    }
+
+    /// Wait on the current thread until `bytes` may be transferred
+    pub(crate) fn acquire_blocking(&self, bytes: usize) {
+        while let Some(wait) = self.try_take(bytes) {
+            std::thread::sleep(wait);
+        }
+    }
+
+    /// Wait until `bytes` may be transferred
+    pub(crate) async fn acquire(&self, bytes: usize) {
+        while let Some(wait) = self.try_take(bytes) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Progress of a [`Throttle::poll_acquire`] across polls
+pub(crate) struct Acquire {
+    bytes: usize,
+    next: usize,
+    sleep: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl Acquire {
+    pub fn new(bytes: usize) -> Self {
+        Self {
+            bytes,
+            next: 0,
+            sleep: None,
+        }
+    }
+}
+
+/// The limiters a single transfer is subject to
+pub(crate) struct Throttle {
+    limiters: Vec<RateLimiter>,
+}
+
+impl Throttle {
+    /// Limiters of an upload through `node` with an optional per-operation limit
+    pub fn upload(node: &CodexNode, limit: Option<&RateLimiter>) -> Self {
+        Self::new(node.upload_limiter(), limit)
+    }
+
+    /// Limiters of a download through `node` with an optional per-operation limit
+    pub fn download(node: &CodexNode, limit: Option<&RateLimiter>) -> Self {
+        Self::new(node.download_limiter(), limit)
+    }
+
+    fn new(node_limiter: &RateLimiter, limit: Option<&RateLimiter>) -> Self {
+        Self {
+            limiters: std::iter::once(node_limiter)
+                .chain(limit)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Whether any of the limiters currently has a rate set
+    pub fn is_limited(&self) -> bool {
+        self.limiters.iter().any(RateLimiter::is_limited)
+    }
+
+    /// Wait on the current thread until every limiter admits `bytes`
+    pub fn acquire_blocking(&self, bytes: usize) {
+        for limiter in &self.limiters {
+            limiter.acquire_blocking(bytes);
+        }
+    }
+
+    /// Wait until every limiter admits `bytes`
+    pub async fn acquire(&self, bytes: usize) {
+        for limiter in &self.limiters {
+            limiter.acquire(bytes).await;
+        }
+    }
+
+    /// Poll-based [`acquire`](Self::acquire) for `poll_*` implementations
+    ///
+    /// `acquire` carries the limiters already passed and the current wait
+    /// from one poll to the next.
+    pub fn poll_acquire(&self, cx: &mut Context<'_>, acquire: &mut Acquire) -> Poll<()> {
+        loop {
+            if let Some(sleep) = acquire.sleep.as_mut() {
+                ready!(sleep.as_mut().poll(cx));
+                acquire.sleep = None;
+            }
+
+            let Some(limiter) = self.limiters.get(acquire.next) else {
+                return Poll::Ready(());
+            };
+            match limiter.try_take(acquire.bytes) {
+                None => acquire.next += 1,
+                Some(wait) => acquire.sleep = Some(Box::pin(tokio::time::sleep(wait))),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unlimited() {
+        let limiter = RateLimiter::unlimited();
+        assert!(!limiter.is_limited());
+        assert_eq!(limiter.try_take(usize::MAX), None);
+    }
+
+    #[test]
+    fn test_burst_then_wait() {
+        let limiter = RateLimiter::new(1000);
+        assert_eq!(limiter.try_take(600), None);
+        assert_eq!(limiter.try_take(400), None);
+
+        let wait = limiter.try_take(500).unwrap();
+        assert!(wait > Duration::ZERO && wait <= MAX_WAIT);
+    }
+
+    #[test]
+    fn test_large_transfer_goes_into_debt() {
+        let limiter = RateLimiter::new(1000);
+        assert_eq!(limiter.try_take(5000), None);
+        assert!(limiter.try_take(1).is_some());
+    }
+
+    #[test]
+    fn test_acquire_blocking_paces_transfers() {
+        let limiter = RateLimiter::new(50_000);
+        let start = Instant::now();
+        limiter.acquire_blocking(50_000);
+        limiter.acquire_blocking(25_000);
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_set_rate_is_shared() {
+        let limiter = RateLimiter::new(1000);
+        let clone = limiter.clone();
+        clone.set_rate(0);
+        assert!(!limiter.is_limited());
+        assert_eq!(limiter.try_take(1_000_000), None);
+
+        limiter.set_rate(10);
+        assert_eq!(clone.rate(), 10);
+        assert!(clone.try_take(100).is_some());
+    }
+
+    #[test]
+    fn test_throttle_combines_limiters() {
+        let node_limiter = RateLimiter::unlimited();
+        let throttle = Throttle::new(&node_limiter, None);
+        assert!(!throttle.is_limited());
+
+        let limit = RateLimiter::new(1000);
+        let throttle = Throttle::new(&node_limiter, Some(&limit));
+        assert!(throttle.is_limited());
+
+        node_limiter.set_rate(500);
+        limit.set_rate(0);
+        assert!(throttle.is_limited());
+    }
+
+    #[tokio::test]
+    async fn test_poll_acquire_paces_transfers() {
+        let node_limiter = RateLimiter::new(50_000);
+        let limit = RateLimiter::new(100_000);
+        let throttle = Throttle::new(&node_limiter, Some(&limit));
+        let start = Instant::now();
+
+        for bytes in [50_000, 25_000] {
+            let mut acquire = Acquire::new(bytes);
+            std::future::poll_fn(|cx| throttle.poll_acquire(cx, &mut acquire)).await;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        assert!(limit.try_take(50_000).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_acquire_paces_transfers() {
+        let limiter = RateLimiter::new(50_000);
+        let start = Instant::now();
+        limiter.acquire(50_000).await;
+        limiter.acquire(25_000).await;
+        assert!(start.elapsed() >= Duration::from_millis(400));
+    }
+}
@@ -0,0 +1,284 @@
+//! Uploads from asynchronous sources
+//!
+//! [`upload_async_reader`] reads an [`AsyncRead`] on the calling task and feeds
+//! the upload session chunk by chunk. libcodex reports completion through
+//! callbacks, which are awaited directly instead of parking a blocking thread,
+//! so the upload of a request body or a `tokio::fs::File` costs no more than
+//! the task it runs on. The next chunk is read while the previous one is being
+//! stored by the node.
+
+use crate::callback::{deadline_after, run_until, CallbackFuture};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::throttle::Throttle;
+use crate::upload::compression::AsyncCompressor;
+use crate::upload::file::{
+    start_upload_cancel, start_upload_chunk, start_upload_finalize, start_upload_init, SessionGuard,
+};
+use crate::upload::mime::SNIFF_LEN;
+use crate::upload::types::{UploadOptions, UploadProgress, UploadResult, UploadStrategy};
+use crate::upload::verify::{verify_result, wants_digest, ContentHasher};
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// Initialize an upload session without blocking the current thread
+pub(crate) async fn upload_init_async(node: &CodexNode, options: &UploadOptions) -> Result<String> {
+    let future = CallbackFuture::new();
+    start_upload_init(node, options, &future)?;
+    let session_id = future.await?;
+    node.uploads().register(&session_id);
+    Ok(session_id)
+}
+
+/// Upload a chunk without blocking the current thread
+///
+/// The chunk is held until libcodex is done with it, even if this future is
+/// dropped first.
+pub(crate) async fn upload_chunk_async(
+    node: &CodexNode,
+    session_id: &str,
+    chunk: Bytes,
+) -> Result<()> {
+    let future = CallbackFuture::new();
+    start_upload_chunk(node, session_id, chunk, &future)?;
+    future.await?;
+    Ok(())
+}
+
+/// Finalize an upload session without blocking the current thread
+pub(crate) async fn upload_finalize_async(node: &CodexNode, session_id: &str) -> Result<String> {
+    let future = CallbackFuture::new();
+    start_upload_finalize(node, session_id, &future)?;
+    let cid = future.await?;
+    node.uploads().remove(session_id);
+    Ok(cid)
+}
+
+/// Cancel an upload session without blocking the current thread
+pub(crate) async fn upload_cancel_async(node: &CodexNode, session_id: &str) -> Result<()> {
+    let future = CallbackFuture::new();
+    start_upload_cancel(node, session_id, &future)?;
+    future.await?;
+    Ok(())
+}
+
+/// Upload data from any AsyncRead implementation
+///
+/// Reads `reader` in `chunk_size` pieces and uploads them in order, reading the
+/// next chunk while the node stores the current one. No blocking thread is
+/// held for the duration of the transfer.
+///
+/// Filenames and mimetypes are recorded as for
+/// [`upload_reader`](crate::upload::upload_reader), including sniffing and
+/// compression.
+///
+/// # Arguments
+///
+/// * `node` - The Codex node to use for the upload
+/// * `options` - Upload options including chunk size and progress callbacks
+/// * `reader` - Any type that implements AsyncRead
+///
+/// # Returns
+///
+/// An `UploadResult` containing the CID and upload statistics
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - The reader fails
+/// - The upload fails for any reason
+///
+/// The session is cancelled on failure, and when the returned future is
+/// dropped before the upload is finalized. The whole upload must finish
+/// within [`UploadOptions::timeout`] seconds; on expiry
+/// [`CodexError::Timeout`] reports the bytes sent so far.
+pub async fn upload_async_reader<R>(
+    node: &CodexNode,
+    options: UploadOptions,
+    reader: R,
+) -> Result<UploadResult>
+where
+    R: AsyncRead + Unpin,
+{
+    options.validate()?;
+    options.reader_strategy()?;
+
+    let start_time = std::time::Instant::now();
+    let deadline = deadline_after(options.timeout);
+    let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+    let timed_out = |e: CodexError, sent| e.with_timeout_progress("upload", Some(sent), None);
+
+    let mut reader = reader;
+    let mut head = Vec::new();
+    if options.needs_sniff() {
+        let sniff = async {
+            (&mut reader)
+                .take(SNIFF_LEN as u64)
+                .read_to_end(&mut head)
+                .await?;
+            Ok(())
+        };
+        run_until(deadline, sniff)
+            .await
+            .map_err(|e| timed_out(e, 0))?;
+    }
+    let options = options.sniffed(&head);
+    let reader = AsyncCompressor::new(
+        std::io::Cursor::new(head).chain(reader),
+        options.compression,
+    )?;
+
+    let session_id = run_until(deadline, upload_init_async(node, &options))
+        .await
+        .map_err(|e| timed_out(e, 0))?;
+    let guard = SessionGuard::new(node, &session_id);
+
+    let hasher = wants_digest(&options).then(ContentHasher::default);
+    let mut bytes_sent = 0;
+
+    let sent = run_until(
+        deadline,
+        send_async_reader(
+            node,
+            &options,
+            reader,
+            &session_id,
+            chunk_size,
+            hasher.as_ref(),
+            &mut bytes_sent,
+        ),
+    )
+    .await;
+
+    match sent {
+        Ok((total_bytes, chunk_count)) => {
+            let cid = match run_until(deadline, upload_finalize_async(node, &session_id)).await {
+                Ok(cid) => cid,
+                Err(e) => {
+                    let _ = upload_cancel_async(node, &session_id).await;
+                    guard.disarm();
+                    return Err(e.with_timeout_progress(
+                        "upload",
+                        Some(total_bytes),
+                        Some(total_bytes),
+                    ));
+                }
+            };
+            guard.disarm();
+
+            let result = UploadResult::new(cid, total_bytes)
+                .chunks(chunk_count)
+                .duration_ms(start_time.elapsed().as_millis() as u64)
+                .strategy(UploadStrategy::Chunked)
+                .compression(options.compression);
+            let digest = hasher.map(|hasher| hasher.finish());
+            verify_result(node, &options, result, digest).await
+        }
+        Err(e) => {
+            let _ = upload_cancel_async(node, &session_id).await;
+            guard.disarm();
+            Err(timed_out(e, bytes_sent))
+        }
+    }
+}
+
+async fn send_async_reader<R>(
+    node: &CodexNode,
+    options: &UploadOptions,
+    mut reader: R,
+    session_id: &str,
+    chunk_size: usize,
+    hasher: Option<&ContentHasher>,
+    bytes_sent: &mut usize,
+) -> Result<(usize, usize)>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut total_bytes = 0;
+    let mut chunk_count = 0;
+    let throttle = Throttle::upload(node, options.rate_limit.as_ref());
+
+    let mut current = vec![0u8; chunk_size];
+    let mut len = read_full(&mut reader, &mut current).await?;
+
+    while len > 0 {
+        // Each chunk gets its own buffer, owned by libcodex's callback until
+        // the node is done reading it
+        current.truncate(len);
+        let chunk = Bytes::from(std::mem::take(&mut current));
+        if let Some(hasher) = hasher {
+            hasher.update(&chunk);
+        }
+
+        throttle.acquire(len).await;
+        let send = upload_chunk_async(node, session_id, chunk);
+
+        let mut next = vec![0u8; chunk_size];
+        let next_len = if len < chunk_size {
+            // Short read means end of input
+            send.await?;
+            0
+        } else {
+            let (sent, read) = futures::join!(send, read_full(&mut reader, &mut next));
+            sent?;
+            read?
+        };
+
+        total_bytes += len;
+        chunk_count += 1;
+        *bytes_sent = total_bytes;
+
+        if let Some(ref callback) = options.on_progress {
+            callback(UploadProgress::new_chunked(
+                total_bytes,
+                None,
+                chunk_count,
+                chunk_count,
+            ));
+        }
+
+        current = next;
+        len = next_len;
+    }
+
+    Ok((total_bytes, chunk_count))
+}
+
+/// Fill `buffer` from `reader`, stopping early only at end of input
+async fn read_full<R>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]).await? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_full_fills_across_short_reads() {
+        let (mut client, mut server) = tokio::io::duplex(4);
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            server.write_all(b"hello world").await.unwrap();
+        });
+
+        let mut buffer = [0u8; 8];
+        let n = read_full(&mut client, &mut buffer).await.unwrap();
+        assert_eq!(n, 8);
+        assert_eq!(&buffer, b"hello wo");
+
+        writer.await.unwrap();
+        let n = read_full(&mut client, &mut buffer).await.unwrap();
+        assert_eq!(n, 3);
+        assert_eq!(&buffer[..n], b"rld");
+    }
+}
@@ -0,0 +1,484 @@
+//! Batch uploads
+//!
+//! [`BatchUploader`] uploads many files, readers and buffers with a bounded
+//! number of uploads in flight. Failed items are retried with exponential
+//! backoff, results are streamed as each item completes, and progress is
+//! reported across the whole batch rather than per item.
+
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::upload::file::{upload_file, upload_reader};
+use crate::upload::memory::upload_bytes;
+use crate::upload::types::{UploadOptions, UploadProgress, UploadResult};
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::StreamExt;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Default number of uploads in flight at the same time
+pub const DEFAULT_BATCH_CONCURRENCY: usize = 4;
+
+/// Default number of times a failed item is retried
+pub const DEFAULT_BATCH_RETRIES: usize = 2;
+
+/// Default delay before the first retry; later retries double it
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+type ProgressCallback = Arc<dyn Fn(UploadProgress) + Send + Sync>;
+
+enum Source {
+    File(PathBuf),
+    Reader(Option<Box<dyn Read + Send>>),
+    Bytes(Bytes),
+}
+
+struct BatchItem {
+    name: String,
+    source: Source,
+}
+
+/// Uploads a batch of files, readers and buffers
+///
+/// Items are uploaded at most [`concurrency`](BatchUploader::concurrency) at a
+/// time with the options given to [`options`](BatchUploader::options). Files
+/// are uploaded with [`upload_file`], readers with [`upload_reader`] and
+/// buffers with [`upload_bytes`]; readers and buffers record their name as the
+/// filename.
+///
+/// An item that fails is retried up to [`retries`](BatchUploader::retries)
+/// times unless the error shows that another attempt cannot succeed, such as
+/// an invalid parameter. Readers cannot be rewound, so they are only tried
+/// once.
+///
+/// One failed item does not stop the others; each outcome is reported in its
+/// own [`BatchItemResult`].
+pub struct BatchUploader {
+    node: CodexNode,
+    options: UploadOptions,
+    concurrency: usize,
+    retries: usize,
+    retry_delay: Duration,
+    on_progress: Option<ProgressCallback>,
+    items: Vec<BatchItem>,
+}
+
+impl std::fmt::Debug for BatchUploader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BatchUploader")
+            .field("options", &self.options)
+            .field("concurrency", &self.concurrency)
+            .field("retries", &self.retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("on_progress", &self.on_progress.is_some())
+            .field("items", &self.items.len())
+            .finish()
+    }
+}
+
+impl BatchUploader {
+    /// Create an empty batch uploading to `node`
+    pub fn new(node: &CodexNode) -> Self {
+        Self {
+            node: node.clone(),
+            options: UploadOptions::new(),
+            concurrency: DEFAULT_BATCH_CONCURRENCY,
+            retries: DEFAULT_BATCH_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            on_progress: None,
+            items: Vec::new(),
+        }
+    }
+
+    /// Set the options applied to every item
+    ///
+    /// The file path is replaced by each file's own path and the progress
+    /// callback by the batch's; see [`BatchUploader::on_progress`].
+    pub fn options(mut self, options: UploadOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the number of uploads in flight at the same time
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Set the number of times a failed item is retried
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Set the delay before the first retry of an item
+    pub fn retry_delay(mut self, delay: Duration) -> Self {
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Set the callback receiving progress across the whole batch
+    ///
+    /// Bytes are summed over all items. The total is only known when every
+    /// item has a known size, which readers do not.
+    pub fn on_progress<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(UploadProgress) + Send + Sync + 'static,
+    {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Add a file to the batch
+    #[allow(clippy::should_implement_trait)]
+    pub fn add<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+        self.items.push(BatchItem {
+            name: path.display().to_string(),
+            source: Source::File(path),
+        });
+        self
+    }
+
+    /// Add a reader to the batch, recording `name` as its filename
+    pub fn add_reader<R>(mut self, name: impl Into<String>, reader: R) -> Self
+    where
+        R: Read + Send + 'static,
+    {
+        self.items.push(BatchItem {
+            name: name.into(),
+            source: Source::Reader(Some(Box::new(reader))),
+        });
+        self
+    }
+
+    /// Add an in-memory buffer to the batch, recording `name` as its filename
+    pub fn add_bytes(mut self, name: impl Into<String>, data: impl Into<Bytes>) -> Self {
+        self.items.push(BatchItem {
+            name: name.into(),
+            source: Source::Bytes(data.into()),
+        });
+        self
+    }
+
+    /// Number of items in the batch
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether the batch has no items
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Validate the batch settings and the shared options
+    pub fn validate(&self) -> Result<()> {
+        if self.concurrency == 0 {
+            return Err(CodexError::invalid_parameter(
+                "concurrency",
+                "Concurrency must be greater than 0",
+            ));
+        }
+
+        if self.options.journal.is_some() {
+            return Err(CodexError::invalid_parameter(
+                "journal",
+                "Batch uploads cannot share a journal",
+            ));
+        }
+
+        let mut options = self.options.clone();
+        options.filepath = None;
+        options.validate()
+    }
+
+    /// Start the uploads and stream each item's result as it completes
+    ///
+    /// Results arrive in completion order; [`BatchItemResult::index`] gives
+    /// the position of the item in the batch. Nothing is uploaded until the
+    /// stream is polled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the concurrency is 0 or the options are invalid
+    pub fn stream(self) -> Result<BoxStream<'static, BatchItemResult>> {
+        self.validate()?;
+
+        let sizes = self.items.iter().map(|item| item.size()).collect();
+        let runner = Arc::new(Runner {
+            node: self.node,
+            options: self.options,
+            retries: self.retries,
+            retry_delay: self.retry_delay,
+            progress: BatchProgress::new(sizes, self.on_progress),
+        });
+
+        Ok(futures::stream::iter(self.items.into_iter().enumerate())
+            .map(move |(index, item)| {
+                let runner = runner.clone();
+                async move { runner.upload(index, item).await }
+            })
+            .buffer_unordered(self.concurrency)
+            .boxed())
+    }
+
+    /// Upload every item and return the results in the order items were added
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the concurrency is 0 or the options are invalid.
+    /// Failed items are reported in their results instead.
+    pub async fn run(self) -> Result<Vec<BatchItemResult>> {
+        let mut results: Vec<BatchItemResult> = self.stream()?.collect().await;
+        results.sort_by_key(|result| result.index);
+        Ok(results)
+    }
+}
+
+impl BatchItem {
+    fn size(&self) -> Option<usize> {
+        match self.source {
+            Source::File(ref path) => std::fs::metadata(path).ok().map(|m| m.len() as usize),
+            Source::Reader(_) => None,
+            Source::Bytes(ref data) => Some(data.len()),
+        }
+    }
+}
+
+/// Outcome of one item of a batch
+#[derive(Debug, Clone)]
+pub struct BatchItemResult {
+    /// Position of the item in the order it was added
+    pub index: usize,
+    /// Path of the file, or the name given to a reader or buffer
+    pub name: String,
+    /// Number of attempts made
+    pub attempts: usize,
+    /// The upload result of the last attempt
+    pub result: Result<UploadResult>,
+}
+
+impl BatchItemResult {
+    /// CID of the item if it was uploaded
+    pub fn cid(&self) -> Option<&str> {
+        self.result.as_ref().ok().map(|result| result.cid.as_str())
+    }
+}
+
+/// State shared by the uploads of a batch
+struct Runner {
+    node: CodexNode,
+    options: UploadOptions,
+    retries: usize,
+    retry_delay: Duration,
+    progress: Arc<BatchProgress>,
+}
+
+impl Runner {
+    async fn upload(self: Arc<Self>, index: usize, mut item: BatchItem) -> BatchItemResult {
+        let mut attempts = 0;
+
+        loop {
+            attempts += 1;
+            self.progress.record(index, 0);
+
+            let result = self.attempt(index, &item.name, &mut item.source).await;
+            let retry = match result {
+                Ok(ref uploaded) => {
+                    self.progress.complete(index, uploaded.size);
+                    false
+                }
+                Err(ref e) => {
+                    attempts <= self.retries
+                        && is_retryable(e)
+                        && !matches!(item.source, Source::Reader(None))
+                }
+            };
+
+            if !retry {
+                return BatchItemResult {
+                    index,
+                    name: item.name,
+                    attempts,
+                    result,
+                };
+            }
+
+            if let Err(ref e) = result {
+                log::warn!(
+                    "Upload of {} failed (attempt {}), retrying: {}",
+                    item.name,
+                    attempts,
+                    e
+                );
+            }
+            tokio::time::sleep(backoff(self.retry_delay, attempts)).await;
+        }
+    }
+
+    async fn attempt(&self, index: usize, name: &str, source: &mut Source) -> Result<UploadResult> {
+        let mut options = self.options.clone();
+        options.on_progress = self.progress.item_callback(index);
+
+        match source {
+            Source::File(path) => upload_file(&self.node, options.filepath(path.clone())).await,
+            Source::Reader(reader) => {
+                let reader = reader.take().ok_or_else(|| {
+                    CodexError::upload_error(format!("Reader for {} was already consumed", name))
+                })?;
+                options.filepath = None;
+                upload_reader(&self.node, options.filename(name), reader).await
+            }
+            Source::Bytes(data) => {
+                options.filepath = None;
+                upload_bytes(&self.node, data.clone(), options.filename(name)).await
+            }
+        }
+    }
+}
+
+/// Whether another attempt at an upload that failed with `error` may succeed
+fn is_retryable(error: &CodexError) -> bool {
+    !matches!(
+        error,
+        CodexError::InvalidParameter { .. }
+            | CodexError::ConfigError { .. }
+            | CodexError::EncryptionError { .. }
+            | CodexError::Cancelled { .. }
+    )
+}
+
+/// Delay before the attempt following attempt number `attempts`
+fn backoff(delay: Duration, attempts: usize) -> Duration {
+    delay.saturating_mul(1 << (attempts - 1).min(16))
+}
+
+/// Progress summed over the items of a batch
+struct BatchProgress {
+    sizes: Vec<Option<usize>>,
+    uploaded: Mutex<Vec<usize>>,
+    start_time: Instant,
+    callback: Option<ProgressCallback>,
+}
+
+impl BatchProgress {
+    fn new(sizes: Vec<Option<usize>>, callback: Option<ProgressCallback>) -> Arc<Self> {
+        Arc::new(Self {
+            uploaded: Mutex::new(vec![0; sizes.len()]),
+            sizes,
+            start_time: Instant::now(),
+            callback,
+        })
+    }
+
+    /// Progress callback for the uploads of item `index`
+    fn item_callback(self: &Arc<Self>, index: usize) -> Option<ProgressCallback> {
+        self.callback.as_ref()?;
+        let progress = self.clone();
+        Some(Arc::new(move |item: UploadProgress| {
+            progress.record(index, item.bytes_uploaded)
+        }))
+    }
+
+    /// Record that item `index` has finished, counting all of it as uploaded
+    fn complete(&self, index: usize, uploaded_size: usize) {
+        self.record(index, self.sizes[index].unwrap_or(uploaded_size));
+    }
+
+    /// Record the bytes uploaded so far for item `index`
+    ///
+    /// The callback runs under the lock so that reports arrive in order.
+    fn record(&self, index: usize, bytes: usize) {
+        let mut uploaded = self.uploaded.lock().unwrap();
+        uploaded[index] = bytes;
+
+        if let Some(ref callback) = self.callback {
+            callback(self.snapshot(&uploaded));
+        }
+    }
+
+    fn snapshot(&self, uploaded: &[usize]) -> UploadProgress {
+        let bytes: usize = uploaded.iter().sum();
+        let total = self.sizes.iter().copied().sum::<Option<usize>>();
+        let progress = UploadProgress::new(bytes, total);
+
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            progress.with_speed(bytes as f64 / elapsed)
+        } else {
+            progress
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collecting() -> (Arc<Mutex<Vec<UploadProgress>>>, Option<ProgressCallback>) {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        let callback: ProgressCallback =
+            Arc::new(move |progress| sink.lock().unwrap().push(progress));
+        (seen, Some(callback))
+    }
+
+    #[test]
+    fn test_batch_progress() {
+        let (seen, callback) = collecting();
+        let progress = BatchProgress::new(vec![Some(100), Some(300)], callback);
+
+        let first = progress.item_callback(0).unwrap();
+        first(UploadProgress::new(50, Some(100)));
+        progress.record(1, 150);
+
+        let last = seen.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last.bytes_uploaded, 200);
+        assert_eq!(last.total_bytes, Some(400));
+        assert_eq!(last.percentage, 0.5);
+
+        // A retry starts the item over
+        progress.record(1, 0);
+        progress.complete(0, 120);
+        let last = seen.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last.bytes_uploaded, 100);
+    }
+
+    #[test]
+    fn test_batch_progress_unknown_total() {
+        let (seen, callback) = collecting();
+        let progress = BatchProgress::new(vec![Some(100), None], callback);
+
+        progress.complete(1, 42);
+        let last = seen.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last.bytes_uploaded, 42);
+        assert_eq!(last.total_bytes, None);
+    }
+
+    #[test]
+    fn test_no_item_callback_without_batch_callback() {
+        let progress = BatchProgress::new(vec![Some(1)], None);
+        assert!(progress.item_callback(0).is_none());
+    }
+
+    #[test]
+    fn test_is_retryable() {
+        assert!(is_retryable(&CodexError::upload_error("node busy")));
+        assert!(is_retryable(&CodexError::timeout("upload")));
+        assert!(!is_retryable(&CodexError::invalid_parameter(
+            "filename", "bad"
+        )));
+        assert!(!is_retryable(&CodexError::cancelled("upload")));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let delay = Duration::from_millis(100);
+        assert_eq!(backoff(delay, 1), Duration::from_millis(100));
+        assert_eq!(backoff(delay, 2), Duration::from_millis(200));
+        assert_eq!(backoff(delay, 4), Duration::from_millis(800));
+        assert!(backoff(delay, 1000) > Duration::from_secs(60));
+    }
+}
@@ -4,17 +4,29 @@
 //! as part of an upload session. Chunks are the basic unit of data transfer
 //! in the Codex network.
 
-use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
+use crate::callback::step_deadline;
 use crate::error::{CodexError, Result};
-use crate::ffi::{codex_upload_chunk, free_c_string, string_to_c_string};
 use crate::node::lifecycle::CodexNode;
-use libc::c_void;
+use crate::throttle::Throttle;
+use crate::upload::file::upload_chunk_sync;
+use bytes::Bytes;
 
 /// Upload a chunk of data as part of an ongoing upload session
 ///
 /// Uploads a single chunk of data to the Codex network. The chunk will be
 /// associated with the specified session ID.
 ///
+/// The chunk is handed to libcodex straight from its buffer, so a `Vec<u8>`
+/// or a slice of a larger [`Bytes`] is uploaded without being copied. The
+/// buffer is kept alive until libcodex is done with it, even if this future
+/// is dropped or times out first.
+///
+/// The chunk is held back to respect the node-wide
+/// [`CodexNode::upload_limiter`]. These functions only know the session ID,
+/// so an [`UploadOptions::rate_limit`](crate::upload::UploadOptions::rate_limit)
+/// given to `upload_init` does not apply; use an
+/// [`UploadSession`](crate::upload::UploadSession) for a per-upload limit.
+///
 /// # Arguments
 ///
 /// * `node` - The Codex node to use for the upload
@@ -31,55 +43,23 @@ use libc::c_void;
 /// - The session ID is empty
 /// - The chunk is empty
 /// - The upload fails for any reason
-pub async fn upload_chunk(node: &CodexNode, session_id: &str, chunk: Vec<u8>) -> Result<()> {
+pub async fn upload_chunk(
+    node: &CodexNode,
+    session_id: &str,
+    chunk: impl Into<Bytes>,
+) -> Result<()> {
+    let chunk = chunk.into();
+    Throttle::upload(node, None).acquire(chunk.len()).await;
+    send_chunk(node, session_id, chunk).await
+}
+
+/// Upload a chunk without waiting on any limiter, for callers that throttle
+pub(crate) async fn send_chunk(node: &CodexNode, session_id: &str, chunk: Bytes) -> Result<()> {
     let node = node.clone();
     let session_id = session_id.to_string();
 
     tokio::task::spawn_blocking(move || {
-        if session_id.is_empty() {
-            return Err(CodexError::invalid_parameter(
-                "session_id",
-                "Session ID cannot be empty",
-            ));
-        }
-
-        if chunk.is_empty() {
-            return Err(CodexError::invalid_parameter(
-                "chunk",
-                "Chunk cannot be empty",
-            ));
-        }
-
-        let future = CallbackFuture::new();
-
-        let chunk_ptr = chunk.as_ptr() as *mut u8;
-        let chunk_len = chunk.len();
-        let context_ptr = future.context_ptr() as *mut c_void;
-
-        let result = with_libcodex_lock(|| unsafe {
-            node.with_ctx(|ctx| {
-                let c_session_id = string_to_c_string(&session_id);
-                let result = codex_upload_chunk(
-                    ctx as *mut _,
-                    c_session_id,
-                    chunk_ptr,
-                    chunk_len,
-                    Some(c_callback),
-                    context_ptr,
-                );
-
-                free_c_string(c_session_id);
-
-                result
-            })
-        });
-
-        if result != 0 {
-            return Err(CodexError::upload_error("Failed to upload chunk"));
-        }
-
-        future.wait()?;
-        Ok(())
+        upload_chunk_sync(&node, &session_id, chunk, step_deadline())
     })
     .await?
 }
@@ -88,13 +68,17 @@ pub async fn upload_chunk(node: &CodexNode, session_id: &str, chunk: Vec<u8>) ->
 ///
 /// Convenience function to upload multiple chunks one after another.
 /// This is useful when you have all chunks ready and want to upload them
-/// in a single operation.
+/// in a single operation. All chunks are sent from one blocking task, so
+/// there is no scheduling round-trip between consecutive chunks.
+///
+/// Each chunk is held back to respect the node-wide
+/// [`CodexNode::upload_limiter`], as with [`upload_chunk`].
 ///
 /// # Arguments
 ///
 /// * `node` - The Codex node to use for the upload
 /// * `session_id` - The session ID returned by `upload_init`
-/// * `chunks` - A vector of chunks to upload
+/// * `chunks` - The chunks to upload, in order
 ///
 /// # Returns
 ///
@@ -103,11 +87,25 @@ pub async fn upload_chunk(node: &CodexNode, session_id: &str, chunk: Vec<u8>) ->
 /// # Errors
 ///
 /// Returns an error if any chunk fails to upload
-pub async fn upload_chunks(node: &CodexNode, session_id: &str, chunks: Vec<Vec<u8>>) -> Result<()> {
-    for (index, chunk) in chunks.into_iter().enumerate() {
-        upload_chunk(node, session_id, chunk).await.map_err(|e| {
-            CodexError::upload_error(format!("Failed to upload chunk {}: {}", index, e))
-        })?;
-    }
-    Ok(())
+pub async fn upload_chunks(
+    node: &CodexNode,
+    session_id: &str,
+    chunks: impl IntoIterator<Item = impl Into<Bytes>>,
+) -> Result<()> {
+    let node = node.clone();
+    let session_id = session_id.to_string();
+    let chunks: Vec<Bytes> = chunks.into_iter().map(Into::into).collect();
+
+    let throttle = Throttle::upload(&node, None);
+
+    tokio::task::spawn_blocking(move || {
+        for (index, chunk) in chunks.iter().enumerate() {
+            throttle.acquire_blocking(chunk.len());
+            upload_chunk_sync(&node, &session_id, chunk.clone(), step_deadline()).map_err(|e| {
+                CodexError::upload_error(format!("Failed to upload chunk {}: {}", index, e))
+            })?;
+        }
+        Ok(())
+    })
+    .await?
 }
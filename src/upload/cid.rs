@@ -0,0 +1,404 @@
+//! Offline CID computation
+//!
+//! [`compute_cid`] reproduces the manifest CID a Codex node assigns to content,
+//! without a node. It follows the steps `CodexNodeRef.store` takes in
+//! nim-codex:
+//!
+//! 1. The content is split into `block_size` blocks; the last block is not
+//!    padded.
+//! 2. Each block is hashed with SHA-256. The digests are the leaves of a
+//!    Codex merkle tree, whose inner nodes are `sha256(left || right || key)`
+//!    with a one-byte key marking bottom-layer and odd nodes. An odd node is
+//!    paired with an all-zero hash.
+//! 3. The root becomes the tree CID (`codex-root` codec).
+//! 4. The manifest (tree CID, block size, dataset size, codecs, CID version
+//!    and optional filename and mimetype) is protobuf-encoded, wrapped as the
+//!    data field of a node, and hashed into the manifest CID (`codex-manifest`
+//!    codec).
+//!
+//! The filename and mimetype are part of the manifest, so they must match
+//! what the upload will record for the CIDs to agree.
+//! `CidOptions::try_from(&upload_options)` derives them from the options of
+//! the upload.
+
+use crate::error::{CodexError, Result};
+use crate::upload::mime::mimetype_for_filename;
+use crate::upload::types::UploadOptions;
+use sha2::{Digest, Sha256};
+use std::io::{ErrorKind, Read};
+
+/// Block size used by Codex when none is given
+pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+const CID_VERSION: u64 = 1;
+const SHA2_256: u64 = 0x12;
+const MANIFEST_CODEC: u64 = 0xcd01;
+const BLOCK_CODEC: u64 = 0xcd02;
+const DATASET_ROOT_CODEC: u64 = 0xcd03;
+
+const KEY_NONE: u8 = 0x0;
+const KEY_BOTTOM_LAYER: u8 = 0x1;
+const KEY_ODD: u8 = 0x2;
+const KEY_ODD_AND_BOTTOM_LAYER: u8 = 0x3;
+
+type Hash = [u8; 32];
+
+/// Manifest fields that influence the CID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CidOptions {
+    /// Size of each block in bytes
+    pub block_size: usize,
+    /// Filename recorded in the manifest
+    pub filename: Option<String>,
+    /// Mimetype recorded in the manifest
+    pub mimetype: Option<String>,
+}
+
+impl Default for CidOptions {
+    fn default() -> Self {
+        Self {
+            block_size: DEFAULT_BLOCK_SIZE,
+            filename: None,
+            mimetype: None,
+        }
+    }
+}
+
+impl CidOptions {
+    /// Create new CID options with the default block size
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the block size
+    pub fn block_size(mut self, size: usize) -> Self {
+        self.block_size = size;
+        self
+    }
+
+    /// Set the filename recorded in the manifest
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Set the mimetype recorded in the manifest
+    pub fn mimetype(mut self, mimetype: impl Into<String>) -> Self {
+        self.mimetype = Some(mimetype.into());
+        self
+    }
+}
+
+/// The block size, filename and mimetype an upload with these options records
+///
+/// The filename is the one handed to libcodex, with the extensions added for
+/// the mimetype and compression, and the mimetype is the one libcodex derives
+/// from it. A compressed upload stores the compressed bytes, so the reader
+/// given to [`compute_manifest`] must yield those.
+///
+/// Without a mimetype or a filename extension that implies one, the upload
+/// sniffs the mimetype from the content, which these options cannot know;
+/// set [`UploadOptions::mimetype`] to make the CID predictable.
+///
+/// Fails if the upload itself would reject the filename or mimetype.
+impl TryFrom<&UploadOptions> for CidOptions {
+    type Error = CodexError;
+
+    fn try_from(options: &UploadOptions) -> Result<Self> {
+        let name = options.upload_name()?;
+        let mut cid_options =
+            CidOptions::new().block_size(options.chunk_size.unwrap_or(1024 * 1024));
+        if !name.is_empty() {
+            cid_options.mimetype = mimetype_for_filename(&name).map(str::to_string);
+            cid_options.filename = Some(name);
+        }
+        Ok(cid_options)
+    }
+}
+
+/// Result of [`compute_manifest`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComputedManifest {
+    /// Manifest CID, the CID an upload of the same content returns
+    pub cid: String,
+    /// CID of the merkle tree root
+    pub tree_cid: String,
+    /// Size of the content in bytes
+    pub dataset_size: usize,
+    /// Size of each block in bytes
+    pub block_size: usize,
+    /// Number of blocks the content was split into
+    pub blocks: usize,
+}
+
+/// Compute the CID Codex would assign to the content of `reader`
+///
+/// `block_size` must match the chunk size the upload will use. No filename
+/// or mimetype is assumed, so the CID only matches uploads that record
+/// neither. Uploads that are named, have a mimetype sniffed from their
+/// content or are compressed get a different CID; use [`compute_manifest`]
+/// with [`CidOptions::try_from`] their [`UploadOptions`] for those.
+pub fn compute_cid<R: Read>(reader: R, block_size: usize) -> Result<String> {
+    let options = CidOptions::new().block_size(block_size);
+    Ok(compute_manifest(reader, &options)?.cid)
+}
+
+/// Compute the manifest Codex would create for the content of `reader`
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `block_size` is 0
+/// - The content is empty; Codex refuses to store empty datasets
+/// - The reader fails
+pub fn compute_manifest<R: Read>(mut reader: R, options: &CidOptions) -> Result<ComputedManifest> {
+    if options.block_size == 0 {
+        return Err(CodexError::invalid_parameter(
+            "block_size",
+            "Block size must be greater than 0",
+        ));
+    }
+
+    let mut buffer = vec![0u8; options.block_size];
+    let mut leaves = Vec::new();
+    let mut dataset_size = 0;
+
+    loop {
+        let n = read_full(&mut reader, &mut buffer)?;
+        if n == 0 {
+            break;
+        }
+
+        leaves.push(Sha256::digest(&buffer[..n]).into());
+        dataset_size += n;
+
+        if n < buffer.len() {
+            break;
+        }
+    }
+
+    if leaves.is_empty() {
+        return Err(CodexError::invalid_parameter(
+            "reader",
+            "Cannot compute the CID of empty content",
+        ));
+    }
+
+    let root = merkle_root(&leaves);
+    let tree_cid = cid_bytes(DATASET_ROOT_CODEC, &root);
+
+    let mut header = Vec::new();
+    write_bytes(&mut header, 1, &tree_cid);
+    write_varint_field(&mut header, 2, options.block_size as u64);
+    write_varint_field(&mut header, 3, dataset_size as u64);
+    write_varint_field(&mut header, 4, BLOCK_CODEC);
+    write_varint_field(&mut header, 5, SHA2_256);
+    write_varint_field(&mut header, 6, CID_VERSION);
+    if let Some(ref filename) = options.filename {
+        write_bytes(&mut header, 8, filename.as_bytes());
+    }
+    if let Some(ref mimetype) = options.mimetype {
+        write_bytes(&mut header, 9, mimetype.as_bytes());
+    }
+
+    let mut node = Vec::new();
+    write_bytes(&mut node, 1, &header);
+
+    let manifest_hash: Hash = Sha256::digest(&node).into();
+
+    Ok(ComputedManifest {
+        cid: cid_string(&cid_bytes(MANIFEST_CODEC, &manifest_hash)),
+        tree_cid: cid_string(&tree_cid),
+        dataset_size,
+        block_size: options.block_size,
+        blocks: leaves.len(),
+    })
+}
+
+/// Root of the Codex merkle tree over `leaves`
+fn merkle_root(leaves: &[Hash]) -> Hash {
+    let mut layer = leaves.to_vec();
+    let mut bottom = true;
+
+    // Above the bottom layer a single node is the root; the bottom layer is
+    // always compressed, even when it holds one leaf.
+    while bottom || layer.len() > 1 {
+        let (even_key, odd_key) = if bottom {
+            (KEY_BOTTOM_LAYER, KEY_ODD_AND_BOTTOM_LAYER)
+        } else {
+            (KEY_NONE, KEY_ODD)
+        };
+
+        layer = layer
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => compress(left, right, even_key),
+                [odd] => compress(odd, &[0u8; 32], odd_key),
+                _ => unreachable!(),
+            })
+            .collect();
+        bottom = false;
+    }
+
+    layer[0]
+}
+
+fn compress(left: &Hash, right: &Hash, key: u8) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.update([key]);
+    hasher.finalize().into()
+}
+
+/// Binary CIDv1 with a SHA-256 multihash
+fn cid_bytes(codec: u64, digest: &Hash) -> Vec<u8> {
+    let mut cid = Vec::with_capacity(40);
+    write_varint(&mut cid, CID_VERSION);
+    write_varint(&mut cid, codec);
+    write_varint(&mut cid, SHA2_256);
+    write_varint(&mut cid, digest.len() as u64);
+    cid.extend_from_slice(digest);
+    cid
+}
+
+/// Base58btc multibase string, as printed by Codex
+fn cid_string(cid: &[u8]) -> String {
+    format!("z{}", bs58::encode(cid).into_string())
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(out, field << 3);
+    write_varint(out, value);
+}
+
+fn write_bytes(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(out, (field << 3) | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Fill `buffer` from `reader`, stopping early only at end of input
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_varint() {
+        let mut out = Vec::new();
+        write_varint(&mut out, MANIFEST_CODEC);
+        assert_eq!(out, vec![0x81, 0x9a, 0x03]);
+    }
+
+    #[test]
+    fn test_cid_prefixes() {
+        // Codex manifest and tree CIDs are recognizable by their prefixes
+        let manifest = compute_manifest(Cursor::new(b"hello codex"), &CidOptions::new()).unwrap();
+        assert!(manifest.cid.starts_with("zDv"), "{}", manifest.cid);
+        assert!(
+            manifest.tree_cid.starts_with("zDz"),
+            "{}",
+            manifest.tree_cid
+        );
+        assert_eq!(manifest.dataset_size, 11);
+        assert_eq!(manifest.blocks, 1);
+    }
+
+    #[test]
+    fn test_block_splitting() {
+        let data = vec![1u8; 10];
+        let manifest =
+            compute_manifest(Cursor::new(&data), &CidOptions::new().block_size(4)).unwrap();
+        assert_eq!(manifest.blocks, 3);
+        assert_eq!(manifest.dataset_size, 10);
+
+        let exact =
+            compute_manifest(Cursor::new(&data[..8]), &CidOptions::new().block_size(4)).unwrap();
+        assert_eq!(exact.blocks, 2);
+    }
+
+    #[test]
+    fn test_merkle_root_shape() {
+        let a = [1u8; 32];
+        let b = [2u8; 32];
+        let c = [3u8; 32];
+        let zero = [0u8; 32];
+
+        assert_eq!(
+            merkle_root(&[a]),
+            compress(&a, &zero, KEY_ODD_AND_BOTTOM_LAYER)
+        );
+        assert_eq!(merkle_root(&[a, b]), compress(&a, &b, KEY_BOTTOM_LAYER));
+        assert_eq!(
+            merkle_root(&[a, b, c]),
+            compress(
+                &compress(&a, &b, KEY_BOTTOM_LAYER),
+                &compress(&c, &zero, KEY_ODD_AND_BOTTOM_LAYER),
+                KEY_NONE
+            )
+        );
+    }
+
+    #[test]
+    fn test_manifest_fields_change_cid() {
+        let data = b"same content";
+        let plain = compute_cid(Cursor::new(data), 1024).unwrap();
+        assert_eq!(plain, compute_cid(Cursor::new(data), 1024).unwrap());
+        assert_ne!(plain, compute_cid(Cursor::new(data), 512).unwrap());
+
+        let named = compute_manifest(
+            Cursor::new(data),
+            &CidOptions::new().block_size(1024).filename("a.txt"),
+        )
+        .unwrap();
+        assert_ne!(plain, named.cid);
+    }
+
+    #[test]
+    fn test_options_from_upload_options() {
+        let options = UploadOptions::new()
+            .filename("counts")
+            .mimetype("text/csv")
+            .chunk_size(1024);
+        let cid_options = CidOptions::try_from(&options).unwrap();
+        assert_eq!(cid_options.block_size, 1024);
+        assert_eq!(cid_options.filename.as_deref(), Some("counts.csv"));
+        assert_eq!(cid_options.mimetype.as_deref(), Some("text/csv"));
+
+        let unnamed = CidOptions::try_from(&UploadOptions::new()).unwrap();
+        assert_eq!(unnamed.block_size, 1024 * 1024);
+        assert_eq!(unnamed.filename, None);
+        assert_eq!(unnamed.mimetype, None);
+
+        let unknown = UploadOptions::new().mimetype("application/x-unknown-kind");
+        assert!(CidOptions::try_from(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(compute_cid(Cursor::new(b"data"), 0).is_err());
+        assert!(compute_cid(Cursor::new(Vec::new()), 1024).is_err());
+    }
+}
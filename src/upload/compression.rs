@@ -0,0 +1,382 @@
+//! Transparent compression for uploads
+//!
+//! With [`UploadOptions::compression`](crate::upload::UploadOptions::compression)
+//! set, [`upload_file`](crate::upload::upload_file),
+//! [`upload_reader`](crate::upload::upload_reader),
+//! [`upload_async_reader`](crate::upload::upload_async_reader) and
+//! [`upload_bytes`](crate::upload::upload_bytes) compress content as it is read,
+//! so what the node stores (and what counts against its quota) is the
+//! compressed form.
+//!
+//! The codec is recorded in the manifest: `.zst` is appended to the filename,
+//! which makes libcodex record `application/zstd` as the mimetype.
+//! [`download_stream`](crate::download::download_stream) with
+//! [`DownloadStreamOptions::decompress`](crate::download::DownloadStreamOptions::decompress)
+//! looks for these and decompresses on the fly.
+
+use crate::error::{CodexError, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use zstd::stream::raw::{Decoder, Encoder, InBuffer, Operation, OutBuffer};
+
+/// Zstandard level used by [`Compression::zstd`]
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+
+/// Mimetype libcodex records for zstd-compressed uploads
+pub const ZSTD_MIMETYPE: &str = "application/zstd";
+
+const ZSTD_EXTENSION: &str = "zst";
+
+/// Filename recorded for compressed uploads that have none
+const DEFAULT_NAME: &str = "data";
+
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// How content is compressed before it is uploaded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// Upload content as it is
+    #[default]
+    None,
+    /// Compress content with Zstandard at the given level
+    Zstd { level: i32 },
+}
+
+impl Compression {
+    /// Zstandard at [`DEFAULT_ZSTD_LEVEL`]
+    pub fn zstd() -> Self {
+        Compression::Zstd {
+            level: DEFAULT_ZSTD_LEVEL,
+        }
+    }
+
+    /// Whether content is compressed at all
+    pub fn is_enabled(&self) -> bool {
+        *self != Compression::None
+    }
+
+    /// Check that the compression level is supported
+    pub fn validate(&self) -> Result<()> {
+        if let Compression::Zstd { level } = *self {
+            if !zstd::compression_level_range().contains(&level) {
+                return Err(CodexError::invalid_parameter(
+                    "compression",
+                    format!(
+                        "Zstd level must be between {} and {}",
+                        zstd::compression_level_range().start(),
+                        zstd::compression_level_range().end()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Filename recorded for an upload that would otherwise record `filename`
+    pub(crate) fn recorded_name(&self, filename: Option<&str>) -> Option<String> {
+        match self {
+            Compression::None => filename.map(str::to_string),
+            Compression::Zstd { .. } => Some(format!(
+                "{}.{}",
+                filename.unwrap_or(DEFAULT_NAME),
+                ZSTD_EXTENSION
+            )),
+        }
+    }
+
+    /// Compression recorded in a manifest with `filename` and `mimetype`
+    pub fn from_manifest(filename: &str, mimetype: &str) -> Self {
+        let zst_name = filename
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| extension.eq_ignore_ascii_case(ZSTD_EXTENSION));
+
+        if mimetype.eq_ignore_ascii_case(ZSTD_MIMETYPE) || zst_name {
+            Compression::zstd()
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wrap `reader` so that it yields its content compressed with `compression`
+pub(crate) fn compress_reader<R>(
+    reader: R,
+    compression: Compression,
+) -> Result<Box<dyn Read + Send>>
+where
+    R: Read + Send + 'static,
+{
+    Ok(match compression {
+        Compression::None => Box::new(reader),
+        Compression::Zstd { level } => Box::new(zstd::stream::read::Encoder::new(reader, level)?),
+    })
+}
+
+/// Compress a whole buffer
+pub(crate) fn compress_bytes(data: &[u8], compression: Compression) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Zstd { level } => Ok(zstd::bulk::compress(data, level)?),
+    }
+}
+
+/// AsyncRead adapter compressing another AsyncRead
+///
+/// Passes content through unchanged when compression is disabled.
+pub(crate) struct AsyncCompressor<R> {
+    inner: R,
+    encoder: Option<Encoder<'static>>,
+    input: Vec<u8>,
+    input_pos: usize,
+    input_len: usize,
+    output: Vec<u8>,
+    output_pos: usize,
+    output_len: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> AsyncCompressor<R> {
+    pub fn new(inner: R, compression: Compression) -> Result<Self> {
+        let encoder = match compression {
+            Compression::None => None,
+            Compression::Zstd { level } => Some(Encoder::new(level)?),
+        };
+
+        Ok(Self {
+            inner,
+            encoder,
+            input: vec![0u8; BUFFER_SIZE],
+            input_pos: 0,
+            input_len: 0,
+            output: vec![0u8; BUFFER_SIZE],
+            output_pos: 0,
+            output_len: 0,
+            eof: false,
+            done: false,
+        })
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for AsyncCompressor<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        let encoder = match this.encoder {
+            Some(ref mut encoder) => encoder,
+            None => return Pin::new(&mut this.inner).poll_read(cx, buf),
+        };
+
+        loop {
+            if this.output_pos < this.output_len {
+                let n = buf.remaining().min(this.output_len - this.output_pos);
+                buf.put_slice(&this.output[this.output_pos..this.output_pos + n]);
+                this.output_pos += n;
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.done {
+                return Poll::Ready(Ok(()));
+            }
+
+            if this.input_pos == this.input_len && !this.eof {
+                let mut read = ReadBuf::new(&mut this.input);
+                ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+                this.input_len = read.filled().len();
+                this.input_pos = 0;
+                this.eof = this.input_len == 0;
+            }
+
+            let mut output = OutBuffer::around(&mut this.output[..]);
+            if this.input_pos < this.input_len {
+                let mut input = InBuffer::around(&this.input[this.input_pos..this.input_len]);
+                encoder.run(&mut input, &mut output)?;
+                this.input_pos += input.pos();
+            } else {
+                // End of input: flush the frame until nothing remains
+                this.done = encoder.finish(&mut output, true)? == 0;
+            }
+            this.output_len = output.pos();
+            this.output_pos = 0;
+        }
+    }
+}
+
+/// Writer decompressing zstd content written to it
+///
+/// Unlike `zstd::stream::write::Decoder`, [`finish`](DecompressingWriter::finish)
+/// reports content that ends in the middle of a frame.
+pub(crate) struct DecompressingWriter<W> {
+    inner: W,
+    decoder: Decoder<'static>,
+    output: Vec<u8>,
+    frame_complete: bool,
+    bytes_written: usize,
+}
+
+impl<W: Write> DecompressingWriter<W> {
+    pub fn new(inner: W) -> Result<Self> {
+        Ok(Self {
+            inner,
+            decoder: Decoder::new()?,
+            output: vec![0u8; BUFFER_SIZE],
+            frame_complete: true,
+            bytes_written: 0,
+        })
+    }
+
+    /// Number of decompressed bytes written so far
+    pub fn bytes_written(&self) -> usize {
+        self.bytes_written
+    }
+
+    /// Flush the inner writer, failing if the content was truncated
+    pub fn finish(&mut self) -> Result<()> {
+        if !self.frame_complete {
+            return Err(CodexError::download_error(
+                "Compressed content ends in the middle of a frame",
+            ));
+        }
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for DecompressingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut input = InBuffer::around(buf);
+
+        loop {
+            let mut output = OutBuffer::around(&mut self.output[..]);
+            let hint = self.decoder.run(&mut input, &mut output)?;
+            let produced = output.pos();
+
+            self.inner.write_all(&self.output[..produced])?;
+            self.bytes_written += produced;
+            self.frame_complete = hint == 0;
+
+            // A full output buffer may leave decoded data inside the decoder
+            if input.pos() == buf.len() && produced < self.output.len() {
+                break;
+            }
+        }
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    fn sample() -> Vec<u8> {
+        (0..200_000)
+            .map(|i| format!("{{\"line\":{},\"level\":\"info\"}}\n", i % 1000).into_bytes())
+            .take(5000)
+            .flatten()
+            .collect()
+    }
+
+    #[test]
+    fn test_recorded_name() {
+        assert_eq!(Compression::None.recorded_name(None), None);
+        assert_eq!(
+            Compression::None.recorded_name(Some("logs.json")),
+            Some("logs.json".to_string())
+        );
+        assert_eq!(
+            Compression::zstd().recorded_name(Some("logs.json")),
+            Some("logs.json.zst".to_string())
+        );
+        assert_eq!(
+            Compression::zstd().recorded_name(None),
+            Some("data.zst".to_string())
+        );
+    }
+
+    #[test]
+    fn test_from_manifest() {
+        assert_eq!(
+            Compression::from_manifest("logs.json.zst", ZSTD_MIMETYPE),
+            Compression::zstd()
+        );
+        assert_eq!(
+            Compression::from_manifest("archive.ZST", ""),
+            Compression::zstd()
+        );
+        assert_eq!(
+            Compression::from_manifest("logs.json", "application/json"),
+            Compression::None
+        );
+        assert_eq!(Compression::from_manifest("", ""), Compression::None);
+    }
+
+    #[test]
+    fn test_validate_level() {
+        assert!(Compression::zstd().validate().is_ok());
+        assert!(Compression::Zstd { level: 19 }.validate().is_ok());
+        assert!(Compression::Zstd { level: 1000 }.validate().is_err());
+    }
+
+    #[test]
+    fn test_reader_roundtrip() {
+        let data = sample();
+        let mut compressed = Vec::new();
+        compress_reader(std::io::Cursor::new(data.clone()), Compression::zstd())
+            .unwrap()
+            .read_to_end(&mut compressed)
+            .unwrap();
+        assert!(compressed.len() * 5 < data.len());
+
+        let mut writer = DecompressingWriter::new(Vec::new()).unwrap();
+        for piece in compressed.chunks(1000) {
+            writer.write_all(piece).unwrap();
+        }
+        writer.finish().unwrap();
+        assert_eq!(writer.bytes_written(), data.len());
+        assert_eq!(writer.inner, data);
+    }
+
+    #[test]
+    fn test_truncated_content() {
+        let compressed = compress_bytes(&sample(), Compression::zstd()).unwrap();
+        let mut writer = DecompressingWriter::new(Vec::new()).unwrap();
+        writer
+            .write_all(&compressed[..compressed.len() - 10])
+            .unwrap();
+        assert!(writer.finish().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_async_compressor() {
+        let data = sample();
+        let mut compressed = Vec::new();
+        AsyncCompressor::new(&data[..], Compression::zstd())
+            .unwrap()
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+        assert_eq!(zstd::stream::decode_all(&compressed[..]).unwrap(), data);
+
+        let mut passthrough = Vec::new();
+        AsyncCompressor::new(&data[..], Compression::None)
+            .unwrap()
+            .read_to_end(&mut passthrough)
+            .await
+            .unwrap();
+        assert_eq!(passthrough, data);
+    }
+}
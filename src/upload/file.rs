@@ -4,14 +4,23 @@
 //! and readers to the Codex network. These functions handle the complete
 //! upload lifecycle including session management and chunking.
 
-use crate::callback::{c_callback, CallbackFuture};
+use crate::callback::{c_callback, deadline_after, CallbackFuture};
 use crate::error::{CodexError, Result};
 use crate::ffi::{codex_upload_file, free_c_string, string_to_c_string};
 use crate::node::lifecycle::CodexNode;
-use crate::upload::types::{UploadOptions, UploadProgress, UploadResult};
+use crate::upload::compression::compress_reader;
+use crate::upload::mime::SNIFF_LEN;
+use crate::upload::resume::{send_reader, SendTarget, UploadJournal};
+use crate::upload::types::{UploadOptions, UploadProgress, UploadResult, UploadStrategy};
+use crate::upload::verify::{
+    hash_reader, verify_result, wants_digest, ContentHasher, HashingReader,
+};
+use bytes::Bytes;
 use libc::c_void;
 use std::io::Read;
 use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 /// Upload a file from the filesystem
 ///
@@ -19,6 +28,24 @@ use std::path::Path;
 /// This function handles the complete upload process including file validation,
 /// session creation, and progress tracking.
 ///
+/// The path taken follows [`UploadOptions::file_strategy`]: with
+/// [`UploadStrategy::Stream`] the node reads the file itself, with
+/// [`UploadStrategy::Chunked`] it is sent through an upload session like
+/// [`upload_reader`]. The strategy used is recorded in the result.
+///
+/// [`UploadOptions::on_progress`] is called on both paths. Natively streamed
+/// files report progress as the node stores each block, measured against the
+/// size of the file when the upload started.
+///
+/// The node reads natively streamed files itself, so rate limits cannot
+/// apply to them: `Auto` uses the chunked path while
+/// [`CodexNode::upload_limiter`] has a rate set, and `Stream` is rejected
+/// together with [`UploadOptions::rate_limit`]. For the same reason nothing
+/// can be journaled, so `Stream` is rejected with [`UploadOptions::journal`].
+///
+/// The whole upload must finish within [`UploadOptions::timeout`] seconds;
+/// otherwise the session is cancelled and [`CodexError::Timeout`] is returned.
+///
 /// # Arguments
 ///
 /// * `node` - The Codex node to use for the upload
@@ -35,33 +62,98 @@ use std::path::Path;
 /// - The file doesn't exist
 /// - The upload fails for any reason
 pub async fn upload_file(node: &CodexNode, options: UploadOptions) -> Result<UploadResult> {
-    let node = node.clone();
-    let options = options.clone();
-
-    tokio::task::spawn_blocking(move || {
-        if options.filepath.is_none() {
+    let filepath = match options.filepath {
+        Some(ref filepath) => filepath.clone(),
+        None => {
             return Err(CodexError::invalid_parameter(
                 "filepath",
                 "File path must be specified for file upload",
-            ));
+            ))
         }
+    };
 
-        let filepath = options.filepath.as_ref().unwrap();
+    if !Path::new(&filepath).exists() {
+        return Err(CodexError::invalid_parameter(
+            "filepath",
+            format!("File does not exist: {}", filepath.display()),
+        ));
+    }
 
-        if !Path::new(filepath).exists() {
-            return Err(CodexError::invalid_parameter(
-                "filepath",
-                format!("File does not exist: {}", filepath.display()),
-            ));
-        }
+    // The node reads natively streamed files itself, past any rate limit
+    let throttled = options.strategy == UploadStrategy::Auto && node.upload_limiter().is_limited();
+    if throttled || options.file_strategy() == UploadStrategy::Chunked {
+        let file = std::fs::File::open(&filepath)?;
+        return upload_reader(node, options, file).await;
+    }
+
+    if options.filename.is_some() || options.mimetype.is_some() {
+        return Err(CodexError::invalid_parameter(
+            "strategy",
+            "Stream uploads record the name of the file on disk; use Chunked to set a filename or mimetype",
+        ));
+    }
+
+    if options.compression.is_enabled() {
+        return Err(CodexError::invalid_parameter(
+            "strategy",
+            "Stream uploads send the file as it is on disk; use Chunked to compress it",
+        ));
+    }
+
+    if options.rate_limit.is_some() {
+        return Err(CodexError::invalid_parameter(
+            "strategy",
+            "Stream uploads are read by the node and cannot be rate limited; use Chunked",
+        ));
+    }
+
+    if options.journal.is_some() {
+        return Err(CodexError::invalid_parameter(
+            "strategy",
+            "Stream uploads are read by the node and cannot be journaled; use Chunked",
+        ));
+    }
+
+    let task_node = node.clone();
+    let task_options = options.clone();
+
+    let (result, digest) = tokio::task::spawn_blocking(move || {
+        let node = task_node;
+        let options = task_options;
+        let filepath = &filepath;
 
         let start_time = std::time::Instant::now();
+        let deadline = deadline_after(options.timeout);
 
         let file_size = std::fs::metadata(filepath)?.len() as usize;
+        let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+        let progress = Arc::new(Mutex::new(FileProgress::new(file_size, chunk_size)));
+        let timed_out = |e: CodexError| {
+            let bytes = progress.lock().unwrap().bytes_uploaded;
+            e.with_timeout_progress("upload", Some(bytes), Some(file_size))
+        };
 
-        let session_id = upload_init_sync(&node, &options)?;
+        // libcodex reads the file from the path the session was opened with
+        let path = filepath.to_str().ok_or_else(|| {
+            CodexError::invalid_parameter("filepath", "File path must be valid UTF-8")
+        })?;
+        let future = CallbackFuture::new();
+        start_upload_init_named(&node, path, chunk_size, &future)?;
+        let session_id = future.wait_until(deadline).map_err(timed_out)?;
+        node.uploads().register(&session_id);
 
         let future = CallbackFuture::new();
+        let on_progress = options.on_progress.clone();
+        let tracker = progress.clone();
+        let registry = node.uploads().clone();
+        let tracked_id = session_id.clone();
+        future.set_progress_callback(move |len, _| {
+            registry.record_chunk(&tracked_id, len);
+            let update = tracker.lock().unwrap().record(len);
+            if let Some(ref callback) = on_progress {
+                callback(update);
+            }
+        });
 
         let context_ptr = future.context_ptr() as *mut c_void;
 
@@ -82,15 +174,82 @@ pub async fn upload_file(node: &CodexNode, options: UploadOptions) -> Result<Upl
             return Err(CodexError::library_error("Failed to upload file"));
         }
 
-        let cid = future.wait()?;
+        let waited = future.wait_until(deadline);
+        future.context.clear_progress_callback();
+
+        let cid = match waited {
+            Ok(cid) => cid,
+            Err(e) => {
+                let _ = upload_cancel_sync(&node, &session_id);
+                return Err(timed_out(e));
+            }
+        };
+        node.uploads().remove(&session_id);
 
         let duration = start_time.elapsed();
+        let chunks = progress.lock().unwrap().chunks_stored;
 
-        Ok(UploadResult::new(cid, file_size)
+        let digest = if wants_digest(&options) {
+            Some(hash_reader(std::fs::File::open(filepath)?)?)
+        } else {
+            None
+        };
+
+        let mut result = UploadResult::new(cid, file_size)
             .duration_ms(duration.as_millis() as u64)
-            .verified(options.verify))
+            .strategy(UploadStrategy::Stream);
+        if chunks > 0 {
+            result = result.chunks(chunks);
+        }
+        Ok((result, digest))
     })
-    .await?
+    .await??;
+
+    verify_result(node, &options, result, digest).await
+}
+
+/// Progress of a file the node reads itself
+///
+/// libcodex reports every block it stores from the file with the block size
+/// in the callback length.
+struct FileProgress {
+    total_bytes: usize,
+    total_chunks: usize,
+    bytes_uploaded: usize,
+    chunks_stored: usize,
+    start_time: Instant,
+}
+
+impl FileProgress {
+    fn new(total_bytes: usize, chunk_size: usize) -> Self {
+        Self {
+            total_bytes,
+            total_chunks: total_bytes.div_ceil(chunk_size).max(1),
+            bytes_uploaded: 0,
+            chunks_stored: 0,
+            start_time: Instant::now(),
+        }
+    }
+
+    /// Account for a stored block of `len` bytes
+    fn record(&mut self, len: usize) -> UploadProgress {
+        self.bytes_uploaded = (self.bytes_uploaded + len).min(self.total_bytes);
+        self.chunks_stored += 1;
+
+        let progress = UploadProgress::new_chunked(
+            self.bytes_uploaded,
+            Some(self.total_bytes),
+            self.chunks_stored.min(self.total_chunks),
+            self.total_chunks,
+        );
+
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            progress.with_speed(self.bytes_uploaded as f64 / elapsed)
+        } else {
+            progress
+        }
+    }
 }
 
 /// Upload data from any Read implementation
@@ -99,6 +258,26 @@ pub async fn upload_file(node: &CodexNode, options: UploadOptions) -> Result<Upl
 /// This is useful for uploading data from memory, network streams, or custom sources.
 /// The function handles chunking the data and tracking progress.
 ///
+/// The manifest records [`UploadOptions::filename`] and
+/// [`UploadOptions::mimetype`]. When the filename has no recognized extension
+/// and no mimetype is given, the mimetype is sniffed from the first bytes of
+/// the content.
+///
+/// With [`UploadOptions::compression`] set, the content is compressed as it
+/// is read and the size in the result is the compressed size.
+///
+/// The reader is consumed on a separate thread that stays up to
+/// [`UploadOptions::read_ahead`] chunks ahead of the node, so reading and
+/// uploading overlap. Set `read_ahead(0)` to read and upload strictly in turn.
+///
+/// With [`UploadOptions::journal`] set, progress is recorded in a journal file
+/// and the session is left open on failure, so the upload can be continued
+/// with [`resume_upload`](crate::upload::resume_upload).
+///
+/// The whole upload must finish within [`UploadOptions::timeout`] seconds. On
+/// expiry the session is cancelled, unless it is journaled, and
+/// [`CodexError::Timeout`] reports the bytes sent so far.
+///
 /// # Arguments
 ///
 /// * `node` - The Codex node to use for the upload
@@ -112,6 +291,9 @@ pub async fn upload_file(node: &CodexNode, options: UploadOptions) -> Result<Upl
 /// # Errors
 ///
 /// Returns an error if:
+/// - The strategy is [`UploadStrategy::Stream`], which needs a file
+/// - [`UploadOptions::segment_size`] is set, which needs
+///   [`resume_upload`](crate::upload::resume_upload)
 /// - The reader fails
 /// - The upload fails for any reason
 pub async fn upload_reader<R>(
@@ -122,77 +304,140 @@ pub async fn upload_reader<R>(
 where
     R: Read + Send + 'static,
 {
-    let node = node.clone();
+    options.reader_strategy()?;
 
-    tokio::task::spawn_blocking(move || {
+    if options.segment_size.is_some() {
+        return Err(CodexError::invalid_parameter(
+            "segment_size",
+            "Segmented uploads are started and continued with resume_upload",
+        ));
+    }
+
+    let task_node = node.clone();
+    let task_options = options.clone();
+    let hasher = wants_digest(&options).then(ContentHasher::default);
+    let task_hasher = hasher.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let node = task_node;
+        let options = task_options;
         options.validate()?;
 
         let start_time = std::time::Instant::now();
+        let deadline = deadline_after(options.timeout);
         let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
 
-        let session_id = upload_init_sync(&node, &options)?;
-
-        let mut buffer = vec![0u8; chunk_size];
-        let mut total_bytes = 0;
-        let mut chunk_count = 0;
         let mut reader = reader;
+        let mut head = Vec::new();
+        if options.needs_sniff() {
+            reader
+                .by_ref()
+                .take(SNIFF_LEN as u64)
+                .read_to_end(&mut head)?;
+        }
+        let options = options.sniffed(&head);
+        let reader = compress_reader(
+            std::io::Cursor::new(head).chain(reader),
+            options.compression,
+        )?;
+
+        let session_id = upload_init_sync(&node, &options, deadline)
+            .map_err(|e| e.with_timeout_progress("upload", Some(0), None))?;
+
+        let mut journal = match options.journal {
+            Some(ref path) => Some(UploadJournal::start(&node, path, &session_id, chunk_size)?),
+            None => None,
+        };
+
+        let reader: Box<dyn Read + Send> = match task_hasher {
+            Some(hasher) => Box::new(HashingReader::new(reader, hasher)),
+            None => Box::new(reader),
+        };
 
-        loop {
-            match reader.read(&mut buffer) {
-                Ok(0) => break,
-                Ok(n) => {
-                    total_bytes += n;
-                    chunk_count += 1;
-
-                    upload_chunk_sync(&node, &session_id, &buffer[..n])?;
-
-                    if let Some(ref callback) = options.on_progress {
-                        let progress = UploadProgress::new_chunked(
-                            total_bytes,
-                            None,
-                            chunk_count,
-                            chunk_count,
-                        );
-                        callback(progress);
-                    }
+        let sent = send_reader(
+            &node,
+            &options,
+            reader,
+            SendTarget {
+                session_id: &session_id,
+                chunk_size,
+                deadline,
+            },
+            journal.as_mut(),
+            (0, 0),
+        );
+
+        let (total_bytes, chunk_count) = match sent {
+            Ok(totals) => totals,
+            Err(e) => {
+                // A journaled session is kept open so it can be resumed
+                if journal.is_none() {
+                    let _ = upload_cancel_sync(&node, &session_id);
                 }
-                Err(e) => {
+                return Err(e);
+            }
+        };
+
+        let cid = match upload_finalize_sync(&node, &session_id, deadline) {
+            Ok(cid) => cid,
+            Err(e) => {
+                if journal.is_none() {
                     let _ = upload_cancel_sync(&node, &session_id);
-                    return Err(CodexError::from(e));
                 }
+                return Err(e.with_timeout_progress(
+                    "upload",
+                    Some(total_bytes),
+                    Some(total_bytes),
+                ));
             }
-        }
+        };
 
-        let cid = upload_finalize_sync(&node, &session_id)?;
+        if let Some(journal) = journal {
+            journal.remove()?;
+        }
 
         let duration = start_time.elapsed();
 
         Ok(UploadResult::new(cid, total_bytes)
             .chunks(chunk_count)
             .duration_ms(duration.as_millis() as u64)
-            .verified(options.verify))
+            .strategy(UploadStrategy::Chunked)
+            .compression(options.compression))
     })
-    .await?
+    .await??;
+
+    let digest = hasher.map(|hasher| hasher.finish());
+    verify_result(node, &options, result, digest).await
 }
 
-/// Synchronous version of upload_init for internal use
-fn upload_init_sync(node: &CodexNode, options: &UploadOptions) -> Result<String> {
+/// Issue `codex_upload_init`; the session ID arrives through `future`
+pub(crate) fn start_upload_init(
+    node: &CodexNode,
+    options: &UploadOptions,
+    future: &CallbackFuture,
+) -> Result<()> {
     options.validate()?;
 
-    let future = CallbackFuture::new();
-
-    let filepath_str = options
-        .filepath
-        .as_ref()
-        .and_then(|p| p.to_str())
-        .unwrap_or("");
+    start_upload_init_named(
+        node,
+        &options.upload_name()?,
+        options.chunk_size.unwrap_or(1024 * 1024),
+        future,
+    )
+}
 
-    let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+/// Issue `codex_upload_init` with the path libcodex records as the filename
+fn start_upload_init_named(
+    node: &CodexNode,
+    name: &str,
+    chunk_size: usize,
+    future: &CallbackFuture,
+) -> Result<()> {
     let context_ptr = future.context_ptr() as *mut c_void;
 
     let result = crate::callback::with_libcodex_lock(|| unsafe {
         node.with_ctx(|ctx| {
-            let c_filepath = crate::ffi::string_to_c_string(filepath_str);
+            let c_filepath = crate::ffi::string_to_c_string(name);
             let result = crate::ffi::codex_upload_init(
                 ctx as *mut _,
                 c_filepath,
@@ -213,12 +458,33 @@ fn upload_init_sync(node: &CodexNode, options: &UploadOptions) -> Result<String>
         return Err(CodexError::upload_error("Failed to initialize upload"));
     }
 
-    let session_id = future.wait()?;
+    Ok(())
+}
+
+/// Synchronous version of upload_init for internal use, waiting until `deadline`
+pub(crate) fn upload_init_sync(
+    node: &CodexNode,
+    options: &UploadOptions,
+    deadline: Option<Instant>,
+) -> Result<String> {
+    let future = CallbackFuture::new();
+    start_upload_init(node, options, &future)?;
+
+    let session_id = future.wait_until(deadline)?;
+    node.uploads().register(&session_id);
     Ok(session_id)
 }
 
-/// Synchronous version of upload_chunk for internal use
-fn upload_chunk_sync(node: &CodexNode, session_id: &str, chunk: &[u8]) -> Result<()> {
+/// Issue `codex_upload_chunk`; completion is reported through `future`
+///
+/// `future` holds on to `chunk` until libcodex reports completion, so the
+/// node can keep reading it after `future` is dropped or a deadline passes.
+pub(crate) fn start_upload_chunk(
+    node: &CodexNode,
+    session_id: &str,
+    chunk: Bytes,
+    future: &CallbackFuture,
+) -> Result<()> {
     if session_id.is_empty() {
         return Err(CodexError::invalid_parameter(
             "session_id",
@@ -233,11 +499,10 @@ fn upload_chunk_sync(node: &CodexNode, session_id: &str, chunk: &[u8]) -> Result
         ));
     }
 
-    let future = CallbackFuture::new();
-
     let chunk_ptr = chunk.as_ptr() as *mut u8;
     let chunk_len = chunk.len();
     let context_ptr = future.context_ptr() as *mut c_void;
+    future.context.hold_until_complete(chunk);
 
     let result = crate::callback::with_libcodex_lock(|| unsafe {
         node.with_ctx(|ctx| {
@@ -258,15 +523,37 @@ fn upload_chunk_sync(node: &CodexNode, session_id: &str, chunk: &[u8]) -> Result
     });
 
     if result != 0 {
+        future.context.release_held();
         return Err(CodexError::upload_error("Failed to upload chunk"));
     }
 
-    future.wait()?;
+    node.uploads().record_chunk(session_id, chunk_len);
     Ok(())
 }
 
-/// Synchronous version of upload_finalize for internal use
-fn upload_finalize_sync(node: &CodexNode, session_id: &str) -> Result<String> {
+/// Synchronous version of upload_chunk for internal use, waiting until `deadline`
+///
+/// The chunk stays alive until libcodex is done with it, also when `deadline`
+/// passes first.
+pub(crate) fn upload_chunk_sync(
+    node: &CodexNode,
+    session_id: &str,
+    chunk: Bytes,
+    deadline: Option<Instant>,
+) -> Result<()> {
+    let future = CallbackFuture::new();
+    start_upload_chunk(node, session_id, chunk, &future)?;
+
+    future.wait_until(deadline)?;
+    Ok(())
+}
+
+/// Issue `codex_upload_finalize`; the CID arrives through `future`
+pub(crate) fn start_upload_finalize(
+    node: &CodexNode,
+    session_id: &str,
+    future: &CallbackFuture,
+) -> Result<()> {
     if session_id.is_empty() {
         return Err(CodexError::invalid_parameter(
             "session_id",
@@ -274,8 +561,6 @@ fn upload_finalize_sync(node: &CodexNode, session_id: &str) -> Result<String> {
         ));
     }
 
-    let future = CallbackFuture::new();
-
     let context_ptr = future.context_ptr() as *mut c_void;
 
     let result = crate::callback::with_libcodex_lock(|| unsafe {
@@ -298,12 +583,29 @@ fn upload_finalize_sync(node: &CodexNode, session_id: &str) -> Result<String> {
         return Err(CodexError::upload_error("Failed to finalize upload"));
     }
 
-    let cid = future.wait()?;
+    Ok(())
+}
+
+/// Synchronous version of upload_finalize for internal use, waiting until `deadline`
+pub(crate) fn upload_finalize_sync(
+    node: &CodexNode,
+    session_id: &str,
+    deadline: Option<Instant>,
+) -> Result<String> {
+    let future = CallbackFuture::new();
+    start_upload_finalize(node, session_id, &future)?;
+
+    let cid = future.wait_until(deadline)?;
+    node.uploads().remove(session_id);
     Ok(cid)
 }
 
-/// Synchronous version of upload_cancel for internal use
-fn upload_cancel_sync(node: &CodexNode, session_id: &str) -> Result<()> {
+/// Issue `codex_upload_cancel`; completion is reported through `future`
+pub(crate) fn start_upload_cancel(
+    node: &CodexNode,
+    session_id: &str,
+    future: &CallbackFuture,
+) -> Result<()> {
     if session_id.is_empty() {
         return Err(CodexError::invalid_parameter(
             "session_id",
@@ -311,8 +613,6 @@ fn upload_cancel_sync(node: &CodexNode, session_id: &str) -> Result<()> {
         ));
     }
 
-    let future = CallbackFuture::new();
-
     let context_ptr = future.context_ptr() as *mut c_void;
 
     let result = crate::callback::with_libcodex_lock(|| unsafe {
@@ -335,6 +635,94 @@ fn upload_cancel_sync(node: &CodexNode, session_id: &str) -> Result<()> {
         return Err(CodexError::upload_error("Failed to cancel upload"));
     }
 
+    node.uploads().remove(session_id);
+    Ok(())
+}
+
+/// Cancels an upload session when dropped, unless it was disarmed
+///
+/// Held by uploads whose future may be dropped between two libcodex calls,
+/// so that a session is not left open on the node when that happens.
+pub(crate) struct SessionGuard {
+    node: CodexNode,
+    session_id: String,
+    armed: bool,
+}
+
+impl SessionGuard {
+    pub fn new(node: &CodexNode, session_id: &str) -> Self {
+        Self {
+            node: node.clone(),
+            session_id: session_id.to_string(),
+            armed: true,
+        }
+    }
+
+    /// The session was finalized or cancelled; leave it alone on drop
+    pub fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+
+        // Fire and forget: the callback of a dropped future is ignored
+        let future = CallbackFuture::new();
+        if let Err(e) = start_upload_cancel(&self.node, &self.session_id, &future) {
+            log::warn!(
+                "Failed to cancel abandoned upload session {}: {}",
+                self.session_id,
+                e
+            );
+        }
+    }
+}
+
+/// Synchronous version of upload_cancel for internal use
+pub(crate) fn upload_cancel_sync(node: &CodexNode, session_id: &str) -> Result<()> {
+    let future = CallbackFuture::new();
+    start_upload_cancel(node, session_id, &future)?;
+
     future.wait()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_progress() {
+        let mut progress = FileProgress::new(2500, 1024);
+        assert_eq!(progress.total_chunks, 3);
+
+        let first = progress.record(1024);
+        assert_eq!(first.bytes_uploaded, 1024);
+        assert_eq!(first.total_bytes, Some(2500));
+        assert_eq!(first.current_chunk, Some(1));
+        assert_eq!(first.total_chunks, Some(3));
+
+        progress.record(1024);
+        let last = progress.record(452);
+        assert_eq!(last.bytes_uploaded, 2500);
+        assert_eq!(last.current_chunk, Some(3));
+        assert_eq!(last.percentage, 1.0);
+
+        // Reports beyond the size of the file do not overshoot
+        let extra = progress.record(100);
+        assert_eq!(extra.bytes_uploaded, 2500);
+        assert_eq!(extra.current_chunk, Some(3));
+        assert_eq!(progress.chunks_stored, 4);
+    }
+
+    #[test]
+    fn test_file_progress_empty_file() {
+        let mut progress = FileProgress::new(0, 1024);
+        assert_eq!(progress.total_chunks, 1);
+        assert_eq!(progress.record(0).percentage, 0.0);
+    }
+}
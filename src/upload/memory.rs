@@ -0,0 +1,157 @@
+//! Uploads of data already in memory
+//!
+//! [`upload_bytes`] splits a [`Bytes`] buffer into chunk-sized slices that
+//! share its allocation and hands each slice to libcodex as it is, so the data
+//! is never copied on its way to the node. Each slice keeps the allocation
+//! alive until libcodex is done with it, even if the upload future is
+//! dropped. Completion callbacks are awaited directly, without a blocking
+//! thread, which keeps the cost of many small uploads down to the libcodex
+//! calls themselves.
+
+use crate::callback::{deadline_after, run_until};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::throttle::Throttle;
+use crate::upload::async_reader::{
+    upload_cancel_async, upload_chunk_async, upload_finalize_async, upload_init_async,
+};
+use crate::upload::compression::{compress_bytes, Compression};
+use crate::upload::file::SessionGuard;
+use crate::upload::mime::SNIFF_LEN;
+use crate::upload::types::{UploadOptions, UploadProgress, UploadResult, UploadStrategy};
+use crate::upload::verify::{verify_result, wants_digest, ContentDigest};
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+
+/// Upload a buffer that is already in memory
+///
+/// Accepts anything that converts into [`Bytes`] without copying, such as a
+/// `Vec<u8>`, a `String` or another `Bytes`. The buffer is uploaded in
+/// `options.chunk_size` slices of the same allocation.
+///
+/// With [`UploadOptions::compression`] set, the buffer is compressed as a
+/// whole first and the compressed copy is uploaded instead.
+///
+/// # Arguments
+///
+/// * `node` - The Codex node to use for the upload
+/// * `data` - The content to upload
+/// * `options` - Upload options including chunk size and progress callbacks
+///
+/// # Returns
+///
+/// An `UploadResult` containing the CID and upload statistics
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - `data` is empty
+/// - The options are invalid, or the strategy is [`UploadStrategy::Stream`]
+/// - The upload does not finish within `options.timeout` seconds
+/// - The upload fails for any reason
+///
+/// The session is cancelled on failure, and when the returned future is
+/// dropped before the upload is finalized.
+pub async fn upload_bytes(
+    node: &CodexNode,
+    data: impl Into<Bytes>,
+    options: UploadOptions,
+) -> Result<UploadResult> {
+    let data = data.into();
+
+    options.validate()?;
+    options.reader_strategy()?;
+
+    if data.is_empty() {
+        return Err(CodexError::invalid_parameter(
+            "data",
+            "Cannot upload an empty buffer",
+        ));
+    }
+
+    let start_time = std::time::Instant::now();
+    let deadline = deadline_after(options.timeout);
+    let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+
+    let options = options.sniffed(&data[..data.len().min(SNIFF_LEN)]);
+    let data = match options.compression {
+        Compression::None => data,
+        compression => Bytes::from(compress_bytes(&data, compression)?),
+    };
+
+    let session_id = run_until(deadline, upload_init_async(node, &options))
+        .await
+        .map_err(|e| e.with_timeout_progress("upload", Some(0), Some(data.len())))?;
+    let guard = SessionGuard::new(node, &session_id);
+
+    let mut bytes_sent = 0;
+    let sent = run_until(
+        deadline,
+        send_bytes(
+            node,
+            &options,
+            &data,
+            &session_id,
+            chunk_size,
+            &mut bytes_sent,
+        ),
+    )
+    .await;
+
+    let cid = match sent {
+        Ok(()) => run_until(deadline, upload_finalize_async(node, &session_id)).await,
+        Err(e) => Err(e),
+    };
+
+    let cid = match cid {
+        Ok(cid) => cid,
+        Err(e) => {
+            let _ = upload_cancel_async(node, &session_id).await;
+            guard.disarm();
+            return Err(e.with_timeout_progress("upload", Some(bytes_sent), Some(data.len())));
+        }
+    };
+    guard.disarm();
+
+    let chunks = data.len().div_ceil(chunk_size);
+    let result = UploadResult::new(cid, data.len())
+        .chunks(chunks)
+        .duration_ms(start_time.elapsed().as_millis() as u64)
+        .strategy(UploadStrategy::Chunked)
+        .compression(options.compression);
+
+    let digest: Option<ContentDigest> =
+        wants_digest(&options).then(|| Sha256::digest(&data).into());
+    verify_result(node, &options, result, digest).await
+}
+
+async fn send_bytes(
+    node: &CodexNode,
+    options: &UploadOptions,
+    data: &Bytes,
+    session_id: &str,
+    chunk_size: usize,
+    bytes_sent: &mut usize,
+) -> Result<()> {
+    let total_chunks = data.len().div_ceil(chunk_size);
+    let throttle = Throttle::upload(node, options.rate_limit.as_ref());
+
+    for (index, start) in (0..data.len()).step_by(chunk_size).enumerate() {
+        let chunk = data.slice(start..data.len().min(start + chunk_size));
+        let len = chunk.len();
+        throttle.acquire(len).await;
+        upload_chunk_async(node, session_id, chunk).await?;
+        *bytes_sent += len;
+
+        if let Some(ref callback) = options.on_progress {
+            callback(UploadProgress::new_chunked(
+                *bytes_sent,
+                Some(data.len()),
+                index + 1,
+                total_chunks,
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+//! Filename and mimetype handling for uploads
+//!
+//! libcodex takes a single path when an upload session is opened and records
+//! its last component as the manifest `filename` and the mimetype registered
+//! for its extension as the manifest `mimetype`. The functions here build that
+//! name from [`UploadOptions::filename`](crate::upload::UploadOptions::filename)
+//! and [`UploadOptions::mimetype`](crate::upload::UploadOptions::mimetype), and
+//! recognize common formats from their first bytes when a filename carries no
+//! extension.
+//!
+//! The mimetype therefore travels as an extension: `notes.txt` uploaded as
+//! `text/csv` is recorded as `notes.txt.csv`, and content without a filename
+//! is recorded as `data.<extension>`.
+
+use crate::error::{CodexError, Result};
+use std::path::Path;
+
+/// Number of leading bytes [`sniff_mimetype`] looks at
+pub const SNIFF_LEN: usize = 512;
+
+/// Extensions and the mimetypes libcodex records for them
+///
+/// Where several extensions share a mimetype, the first one listed is the one
+/// appended to filenames.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("txt", "text/plain"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("md", "text/markdown"),
+    ("js", "application/javascript"),
+    ("json", "application/json"),
+    ("xml", "application/xml"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("zst", "application/zstd"),
+    ("tar", "application/x-tar"),
+    ("wasm", "application/wasm"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+    ("svg", "image/svg+xml"),
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("ogg", "audio/ogg"),
+    ("flac", "audio/flac"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+    ("mov", "video/quicktime"),
+];
+
+/// Mimetype registered for a file extension, without the leading dot
+pub fn mimetype_for_extension(extension: &str) -> Option<&'static str> {
+    let extension = extension.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(ext, _)| *ext == extension)
+        .map(|(_, mimetype)| *mimetype)
+}
+
+/// Preferred file extension for a mimetype
+pub fn extension_for_mimetype(mimetype: &str) -> Option<&'static str> {
+    let mimetype = mimetype.to_ascii_lowercase();
+    EXTENSIONS
+        .iter()
+        .find(|(_, mime)| *mime == mimetype)
+        .map(|(ext, _)| *ext)
+}
+
+/// Mimetype implied by the extension of `filename`
+pub fn mimetype_for_filename(filename: &str) -> Option<&'static str> {
+    Path::new(filename)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(mimetype_for_extension)
+}
+
+/// Recognize the format of content from its first bytes
+///
+/// Only the first [`SNIFF_LEN`] bytes are looked at. Binary formats are
+/// recognized by their magic numbers; anything else that is valid UTF-8
+/// without NUL bytes is reported as `text/plain`.
+pub fn sniff_mimetype(head: &[u8]) -> Option<&'static str> {
+    let head = &head[..head.len().min(SNIFF_LEN)];
+
+    const MAGIC: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\0asm", "application/wasm"),
+        (b"ID3", "audio/mpeg"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"\x1a\x45\xdf\xa3", "video/webm"),
+        (b"BM", "image/bmp"),
+    ];
+
+    if let Some((_, mimetype)) = MAGIC.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return Some(mimetype);
+    }
+
+    if head.len() >= 12 && head.starts_with(b"RIFF") {
+        match &head[8..12] {
+            b"WEBP" => return Some("image/webp"),
+            b"WAVE" => return Some("audio/wav"),
+            _ => {}
+        }
+    }
+
+    if head.len() >= 12 && &head[4..8] == b"ftyp" {
+        return Some(match &head[8..12] {
+            b"qt  " => "video/quicktime",
+            _ => "video/mp4",
+        });
+    }
+
+    if head.len() >= 262 && &head[257..262] == b"ustar" {
+        return Some("application/x-tar");
+    }
+
+    sniff_text(head)
+}
+
+fn sniff_text(head: &[u8]) -> Option<&'static str> {
+    if head.is_empty() || head.contains(&0) {
+        return None;
+    }
+
+    // The sample may end in the middle of a multi-byte character
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&head[..e.valid_up_to()]).ok()?,
+        Err(_) => return None,
+    };
+
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        Some("text/html")
+    } else if start.starts_with("<svg") || (start.starts_with("<?xml") && start.contains("<svg")) {
+        Some("image/svg+xml")
+    } else if start.starts_with("<?xml") {
+        Some("application/xml")
+    } else {
+        Some("text/plain")
+    }
+}
+
+/// Name passed to libcodex so it records `filename` and `mimetype`
+///
+/// If `mimetype` is not the one implied by the extension of `filename`, the
+/// preferred extension for `mimetype` is appended. A mimetype without a
+/// filename is recorded under `data`, as compressed content is. Without
+/// either, the name is empty.
+///
+/// Fails if no extension is known for `mimetype`, since libcodex would not
+/// record it.
+pub(crate) fn upload_name(filename: Option<&str>, mimetype: Option<&str>) -> Result<String> {
+    let Some(mimetype) = mimetype else {
+        return Ok(filename.unwrap_or_default().to_string());
+    };
+    let filename = filename.unwrap_or("data");
+
+    if matches_filename(filename, mimetype) {
+        return Ok(filename.to_string());
+    }
+    match extension_for_mimetype(mimetype) {
+        Some(extension) => Ok(format!("{}.{}", filename, extension)),
+        None => Err(CodexError::invalid_parameter(
+            "mimetype",
+            format!("No file extension is known for mimetype '{}'", mimetype),
+        )),
+    }
+}
+
+fn matches_filename(filename: &str, mimetype: &str) -> bool {
+    mimetype_for_filename(filename).is_some_and(|implied| implied.eq_ignore_ascii_case(mimetype))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extension_lookup() {
+        assert_eq!(mimetype_for_extension("PNG"), Some("image/png"));
+        assert_eq!(mimetype_for_filename("photo.jpeg"), Some("image/jpeg"));
+        assert_eq!(mimetype_for_filename("README"), None);
+        assert_eq!(extension_for_mimetype("image/jpeg"), Some("jpg"));
+        assert_eq!(extension_for_mimetype("application/x-unknown"), None);
+    }
+
+    #[test]
+    fn test_sniff_binary_formats() {
+        assert_eq!(
+            sniff_mimetype(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"),
+            Some("image/png")
+        );
+        assert_eq!(
+            sniff_mimetype(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_mimetype(b"%PDF-1.7\n"), Some("application/pdf"));
+        assert_eq!(sniff_mimetype(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_mimetype(b"\0\0\0\x18ftypmp42"), Some("video/mp4"));
+
+        let mut tar = vec![0u8; 512];
+        tar[..4].copy_from_slice(b"file");
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff_mimetype(&tar), Some("application/x-tar"));
+    }
+
+    #[test]
+    fn test_sniff_text() {
+        assert_eq!(sniff_mimetype(b"hello world"), Some("text/plain"));
+        assert_eq!(
+            sniff_mimetype(b"  <!DOCTYPE html><html>"),
+            Some("text/html")
+        );
+        assert_eq!(
+            sniff_mimetype(b"<?xml version=\"1.0\"?><svg xmlns=\"\">"),
+            Some("image/svg+xml")
+        );
+        assert_eq!(
+            sniff_mimetype(b"<?xml version=\"1.0\"?><a/>"),
+            Some("application/xml")
+        );
+        // Truncated in the middle of a multi-byte character
+        assert_eq!(sniff_mimetype(&"héllo".as_bytes()[..2]), Some("text/plain"));
+        assert_eq!(sniff_mimetype(b"\x01\x02\0\x03"), None);
+        assert_eq!(sniff_mimetype(b""), None);
+    }
+
+    #[test]
+    fn test_upload_name() {
+        let name = |filename, mimetype| upload_name(filename, mimetype).unwrap();
+        assert_eq!(name(None, None), "");
+        assert_eq!(name(None, Some("image/png")), "data.png");
+        assert_eq!(name(Some("cat.png"), None), "cat.png");
+        assert_eq!(name(Some("cat.png"), Some("image/png")), "cat.png");
+        assert_eq!(name(Some("cat"), Some("image/png")), "cat.png");
+        assert_eq!(name(Some("notes.txt"), Some("text/csv")), "notes.txt.csv");
+    }
+
+    #[test]
+    fn test_upload_name_unknown_mimetype() {
+        assert!(matches!(
+            upload_name(Some("cat"), Some("application/x-unknown")),
+            Err(CodexError::InvalidParameter { .. })
+        ));
+        assert!(upload_name(None, Some("application/x-unknown")).is_err());
+    }
+}
@@ -7,6 +7,12 @@
 //!
 //! - [`file::upload_file()`] - Upload a file from the filesystem
 //! - [`file::upload_reader()`] - Upload data from any Read implementation
+//! - [`async_reader::upload_async_reader()`] - Upload data from any AsyncRead implementation
+//! - [`memory::upload_bytes()`] - Upload an in-memory buffer without copying it
+//! - [`writer::UploadWriter`] - Push data into an upload through Write or AsyncWrite
+//! - [`resume::resume_upload()`] - Continue a journaled upload after an interruption
+//! - [`segmented::SegmentManifest`] - Index of an upload finalized segment by segment to survive crashes
+//! - [`batch::BatchUploader`] - Upload many files, readers and buffers with bounded concurrency and retry
 //!
 //! ## Low-Level Operations
 //!
@@ -14,23 +20,52 @@
 //! - [`chunks::upload_chunk()`] - Upload a chunk of data
 //! - [`session::upload_finalize()`] - Finalize an upload and get the CID
 //! - [`session::upload_cancel()`] - Cancel an upload session
+//! - [`session::UploadSession`] - Owned handle over the same steps that cancels on drop
+//! - [`registry::ActiveUpload`] - Sessions still open on a node, and cancellation of idle ones
 //!
 //! ## Streaming Support
 //!
 //! - [`streaming::create_streaming_reader()`] - Create a streaming reader with progress tracking
 //! - [`streaming::StreamingUploadReader`] - Sync streaming reader with progress callbacks
 //! - [`streaming::AsyncStreamingUploadReader`] - Async streaming reader with progress callbacks
+//! - [`pipeline::pipeline_chunks()`] - Read ahead of the upload on a separate thread with bounded memory
+//!
+//! ## Content Addressing
+//!
+//! - [`cid::compute_cid()`] - Compute the CID of content without uploading it
+//! - [`cid::compute_manifest()`] - Compute the full manifest, including filename and mimetype
+//!
+//! ## Compression
+//!
+//! - [`compression::Compression`] - Compress content on its way to the node
+//!
+//! ## Filenames and Mimetypes
+//!
+//! - [`mime::sniff_mimetype()`] - Recognize the format of content from its first bytes
+//! - [`mime::mimetype_for_filename()`] - Mimetype libcodex records for a filename
 //!
 //! ## Configuration
 //!
 //! - [`types::UploadOptions`] - Configure upload behavior including chunk size, verification, and progress callbacks
 //! - [`types::UploadStrategy`] - Different strategies for upload optimization
 
+pub mod async_reader;
+pub mod batch;
 pub mod chunks;
+pub mod cid;
+pub mod compression;
 pub mod file;
+pub mod memory;
+pub mod mime;
+pub mod pipeline;
+pub mod registry;
+pub mod resume;
+pub mod segmented;
 pub mod session;
 pub mod streaming;
 pub mod types;
+pub mod verify;
+pub mod writer;
 
 // Re-export types
 pub use types::{UploadOptions, UploadProgress, UploadResult, UploadStrategy};
@@ -41,10 +76,22 @@ pub use streaming::{
 };
 
 // Re-export high-level file operations
+pub use async_reader::upload_async_reader;
+pub use batch::{BatchItemResult, BatchUploader};
+pub use cid::{compute_cid, compute_manifest, CidOptions, ComputedManifest};
+pub use compression::Compression;
 pub use file::{upload_file, upload_reader};
+pub use memory::upload_bytes;
+pub use mime::{mimetype_for_filename, sniff_mimetype};
+pub use registry::{ActiveUpload, UploadReaper};
+pub use writer::UploadWriter;
+
+// Re-export resumable upload support
+pub use resume::{resume_upload, UploadJournal};
+pub use segmented::{SegmentManifest, UploadSegment};
 
 // Re-export session management operations
-pub use session::{upload_cancel, upload_finalize, upload_init};
+pub use session::{upload_cancel, upload_finalize, upload_init, UploadSession};
 
 // Re-export chunk operations
 pub use chunks::{upload_chunk, upload_chunks};
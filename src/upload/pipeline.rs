@@ -0,0 +1,277 @@
+//! Pipelined chunk reading for uploads
+//!
+//! Reading the source and pushing chunks through libcodex are independent, so
+//! a dedicated reader thread fills chunk buffers ahead of the upload while the
+//! caller's thread hands them to the node in order. The number of buffers in
+//! flight is bounded, which caps memory use at `(read_ahead + 1) * chunk_size`.
+
+use crate::error::{CodexError, Result};
+use std::io::{ErrorKind, Read};
+use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+use std::thread;
+
+/// Number of chunks read ahead of the upload by default
+pub const DEFAULT_READ_AHEAD: usize = 4;
+
+/// Totals reported by [`pipeline_chunks`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PipelineStats {
+    /// Bytes handed to the sink
+    pub bytes: usize,
+    /// Chunks handed to the sink
+    pub chunks: usize,
+}
+
+/// Read `reader` in `chunk_size` pieces and pass them to `sink` in order
+///
+/// With `read_ahead` greater than 0, reading happens on a separate thread that
+/// stays at most `read_ahead` chunks ahead of `sink`. With `read_ahead == 0`,
+/// each chunk is read and sunk in turn on the calling thread.
+///
+/// Every chunk except the last is exactly `chunk_size` bytes long. If `sink`
+/// fails, reading stops and the error is returned.
+pub fn pipeline_chunks<R, F>(
+    reader: R,
+    chunk_size: usize,
+    read_ahead: usize,
+    mut sink: F,
+) -> Result<PipelineStats>
+where
+    R: Read + Send + 'static,
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    if chunk_size == 0 {
+        return Err(CodexError::invalid_parameter(
+            "chunk_size",
+            "Chunk size must be greater than 0",
+        ));
+    }
+
+    if read_ahead == 0 {
+        return sequential_chunks(reader, chunk_size, sink);
+    }
+
+    // Filled buffers travel to the sink; emptied ones come back to be reused.
+    let (filled_tx, filled_rx) = sync_channel::<std::io::Result<Vec<u8>>>(read_ahead);
+    let (empty_tx, empty_rx) = channel::<Vec<u8>>();
+
+    let reader_thread = thread::Builder::new()
+        .name("codex-upload-reader".to_string())
+        .spawn(move || read_loop(reader, chunk_size, read_ahead + 1, filled_tx, empty_rx))
+        .map_err(CodexError::from)?;
+
+    let mut stats = PipelineStats::default();
+    let mut result = Ok(());
+
+    for buffer in filled_rx.iter() {
+        let buffer = match buffer {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                result = Err(CodexError::from(e));
+                break;
+            }
+        };
+
+        if let Err(e) = sink(&buffer) {
+            result = Err(e);
+            break;
+        }
+
+        stats.bytes += buffer.len();
+        stats.chunks += 1;
+
+        // The reader may already be done; a closed pool is fine.
+        let _ = empty_tx.send(buffer);
+    }
+
+    // Dropping the receivers unblocks the reader if we stopped early.
+    drop(filled_rx);
+    drop(empty_tx);
+    let _ = reader_thread.join();
+
+    result.map(|_| stats)
+}
+
+fn read_loop<R: Read>(
+    mut reader: R,
+    chunk_size: usize,
+    max_buffers: usize,
+    filled: SyncSender<std::io::Result<Vec<u8>>>,
+    empty: Receiver<Vec<u8>>,
+) {
+    let mut allocated = 0;
+
+    loop {
+        let mut buffer = match empty.try_recv() {
+            Ok(buffer) => buffer,
+            Err(_) if allocated < max_buffers => {
+                allocated += 1;
+                Vec::with_capacity(chunk_size)
+            }
+            Err(_) => match empty.recv() {
+                Ok(buffer) => buffer,
+                Err(_) => return,
+            },
+        };
+
+        buffer.resize(chunk_size, 0);
+        match read_full(&mut reader, &mut buffer) {
+            Ok(0) => return,
+            Ok(n) => {
+                buffer.truncate(n);
+                let last = n < chunk_size;
+                if filled.send(Ok(buffer)).is_err() || last {
+                    return;
+                }
+            }
+            Err(e) => {
+                let _ = filled.send(Err(e));
+                return;
+            }
+        }
+    }
+}
+
+fn sequential_chunks<R, F>(mut reader: R, chunk_size: usize, mut sink: F) -> Result<PipelineStats>
+where
+    R: Read,
+    F: FnMut(&[u8]) -> Result<()>,
+{
+    let mut buffer = vec![0u8; chunk_size];
+    let mut stats = PipelineStats::default();
+
+    loop {
+        let n = read_full(&mut reader, &mut buffer)?;
+        if n == 0 {
+            break;
+        }
+
+        sink(&buffer[..n])?;
+        stats.bytes += n;
+        stats.chunks += 1;
+
+        if n < chunk_size {
+            break;
+        }
+    }
+
+    Ok(stats)
+}
+
+/// Fill `buffer` from `reader`, stopping early only at end of input
+fn read_full<R: Read>(reader: &mut R, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::time::{Duration, Instant};
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_pipeline_preserves_order_and_content() {
+        let input = data(10_000);
+
+        for read_ahead in [0, 1, 4] {
+            let mut output = Vec::new();
+            let stats = pipeline_chunks(Cursor::new(input.clone()), 1024, read_ahead, |chunk| {
+                output.extend_from_slice(chunk);
+                Ok(())
+            })
+            .unwrap();
+
+            assert_eq!(output, input);
+            assert_eq!(stats.bytes, 10_000);
+            assert_eq!(stats.chunks, 10);
+        }
+    }
+
+    #[test]
+    fn test_pipeline_empty_input() {
+        let stats = pipeline_chunks(Cursor::new(Vec::new()), 1024, 2, |_| Ok(())).unwrap();
+        assert_eq!(stats, PipelineStats::default());
+    }
+
+    #[test]
+    fn test_pipeline_sink_error_stops_reading() {
+        let mut calls = 0;
+        let result = pipeline_chunks(Cursor::new(data(100_000)), 1000, 4, |_| {
+            calls += 1;
+            if calls == 3 {
+                Err(CodexError::upload_error("boom"))
+            } else {
+                Ok(())
+            }
+        });
+
+        assert!(matches!(result, Err(CodexError::UploadError { .. })));
+        assert_eq!(calls, 3);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk gone"))
+        }
+    }
+
+    #[test]
+    fn test_pipeline_reader_error() {
+        let result = pipeline_chunks(FailingReader, 1024, 2, |_| Ok(()));
+        assert!(matches!(result, Err(CodexError::Io(_))));
+    }
+
+    struct SlowReader {
+        inner: Cursor<Vec<u8>>,
+        delay: Duration,
+    }
+
+    impl Read for SlowReader {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            thread::sleep(self.delay);
+            self.inner.read(buf)
+        }
+    }
+
+    #[test]
+    fn test_pipeline_overlaps_reads_and_sink() {
+        let delay = Duration::from_millis(20);
+        let run = |read_ahead| {
+            let reader = SlowReader {
+                inner: Cursor::new(data(10 * 1024)),
+                delay,
+            };
+            let start = Instant::now();
+            pipeline_chunks(reader, 1024, read_ahead, |_| {
+                thread::sleep(delay);
+                Ok(())
+            })
+            .unwrap();
+            start.elapsed()
+        };
+
+        let sequential = run(0);
+        let pipelined = run(4);
+        assert!(
+            pipelined < sequential * 3 / 4,
+            "pipelined {:?} vs sequential {:?}",
+            pipelined,
+            sequential
+        );
+    }
+}
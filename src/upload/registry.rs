@@ -0,0 +1,310 @@
+//! Tracking of the upload sessions opened through a node
+//!
+//! Every session the crate opens with `codex_upload_init` is recorded in the
+//! registry of its [`CodexNode`] until it is finalized or cancelled. The open
+//! sessions can be listed with [`CodexNode::active_uploads`], and sessions
+//! nobody has touched for a while can be cancelled with
+//! [`CodexNode::cancel_idle_uploads`] or periodically by an [`UploadReaper`].
+//!
+//! This catches sessions whose ID was lost, for example when a task panicked
+//! between two chunks, which libcodex would otherwise keep open for the
+//! lifetime of the node. Sessions opened directly through the FFI are not
+//! tracked.
+//!
+//! Sessions of journaled uploads (see [`UploadOptions::journal`]) are listed
+//! but never cancelled as idle: they are deliberately left open after a
+//! failure so that [`resume_upload`] can continue them.
+//!
+//! [`UploadOptions::journal`]: crate::upload::UploadOptions::journal
+//! [`resume_upload`]: crate::upload::resume_upload
+
+use crate::node::lifecycle::{CodexNode, WeakCodexNode};
+use crate::upload::async_reader::upload_cancel_async;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// An upload session that is open on a node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveUpload {
+    /// The session ID used by libcodex
+    pub session_id: String,
+    /// When the session was opened
+    pub started_at: DateTime<Utc>,
+    /// When a chunk was last sent, or the session was opened if none was
+    pub last_activity: DateTime<Utc>,
+    /// Bytes sent to the node so far
+    pub bytes_sent: usize,
+    /// Chunks sent to the node so far
+    pub chunks_sent: usize,
+    /// Whether the session belongs to a journaled upload, which idle
+    /// cancellation leaves alone
+    pub journaled: bool,
+    /// Time since the last activity, as of the call that returned this entry
+    pub idle: Duration,
+}
+
+struct Entry {
+    started_at: DateTime<Utc>,
+    last_activity_at: DateTime<Utc>,
+    last_activity: Instant,
+    bytes_sent: usize,
+    chunks_sent: usize,
+    journaled: bool,
+}
+
+/// The upload sessions open on one node, shared by all its clones
+#[derive(Clone, Default)]
+pub(crate) struct UploadRegistry {
+    sessions: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl UploadRegistry {
+    /// Record a session that was just opened
+    pub fn register(&self, session_id: &str) {
+        let now = Utc::now();
+        self.sessions.lock().unwrap().insert(
+            session_id.to_string(),
+            Entry {
+                started_at: now,
+                last_activity_at: now,
+                last_activity: Instant::now(),
+                bytes_sent: 0,
+                chunks_sent: 0,
+                journaled: false,
+            },
+        );
+    }
+
+    /// Keep a session that a journal records out of idle cancellation
+    pub fn mark_journaled(&self, session_id: &str) {
+        if let Some(entry) = self.sessions.lock().unwrap().get_mut(session_id) {
+            entry.journaled = true;
+        }
+    }
+
+    /// Record a chunk sent on a session; unknown sessions are ignored
+    pub fn record_chunk(&self, session_id: &str, len: usize) {
+        if let Some(entry) = self.sessions.lock().unwrap().get_mut(session_id) {
+            entry.last_activity_at = Utc::now();
+            entry.last_activity = Instant::now();
+            entry.bytes_sent += len;
+            entry.chunks_sent += 1;
+        }
+    }
+
+    /// Forget a session that was finalized or cancelled
+    pub fn remove(&self, session_id: &str) {
+        self.sessions.lock().unwrap().remove(session_id);
+    }
+
+    /// Forget every session, once the node that held them has stopped
+    pub fn clear(&self) {
+        self.sessions.lock().unwrap().clear();
+    }
+
+    /// The open sessions, oldest first
+    pub fn snapshot(&self) -> Vec<ActiveUpload> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut uploads: Vec<_> = sessions
+            .iter()
+            .map(|(id, entry)| ActiveUpload {
+                session_id: id.clone(),
+                started_at: entry.started_at,
+                last_activity: entry.last_activity_at,
+                bytes_sent: entry.bytes_sent,
+                chunks_sent: entry.chunks_sent,
+                journaled: entry.journaled,
+                idle: entry.last_activity.elapsed(),
+            })
+            .collect();
+        uploads.sort_by(|a, b| {
+            a.started_at
+                .cmp(&b.started_at)
+                .then_with(|| a.session_id.cmp(&b.session_id))
+        });
+        uploads
+    }
+
+    /// IDs of the unjournaled sessions untouched for at least `idle_timeout`
+    pub fn idle(&self, idle_timeout: Duration) -> Vec<String> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .iter()
+            .filter(|(_, entry)| !entry.journaled && entry.last_activity.elapsed() >= idle_timeout)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+impl CodexNode {
+    /// Upload sessions opened through this node that are still open, oldest first
+    ///
+    /// A session is listed from the moment `codex_upload_init` returns its ID
+    /// until it is finalized or cancelled, whichever API opened it. Sessions
+    /// are forgotten when the node is stopped.
+    pub fn active_uploads(&self) -> Vec<ActiveUpload> {
+        self.uploads().snapshot()
+    }
+
+    /// Cancel every upload session untouched for at least `idle_timeout`
+    ///
+    /// Returns the IDs of the sessions that were cancelled. Sessions whose
+    /// cancellation fails are logged and forgotten, since libcodex no longer
+    /// knows them in most cases. An [`UploadSession`](crate::upload::UploadSession)
+    /// or [`UploadWriter`](crate::upload::UploadWriter) whose session is
+    /// cancelled this way fails on its next chunk.
+    ///
+    /// Sessions of journaled uploads are skipped, since
+    /// [`resume_upload`](crate::upload::resume_upload) needs them; they are
+    /// closed by finishing the upload or with
+    /// [`upload_cancel`](crate::upload::upload_cancel).
+    pub async fn cancel_idle_uploads(&self, idle_timeout: Duration) -> Vec<String> {
+        let mut cancelled = Vec::new();
+        for session_id in self.uploads().idle(idle_timeout) {
+            match upload_cancel_async(self, &session_id).await {
+                Ok(()) => {
+                    log::info!("Cancelled idle upload session {}", session_id);
+                    cancelled.push(session_id);
+                }
+                Err(e) => {
+                    log::warn!("Failed to cancel idle upload session {}: {}", session_id, e);
+                    self.uploads().remove(&session_id);
+                }
+            }
+        }
+        cancelled
+    }
+
+    /// Cancel idle upload sessions in the background
+    ///
+    /// Every `interval`, sessions untouched for at least `idle_timeout` are
+    /// cancelled as with [`cancel_idle_uploads`](Self::cancel_idle_uploads).
+    /// `idle_timeout` should be well above the time a chunk takes to produce,
+    /// or slow uploads will be cancelled too.
+    ///
+    /// The reaper runs on the current tokio runtime until the returned handle
+    /// is stopped or dropped. It does not keep the node alive and ends by
+    /// itself once the node is gone.
+    pub fn start_upload_reaper(&self, idle_timeout: Duration, interval: Duration) -> UploadReaper {
+        let node = self.downgrade();
+        UploadReaper {
+            task: tokio::spawn(reap(node, idle_timeout, interval)),
+        }
+    }
+}
+
+async fn reap(node: WeakCodexNode, idle_timeout: Duration, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let Some(node) = node.upgrade() else {
+            return;
+        };
+        node.cancel_idle_uploads(idle_timeout).await;
+    }
+}
+
+/// Handle of a background task cancelling idle upload sessions
+///
+/// Created by [`CodexNode::start_upload_reaper`]. The task stops when the
+/// handle is dropped.
+pub struct UploadReaper {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl UploadReaper {
+    /// Stop the reaper
+    pub fn stop(self) {}
+
+    /// Whether the reaper has stopped, for example because its node is gone
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl std::fmt::Debug for UploadReaper {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadReaper")
+            .field("finished", &self.task.is_finished())
+            .finish()
+    }
+}
+
+impl Drop for UploadReaper {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_record() {
+        let registry = UploadRegistry::default();
+        registry.register("a");
+        registry.record_chunk("a", 100);
+        registry.record_chunk("a", 50);
+        registry.record_chunk("unknown", 10);
+
+        let uploads = registry.snapshot();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].session_id, "a");
+        assert_eq!(uploads[0].bytes_sent, 150);
+        assert_eq!(uploads[0].chunks_sent, 2);
+        assert!(uploads[0].last_activity >= uploads[0].started_at);
+    }
+
+    #[test]
+    fn test_remove_and_clear() {
+        let registry = UploadRegistry::default();
+        registry.register("a");
+        registry.register("b");
+        registry.remove("a");
+        assert_eq!(registry.snapshot().len(), 1);
+
+        registry.clear();
+        assert!(registry.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_shared_between_clones() {
+        let registry = UploadRegistry::default();
+        registry.clone().register("a");
+        assert_eq!(registry.snapshot().len(), 1);
+    }
+
+    #[test]
+    fn test_idle_sessions() {
+        let registry = UploadRegistry::default();
+        registry.register("stale");
+        std::thread::sleep(Duration::from_millis(50));
+        registry.register("fresh");
+
+        assert_eq!(registry.idle(Duration::from_millis(40)), vec!["stale"]);
+        assert_eq!(registry.idle(Duration::ZERO).len(), 2);
+
+        registry.record_chunk("stale", 1);
+        assert!(registry.idle(Duration::from_millis(40)).is_empty());
+    }
+
+    #[test]
+    fn test_journaled_sessions_are_not_idle() {
+        let registry = UploadRegistry::default();
+        registry.register("journaled");
+        registry.register("plain");
+        registry.mark_journaled("journaled");
+        registry.mark_journaled("unknown");
+
+        assert_eq!(registry.idle(Duration::ZERO), vec!["plain"]);
+        let uploads = registry.snapshot();
+        assert_eq!(uploads.len(), 2);
+        assert!(uploads
+            .iter()
+            .any(|u| u.session_id == "journaled" && u.journaled));
+    }
+}
@@ -0,0 +1,621 @@
+//! Resumable uploads
+//!
+//! With [`UploadOptions::journal`] set, streaming uploads record their session
+//! ID and the number of bytes the node has confirmed in a small JSON file. If
+//! the upload is interrupted, [`resume_upload`] reads the journal, seeks the
+//! source to the confirmed offset and continues in the same session.
+//!
+//! Upload sessions live inside the running node. When the node has been
+//! restarted, or has dropped the session, [`resume_upload`] notices that the
+//! session is gone and restarts the upload cleanly from the beginning. The
+//! node runs in the same process as the bindings, so a crash of that process
+//! loses the session too. Uploads that have to survive crashes set
+//! [`UploadOptions::segment_size`]: they are finalized segment by segment (see
+//! [`segmented`](crate::upload::segmented)), and a resume after a crash
+//! continues after the last finalized segment.
+//!
+//! Journaled sessions are exempt from idle cancellation
+//! ([`CodexNode::cancel_idle_uploads`] and the upload reaper), which would
+//! otherwise close the session an interrupted upload is waiting to resume.
+
+use crate::callback::{deadline_after, is_expired};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::throttle::Throttle;
+use crate::upload::file::{
+    upload_cancel_sync, upload_chunk_sync, upload_finalize_sync, upload_init_sync,
+};
+use crate::upload::pipeline::pipeline_chunks;
+use crate::upload::segmented::{resume_segmented, UploadSegment};
+use crate::upload::types::{UploadOptions, UploadProgress, UploadResult, UploadStrategy};
+use crate::upload::verify::{hash_reader, verify_result, wants_digest};
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Persistent record of an in-progress upload
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UploadJournal {
+    /// Session ID returned by `upload_init`
+    ///
+    /// Empty while a segmented upload has no segment open.
+    pub session_id: String,
+    /// Chunk size the session was started with
+    pub chunk_size: usize,
+    /// Bytes confirmed by the node
+    pub offset: u64,
+    /// Chunks confirmed by the node
+    pub chunks: usize,
+    /// End offset of a chunk that was sent but not yet confirmed
+    ///
+    /// If set when the journal is loaded, the node may or may not have that
+    /// chunk, so the session cannot be continued safely.
+    pub in_flight: Option<u64>,
+    /// Segment size of a segmented upload, see [`UploadOptions::segment_size`]
+    #[serde(default)]
+    pub segment_size: Option<u64>,
+    /// Segments of a segmented upload finalized so far, in order
+    ///
+    /// Unlike the session, they are stored on the node and survive a crash of
+    /// the process. `offset` and `chunks` count them too.
+    #[serde(default)]
+    pub segments: Vec<UploadSegment>,
+    /// When the journal was last written
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl UploadJournal {
+    /// Create a journal for a session just opened on `node`
+    ///
+    /// The session is marked as journaled in the node's registry so idle
+    /// cancellation leaves it open for [`resume_upload`].
+    pub(crate) fn start(
+        node: &CodexNode,
+        path: &Path,
+        session_id: &str,
+        chunk_size: usize,
+    ) -> Result<Self> {
+        let journal = Self::create(path, session_id, chunk_size)?;
+        node.uploads().mark_journaled(session_id);
+        Ok(journal)
+    }
+
+    /// Default journal location for a source file: `<file>.codex-upload`
+    pub fn path_for<P: AsRef<Path>>(source: P) -> PathBuf {
+        let mut path = source.as_ref().as_os_str().to_owned();
+        path.push(".codex-upload");
+        PathBuf::from(path)
+    }
+
+    /// Start a new journal for `session_id` and write it to `path`
+    pub fn create<P: Into<PathBuf>>(path: P, session_id: &str, chunk_size: usize) -> Result<Self> {
+        let mut journal = Self {
+            session_id: session_id.to_string(),
+            chunk_size,
+            offset: 0,
+            chunks: 0,
+            in_flight: None,
+            segment_size: None,
+            segments: Vec::new(),
+            updated_at: Utc::now(),
+            path: path.into(),
+        };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    /// Start a journal for a segmented upload that has no segment open yet
+    pub(crate) fn create_segmented(
+        path: &Path,
+        chunk_size: usize,
+        segment_size: u64,
+    ) -> Result<Self> {
+        let mut journal = Self::create(path, "", chunk_size)?;
+        journal.segment_size = Some(segment_size);
+        journal.save()?;
+        Ok(journal)
+    }
+
+    /// Load the journal at `path`, or `None` if there is none
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut journal: Self = serde_json::from_str(&json).map_err(|e| {
+            CodexError::upload_error(format!("Corrupt upload journal {}: {}", path.display(), e))
+        })?;
+        journal.path = path.to_path_buf();
+        Ok(Some(journal))
+    }
+
+    /// Where the journal is stored
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the journal describes a state the session can continue from
+    pub fn is_resumable(&self) -> bool {
+        self.in_flight.is_none()
+    }
+
+    /// Bytes covered by the finalized segments
+    pub fn segments_end(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.size).sum()
+    }
+
+    /// Record the session the next segment is sent in
+    pub(crate) fn open_segment(&mut self, node: &CodexNode, session_id: &str) -> Result<()> {
+        self.session_id = session_id.to_string();
+        self.in_flight = None;
+        node.uploads().mark_journaled(session_id);
+        self.save()
+    }
+
+    /// Record the CID of the segment the open session was finalized into
+    pub(crate) fn finish_segment(&mut self, cid: String) -> Result<()> {
+        let size = self.offset - self.segments_end();
+        self.segments.push(UploadSegment { cid, size });
+        self.session_id.clear();
+        self.save()
+    }
+
+    /// Forget the open session and go back to the end of the last segment
+    pub(crate) fn restart_segment(&mut self) -> Result<()> {
+        let chunk_size = self.chunk_size.max(1) as u64;
+        self.offset = self.segments_end();
+        self.chunks = self
+            .segments
+            .iter()
+            .map(|segment| segment.size.div_ceil(chunk_size) as usize)
+            .sum();
+        self.in_flight = None;
+        self.session_id.clear();
+        self.save()
+    }
+
+    /// Forget the finalized segments too and start over from the beginning
+    pub(crate) fn discard_segments(&mut self) -> Result<()> {
+        self.segments.clear();
+        self.restart_segment()
+    }
+
+    /// Delete the journal file
+    pub fn remove(self) -> Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Write the journal atomically and flush it to disk
+    fn save(&mut self) -> Result<()> {
+        self.updated_at = Utc::now();
+        let json = serde_json::to_vec(self)?;
+
+        let mut tmp = self.path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+
+        let mut file = File::create(&tmp)?;
+        file.write_all(&json)?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    fn begin_chunk(&mut self, len: usize) -> Result<()> {
+        self.in_flight = Some(self.offset + len as u64);
+        self.save()
+    }
+
+    fn confirm_chunk(&mut self, len: usize) -> Result<()> {
+        self.offset += len as u64;
+        self.chunks += 1;
+        self.in_flight = None;
+        self.save()
+    }
+}
+
+/// Send one chunk, bracketing it with journal writes
+fn send_chunk(
+    node: &CodexNode,
+    session_id: &str,
+    chunk: Bytes,
+    journal: Option<&mut UploadJournal>,
+    deadline: Option<Instant>,
+) -> Result<()> {
+    match journal {
+        Some(journal) => {
+            let len = chunk.len();
+            journal.begin_chunk(len)?;
+            upload_chunk_sync(node, session_id, chunk, deadline)?;
+            journal.confirm_chunk(len)
+        }
+        None => upload_chunk_sync(node, session_id, chunk, deadline),
+    }
+}
+
+/// Open session that [`send_reader`] streams into
+pub(crate) struct SendTarget<'a> {
+    pub session_id: &'a str,
+    pub chunk_size: usize,
+    /// Running past this fails with [`CodexError::Timeout`]
+    pub deadline: Option<Instant>,
+}
+
+/// Stream `reader` into an open session, starting from `(bytes, chunks)` already sent
+///
+/// Returns the totals including the starting values. A timeout carries the
+/// bytes sent so far.
+pub(crate) fn send_reader<R>(
+    node: &CodexNode,
+    options: &UploadOptions,
+    reader: R,
+    target: SendTarget<'_>,
+    mut journal: Option<&mut UploadJournal>,
+    start: (usize, usize),
+) -> Result<(usize, usize)>
+where
+    R: Read + Send + 'static,
+{
+    let SendTarget {
+        session_id,
+        chunk_size,
+        deadline,
+    } = target;
+    let (mut total_bytes, mut chunk_count) = start;
+    let throttle = Throttle::upload(node, options.rate_limit.as_ref());
+
+    pipeline_chunks(
+        reader,
+        chunk_size,
+        options.effective_read_ahead(),
+        |chunk| {
+            if is_expired(deadline) {
+                return Err(CodexError::timeout_with_progress(
+                    "upload",
+                    total_bytes,
+                    None,
+                ));
+            }
+            throttle.acquire_blocking(chunk.len());
+            // Copied out of the pipeline's buffer, which is reused as soon as
+            // this returns, while a chunk abandoned at the deadline may still
+            // be read by the node
+            let owned = Bytes::copy_from_slice(chunk);
+            send_chunk(node, session_id, owned, journal.as_deref_mut(), deadline)
+                .map_err(|e| e.with_timeout_progress("upload", Some(total_bytes), None))?;
+            chunk_count += 1;
+            total_bytes += chunk.len();
+
+            if let Some(ref callback) = options.on_progress {
+                let progress =
+                    UploadProgress::new_chunked(total_bytes, None, chunk_count, chunk_count);
+                callback(progress);
+            }
+
+            Ok(())
+        },
+    )?;
+
+    Ok((total_bytes, chunk_count))
+}
+
+/// Resume an interrupted upload from its journal
+///
+/// `options.journal` must point at the journal written by the interrupted
+/// [`upload_reader`](crate::upload::upload_reader) or `resume_upload` call, and
+/// `reader` must yield the same data from the start. If the journal is missing,
+/// a new journaled upload is started.
+///
+/// The upload continues from the journaled offset only if the node that
+/// opened the session is still running, for example after the uploading task
+/// failed or timed out. It restarts from the beginning, in a new session,
+/// when:
+/// - the journal shows a chunk whose delivery was never confirmed, or
+/// - the node no longer accepts the recorded session. This is always the case
+///   after the process hosting the node crashed or the node was restarted,
+///   since sessions do not survive the node.
+///
+/// With [`UploadOptions::segment_size`] set, the same cases only restart the
+/// segment that was open: segments finalized before are kept as long as the
+/// node still stores them, also across a crash of the process. Call
+/// `resume_upload` from the first attempt on, since
+/// [`upload_reader`](crate::upload::upload_reader) does not segment uploads.
+/// The resulting CID is that of a
+/// [`SegmentManifest`](crate::upload::SegmentManifest); download the content
+/// with [`download_segmented`](crate::download::download_segmented).
+///
+/// The journal is removed once the upload is finalized.
+///
+/// [`UploadOptions::timeout`] applies to this call. On expiry the session is
+/// left open so the upload can be resumed again.
+pub async fn resume_upload<R>(
+    node: &CodexNode,
+    options: UploadOptions,
+    reader: R,
+) -> Result<UploadResult>
+where
+    R: Read + Seek + Send + 'static,
+{
+    if options.segment_size.is_some() {
+        return resume_segmented(node, options, reader).await;
+    }
+
+    let task_node = node.clone();
+    let task_options = options.clone();
+
+    let (result, digest) = tokio::task::spawn_blocking(move || {
+        let node = task_node;
+        let options = task_options;
+        options.validate()?;
+        options.reader_strategy()?;
+
+        let journal_path = options.journal.clone().ok_or_else(|| {
+            CodexError::invalid_parameter("journal", "A journal path is required to resume")
+        })?;
+
+        let start_time = std::time::Instant::now();
+        let deadline = deadline_after(options.timeout);
+        let mut reader = reader;
+
+        let resumed = match UploadJournal::load(&journal_path)? {
+            Some(mut journal) if journal.is_resumable() && journal.segment_size.is_none() => {
+                if continue_session(&node, &mut journal, &mut reader, u64::MAX, deadline)? {
+                    let start = (journal.offset as usize, journal.chunks);
+                    Some((journal, start))
+                } else {
+                    None
+                }
+            }
+            Some(journal) => {
+                log::info!(
+                    "Upload journal {} cannot be continued, restarting upload",
+                    journal_path.display()
+                );
+                let _ = upload_cancel_sync(&node, &journal.session_id);
+                None
+            }
+            None => None,
+        };
+
+        let (mut journal, start) = match resumed {
+            Some((journal, start)) => (journal, start),
+            None => {
+                reader.seek(SeekFrom::Start(0))?;
+                let session_id = upload_init_sync(&node, &options, deadline)
+                    .map_err(|e| e.with_timeout_progress("upload", Some(0), None))?;
+                let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+                let journal = UploadJournal::start(&node, &journal_path, &session_id, chunk_size)?;
+                (journal, (0, 0))
+            }
+        };
+
+        let session_id = journal.session_id.clone();
+        let chunk_size = journal.chunk_size;
+        let reader = Arc::new(Mutex::new(reader));
+        let (total_bytes, chunk_count) = send_reader(
+            &node,
+            &options,
+            SharedReader(reader.clone()),
+            SendTarget {
+                session_id: &session_id,
+                chunk_size,
+                deadline,
+            },
+            Some(&mut journal),
+            start,
+        )?;
+
+        let cid = match upload_finalize_sync(&node, &session_id, deadline) {
+            Ok(cid) => cid,
+            Err(e @ CodexError::Timeout { .. }) => {
+                return Err(e.with_timeout_progress(
+                    "upload",
+                    Some(total_bytes),
+                    Some(total_bytes),
+                ));
+            }
+            Err(e) => {
+                // Nothing left to resume in this session; start over next time
+                let _ = journal.remove();
+                return Err(e);
+            }
+        };
+        journal.remove()?;
+
+        // Part of the data may have been sent before the interruption, so the
+        // digest is taken over the whole source
+        let digest = if wants_digest(&options) {
+            let mut reader = reader.lock().unwrap();
+            reader.seek(SeekFrom::Start(0))?;
+            Some(hash_reader(&mut *reader)?)
+        } else {
+            None
+        };
+
+        let result = UploadResult::new(cid, total_bytes)
+            .chunks(chunk_count)
+            .duration_ms(start_time.elapsed().as_millis() as u64)
+            .strategy(UploadStrategy::Chunked);
+        Ok((result, digest))
+    })
+    .await??;
+
+    verify_result(node, &options, result, digest).await
+}
+
+/// Lets the source be read again after `send_reader` has consumed its handle
+pub(crate) struct SharedReader<R>(pub Arc<Mutex<R>>);
+
+impl<R: Read> Read for SharedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().read(buf)
+    }
+}
+
+/// Check that the journaled session is still alive by sending the next chunk
+///
+/// The chunk is at most `limit` bytes long. Returns `false`, with the session
+/// cancelled, if the session is gone and the upload has to start over.
+pub(crate) fn continue_session<R: Read + Seek>(
+    node: &CodexNode,
+    journal: &mut UploadJournal,
+    reader: &mut R,
+    limit: u64,
+    deadline: Option<Instant>,
+) -> Result<bool> {
+    reader.seek(SeekFrom::Start(journal.offset))?;
+
+    let mut chunk = Vec::with_capacity(journal.chunk_size);
+    reader
+        .by_ref()
+        .take(limit.min(journal.chunk_size as u64))
+        .read_to_end(&mut chunk)?;
+
+    let session_id = journal.session_id.clone();
+    let alive = if chunk.is_empty() {
+        // Everything was sent before the interruption; only finalize is left
+        // and the caller will do it.
+        true
+    } else {
+        match send_chunk(node, &session_id, chunk.into(), Some(journal), deadline) {
+            Ok(()) => true,
+            Err(CodexError::Io(e)) => return Err(CodexError::Io(e)),
+            Err(e @ CodexError::Timeout { .. }) => {
+                let sent = journal.offset as usize;
+                return Err(e.with_timeout_progress("upload", Some(sent), None));
+            }
+            Err(e) => {
+                log::info!("Upload session {} is gone ({}), restarting", session_id, e);
+                false
+            }
+        }
+    };
+
+    if !alive {
+        let _ = upload_cancel_sync(node, &session_id);
+    }
+
+    Ok(alive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_path_for() {
+        assert_eq!(
+            UploadJournal::path_for("/data/set.tar"),
+            PathBuf::from("/data/set.tar.codex-upload")
+        );
+    }
+
+    #[test]
+    fn test_journal_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.journal");
+
+        assert!(UploadJournal::load(&path).unwrap().is_none());
+
+        let mut journal = UploadJournal::create(&path, "session-1", 1024).unwrap();
+        journal.begin_chunk(1024).unwrap();
+
+        let loaded = UploadJournal::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.in_flight, Some(1024));
+        assert!(!loaded.is_resumable());
+
+        journal.confirm_chunk(1024).unwrap();
+        let loaded = UploadJournal::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.session_id, "session-1");
+        assert_eq!(loaded.chunk_size, 1024);
+        assert_eq!(loaded.offset, 1024);
+        assert_eq!(loaded.chunks, 1);
+        assert!(loaded.is_resumable());
+        assert_eq!(loaded.path(), path.as_path());
+
+        loaded.remove().unwrap();
+        assert!(!path.exists());
+        journal.remove().unwrap();
+    }
+
+    #[test]
+    fn test_journal_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.journal");
+
+        let mut journal = UploadJournal::create_segmented(&path, 1024, 2048).unwrap();
+        assert!(journal.session_id.is_empty());
+
+        // Two chunks make up the first segment
+        journal.session_id = "session-1".to_string();
+        journal.confirm_chunk(1024).unwrap();
+        journal.confirm_chunk(1024).unwrap();
+        journal.finish_segment("zSegment1".to_string()).unwrap();
+        assert_eq!(journal.segments_end(), 2048);
+        assert!(journal.session_id.is_empty());
+
+        // The second segment is lost with its session after one chunk
+        journal.session_id = "session-2".to_string();
+        journal.confirm_chunk(1024).unwrap();
+        journal.begin_chunk(1024).unwrap();
+
+        let mut loaded = UploadJournal::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.segment_size, Some(2048));
+        assert_eq!(loaded.segments, journal.segments);
+        assert_eq!(loaded.offset, 3072);
+
+        loaded.restart_segment().unwrap();
+        let loaded = UploadJournal::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.offset, 2048);
+        assert_eq!(loaded.chunks, 2);
+        assert!(loaded.session_id.is_empty());
+        assert!(loaded.is_resumable());
+
+        let mut loaded = loaded;
+        loaded.discard_segments().unwrap();
+        assert_eq!(loaded.offset, 0);
+        assert_eq!(loaded.chunks, 0);
+        assert!(loaded.segments.is_empty());
+    }
+
+    #[test]
+    fn test_journal_without_segments() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.journal");
+        fs::write(
+            &path,
+            r#"{"session_id":"session-1","chunk_size":1024,"offset":2048,"chunks":2,"in_flight":null,"updated_at":"2026-01-01T00:00:00Z"}"#,
+        )
+        .unwrap();
+
+        let journal = UploadJournal::load(&path).unwrap().unwrap();
+        assert_eq!(journal.segment_size, None);
+        assert!(journal.segments.is_empty());
+        assert_eq!(journal.offset, 2048);
+    }
+
+    #[test]
+    fn test_corrupt_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("upload.journal");
+        fs::write(&path, "not json").unwrap();
+
+        assert!(matches!(
+            UploadJournal::load(&path),
+            Err(CodexError::UploadError { .. })
+        ));
+    }
+}
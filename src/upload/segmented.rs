@@ -0,0 +1,415 @@
+//! Segmented uploads
+//!
+//! Upload sessions live in the memory of the node, which runs in the same
+//! process as the bindings, so a crash of that process loses the session of
+//! an interrupted upload together with everything sent in it. With
+//! [`UploadOptions::segment_size`] set,
+//! [`resume_upload`](crate::upload::resume_upload) splits the source into
+//! segments and finalizes each of them in a session of its own. Finalized
+//! segments are datasets in the repository of the node and outlive the
+//! process. Their CIDs are recorded in the journal, so after a crash only the
+//! segment that was open is sent again.
+//!
+//! Once every segment is stored, a JSON [`SegmentManifest`] listing them is
+//! uploaded and its CID identifies the content.
+//! [`download_segmented`](crate::download::download_segmented) puts the
+//! segments back together.
+
+use crate::callback::deadline_after;
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::storage::exists;
+use crate::upload::file::{
+    upload_cancel_sync, upload_finalize_sync, upload_init_sync, upload_reader,
+};
+use crate::upload::resume::{
+    continue_session, send_reader, SendTarget, SharedReader, UploadJournal,
+};
+use crate::upload::session::upload_cancel;
+use crate::upload::types::{UploadOptions, UploadResult, UploadStrategy};
+use crate::upload::verify::{hash_reader, verify_upload, wants_digest, ContentDigest, Expected};
+use serde::{Deserialize, Serialize};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Value of [`SegmentManifest::format`]
+pub const SEGMENTS_FORMAT: &str = "codex-segments";
+
+/// Current version of the segment manifest format
+pub const SEGMENTS_FORMAT_VERSION: u32 = 1;
+
+/// Filename the segment manifest is uploaded under
+pub const SEGMENTS_INDEX_FILENAME: &str = "codex-segments.json";
+
+/// A finalized segment of a segmented upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSegment {
+    /// CID of the segment content
+    pub cid: String,
+    /// Size of the segment in bytes
+    pub size: u64,
+}
+
+/// Index of a segmented upload
+///
+/// The manifest is uploaded as JSON after every segment it lists, and its CID
+/// stands for the whole content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentManifest {
+    /// Always [`SEGMENTS_FORMAT`]
+    pub format: String,
+    /// Format version, currently [`SEGMENTS_FORMAT_VERSION`]
+    pub version: u32,
+    /// Segments in the order of the content
+    pub segments: Vec<UploadSegment>,
+}
+
+impl SegmentManifest {
+    /// Create a manifest from the segments of an upload, in order
+    pub fn new(segments: Vec<UploadSegment>) -> Self {
+        Self {
+            format: SEGMENTS_FORMAT.to_string(),
+            version: SEGMENTS_FORMAT_VERSION,
+            segments,
+        }
+    }
+
+    /// Parse a manifest and check its format
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        let manifest: Self = serde_json::from_slice(json)
+            .map_err(|e| CodexError::download_error(format!("Invalid segment manifest: {}", e)))?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// Serialize the manifest to JSON
+    pub fn to_json(&self) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec_pretty(self)?)
+    }
+
+    /// Size of the content in bytes
+    pub fn total_size(&self) -> u64 {
+        self.segments.iter().map(|segment| segment.size).sum()
+    }
+
+    /// Check the format marker and that every segment has content
+    pub fn validate(&self) -> Result<()> {
+        if self.format != SEGMENTS_FORMAT {
+            return Err(CodexError::download_error(format!(
+                "Not a segment manifest: format is '{}'",
+                self.format
+            )));
+        }
+
+        if self.version > SEGMENTS_FORMAT_VERSION {
+            return Err(CodexError::download_error(format!(
+                "Unsupported segment manifest version {}",
+                self.version
+            )));
+        }
+
+        if self
+            .segments
+            .iter()
+            .any(|segment| segment.cid.is_empty() || segment.size == 0)
+        {
+            return Err(CodexError::download_error(
+                "Segment manifest lists an empty segment",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// [`resume_upload`](crate::upload::resume_upload) with
+/// [`UploadOptions::segment_size`] set
+pub(crate) async fn resume_segmented<R>(
+    node: &CodexNode,
+    options: UploadOptions,
+    reader: R,
+) -> Result<UploadResult>
+where
+    R: Read + Seek + Send + 'static,
+{
+    options.validate()?;
+    options.reader_strategy()?;
+
+    let journal_path = options.journal.clone().ok_or_else(|| {
+        CodexError::invalid_parameter("journal", "A journal path is required to resume")
+    })?;
+    let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
+    let segment_size = options.segment_size.unwrap_or_default() as u64;
+
+    let start_time = Instant::now();
+    let journal = match UploadJournal::load(&journal_path)? {
+        Some(journal) => Some(stored_segments(node, journal, chunk_size, segment_size).await?),
+        None => None,
+    };
+
+    let task_node = node.clone();
+    let task_options = options.clone();
+
+    let (journal, digests) = tokio::task::spawn_blocking(move || {
+        let node = task_node;
+        let options = task_options;
+        let deadline = deadline_after(options.timeout);
+
+        let mut journal = match journal {
+            Some(journal) => journal,
+            None => UploadJournal::create_segmented(&journal_path, chunk_size, segment_size)?,
+        };
+
+        let reader = Arc::new(Mutex::new(reader));
+        send_segments(
+            &node,
+            &options,
+            &mut journal,
+            &reader,
+            segment_size,
+            deadline,
+        )?;
+
+        // Segments sent before an interruption are hashed from the source too
+        let digests = if wants_digest(&options) {
+            let mut reader = reader.lock().unwrap();
+            reader.seek(SeekFrom::Start(0))?;
+            journal
+                .segments
+                .iter()
+                .map(|segment| hash_reader((&mut *reader).take(segment.size)))
+                .collect::<std::io::Result<Vec<ContentDigest>>>()?
+        } else {
+            Vec::new()
+        };
+
+        Ok::<_, CodexError>((journal, digests))
+    })
+    .await??;
+
+    if journal.segments.is_empty() {
+        journal.remove()?;
+        return Err(CodexError::upload_error(
+            "Nothing to upload: the source is empty",
+        ));
+    }
+
+    let chunk_count = journal.chunks;
+    let manifest = SegmentManifest::new(journal.segments.clone());
+    let index_options = UploadOptions::new()
+        .filename(SEGMENTS_INDEX_FILENAME)
+        .verify(options.verify);
+    let index = upload_reader(node, index_options, Cursor::new(manifest.to_json()?)).await?;
+    journal.remove()?;
+
+    if options.verify {
+        for (i, segment) in manifest.segments.iter().enumerate() {
+            let expected =
+                Expected::new(&options, segment.size as usize).digest(digests.get(i).copied());
+            verify_upload(node, &segment.cid, &expected).await?;
+        }
+    }
+
+    Ok(UploadResult::new(index.cid, manifest.total_size() as usize)
+        .chunks(chunk_count)
+        .duration_ms(start_time.elapsed().as_millis() as u64)
+        .strategy(UploadStrategy::Chunked)
+        .verified(options.verify))
+}
+
+/// Keep the finalized segments of `journal` that the node still stores
+///
+/// A journal written with other chunk or segment sizes, or listing a segment
+/// the node no longer has, is reset to start the upload over.
+async fn stored_segments(
+    node: &CodexNode,
+    mut journal: UploadJournal,
+    chunk_size: usize,
+    segment_size: u64,
+) -> Result<UploadJournal> {
+    let mut stored = journal.chunk_size == chunk_size && journal.segment_size == Some(segment_size);
+    if !stored {
+        log::info!(
+            "Upload journal {} was written for other chunk or segment sizes, restarting upload",
+            journal.path().display()
+        );
+    }
+
+    if stored {
+        for segment in &journal.segments {
+            if !exists(node, &segment.cid).await? {
+                log::info!(
+                    "Segment {} of upload journal {} is no longer stored, restarting upload",
+                    segment.cid,
+                    journal.path().display()
+                );
+                stored = false;
+                break;
+            }
+        }
+    }
+
+    if !stored {
+        if !journal.session_id.is_empty() {
+            let _ = upload_cancel(node, &journal.session_id).await;
+        }
+        journal.chunk_size = chunk_size;
+        journal.segment_size = Some(segment_size);
+        journal.discard_segments()?;
+    }
+
+    Ok(journal)
+}
+
+/// Send the source segment by segment, continuing where `journal` left off
+///
+/// Returns once the last segment is finalized and recorded in the journal.
+fn send_segments<R>(
+    node: &CodexNode,
+    options: &UploadOptions,
+    journal: &mut UploadJournal,
+    reader: &Arc<Mutex<R>>,
+    segment_size: u64,
+    deadline: Option<Instant>,
+) -> Result<()>
+where
+    R: Read + Seek + Send + 'static,
+{
+    let chunk_size = journal.chunk_size;
+
+    // A session from before the interruption is only trusted once it accepts
+    // data, or, with the whole segment sent, once it is finalized
+    let mut resumed = false;
+    if !journal.session_id.is_empty() {
+        let limit = journal.segments_end() + segment_size - journal.offset;
+        resumed = if journal.is_resumable() {
+            let mut reader = reader.lock().unwrap();
+            continue_session(node, journal, &mut *reader, limit, deadline)?
+        } else {
+            let _ = upload_cancel_sync(node, &journal.session_id);
+            false
+        };
+
+        if !resumed {
+            journal.restart_segment()?;
+        }
+    }
+
+    loop {
+        let segment_start = journal.segments_end();
+        if journal.session_id.is_empty() {
+            if at_end(reader, segment_start)? {
+                return Ok(());
+            }
+
+            let session_id = upload_init_sync(node, options, deadline).map_err(|e| {
+                e.with_timeout_progress("upload", Some(segment_start as usize), None)
+            })?;
+            journal.open_segment(node, &session_id)?;
+        }
+
+        let session_id = journal.session_id.clone();
+        let start = (journal.offset as usize, journal.chunks);
+        let remaining = segment_start + segment_size - journal.offset;
+        reader
+            .lock()
+            .unwrap()
+            .seek(SeekFrom::Start(journal.offset))?;
+        send_reader(
+            node,
+            options,
+            SharedReader(reader.clone()).take(remaining),
+            SendTarget {
+                session_id: &session_id,
+                chunk_size,
+                deadline,
+            },
+            Some(&mut *journal),
+            start,
+        )?;
+
+        let cid = match upload_finalize_sync(node, &session_id, deadline) {
+            Ok(cid) => cid,
+            Err(e @ CodexError::Timeout { .. }) => {
+                let sent = journal.offset as usize;
+                return Err(e.with_timeout_progress("upload", Some(sent), None));
+            }
+            Err(e) if resumed => {
+                log::info!(
+                    "Upload session {} is gone ({}), restarting its segment",
+                    session_id,
+                    e
+                );
+                journal.restart_segment()?;
+                resumed = false;
+                continue;
+            }
+            Err(e) => {
+                // The session is closed; the next resume starts the segment over
+                journal.restart_segment()?;
+                return Err(e);
+            }
+        };
+        resumed = false;
+
+        let size = journal.offset - segment_start;
+        journal.finish_segment(cid)?;
+        if size < segment_size {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether the source has no data past `offset`
+fn at_end<R: Read + Seek>(reader: &Mutex<R>, offset: u64) -> Result<bool> {
+    let mut reader = reader.lock().unwrap();
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut byte = [0u8; 1];
+    Ok(reader.read(&mut byte)? == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(cid: &str, size: u64) -> UploadSegment {
+        UploadSegment {
+            cid: cid.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn test_manifest_roundtrip() {
+        let manifest = SegmentManifest::new(vec![segment("zA", 4096), segment("zB", 100)]);
+        assert_eq!(manifest.total_size(), 4196);
+
+        let parsed = SegmentManifest::from_json(&manifest.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, manifest);
+    }
+
+    #[test]
+    fn test_manifest_validation() {
+        let mut manifest = SegmentManifest::new(vec![segment("zA", 4096)]);
+        manifest.format = "codex-directory".to_string();
+        assert!(manifest.validate().is_err());
+
+        let mut manifest = SegmentManifest::new(vec![segment("zA", 4096)]);
+        manifest.version = SEGMENTS_FORMAT_VERSION + 1;
+        assert!(manifest.validate().is_err());
+
+        let manifest = SegmentManifest::new(vec![segment("zA", 4096), segment("zB", 0)]);
+        assert!(manifest.validate().is_err());
+
+        assert!(SegmentManifest::from_json(b"{}").is_err());
+    }
+
+    #[test]
+    fn test_at_end() {
+        let reader = Mutex::new(Cursor::new(vec![1u8, 2, 3]));
+        assert!(!at_end(&reader, 0).unwrap());
+        assert!(!at_end(&reader, 2).unwrap());
+        assert!(at_end(&reader, 3).unwrap());
+    }
+}
@@ -3,21 +3,30 @@
 //! This module provides low-level session management operations for uploads.
 //! These functions handle the lifecycle of upload sessions including initialization,
 //! finalization, and cancellation.
+//!
+//! [`UploadSession`] wraps the same operations in a handle that owns the session
+//! ID, so a session cannot be finalized twice and is cancelled if it is dropped
+//! before being finalized.
 
-use crate::callback::{c_callback, with_libcodex_lock, CallbackFuture};
+use crate::callback::{deadline_after, run_until, step_deadline, CallbackFuture};
 use crate::error::{CodexError, Result};
-use crate::ffi::{
-    codex_upload_cancel, codex_upload_finalize, codex_upload_init, free_c_string,
-    string_to_c_string,
-};
 use crate::node::lifecycle::CodexNode;
+use crate::throttle::Throttle;
+use crate::upload::chunks::send_chunk;
+use crate::upload::file::{
+    start_upload_cancel, upload_cancel_sync, upload_finalize_sync, upload_init_sync,
+};
 use crate::upload::types::UploadOptions;
-use libc::c_void;
+use bytes::Bytes;
+use std::time::Instant;
 
 /// Initialize an upload session
 ///
 /// Creates a new upload session with the specified options. Returns a session ID
-/// that can be used for subsequent chunk uploads.
+/// that can be used for subsequent chunk uploads. The manifest records
+/// [`UploadOptions::filename`] and [`UploadOptions::mimetype`]; no sniffing is
+/// done since the content is not known yet. Chunks are stored as given, so
+/// [`UploadOptions::compression`] is rejected.
 ///
 /// # Arguments
 ///
@@ -28,50 +37,12 @@ use libc::c_void;
 ///
 /// A session ID string that identifies this upload session
 pub async fn upload_init(node: &CodexNode, options: &UploadOptions) -> Result<String> {
+    options.check_uncompressed("upload_init")?;
+
     let node = node.clone();
     let options = options.clone();
 
-    tokio::task::spawn_blocking(move || {
-        options.validate()?;
-
-        let future = CallbackFuture::new();
-
-        let filepath_str = options
-            .filepath
-            .as_ref()
-            .and_then(|p| p.to_str())
-            .unwrap_or("");
-
-        let chunk_size = options.chunk_size.unwrap_or(1024 * 1024);
-        let context_ptr = future.context_ptr() as *mut c_void;
-
-        let result = with_libcodex_lock(|| unsafe {
-            node.with_ctx(|ctx| {
-                let c_filepath = string_to_c_string(filepath_str);
-                let result = codex_upload_init(
-                    ctx as *mut _,
-                    c_filepath,
-                    chunk_size,
-                    Some(c_callback),
-                    context_ptr,
-                );
-
-                if !c_filepath.is_null() {
-                    free_c_string(c_filepath);
-                }
-
-                result
-            })
-        });
-
-        if result != 0 {
-            return Err(CodexError::upload_error("Failed to initialize upload"));
-        }
-
-        let session_id = future.wait()?;
-        Ok(session_id)
-    })
-    .await?
+    tokio::task::spawn_blocking(move || upload_init_sync(&node, &options, step_deadline())).await?
 }
 
 /// Finalize an upload session
@@ -91,42 +62,8 @@ pub async fn upload_finalize(node: &CodexNode, session_id: &str) -> Result<Strin
     let node = node.clone();
     let session_id = session_id.to_string();
 
-    tokio::task::spawn_blocking(move || {
-        if session_id.is_empty() {
-            return Err(CodexError::invalid_parameter(
-                "session_id",
-                "Session ID cannot be empty",
-            ));
-        }
-
-        let future = CallbackFuture::new();
-
-        let context_ptr = future.context_ptr() as *mut c_void;
-
-        let result = with_libcodex_lock(|| unsafe {
-            node.with_ctx(|ctx| {
-                let c_session_id = string_to_c_string(&session_id);
-                let result = codex_upload_finalize(
-                    ctx as *mut _,
-                    c_session_id,
-                    Some(c_callback),
-                    context_ptr,
-                );
-
-                free_c_string(c_session_id);
-
-                result
-            })
-        });
-
-        if result != 0 {
-            return Err(CodexError::upload_error("Failed to finalize upload"));
-        }
-
-        let cid = future.wait()?;
-        Ok(cid)
-    })
-    .await?
+    tokio::task::spawn_blocking(move || upload_finalize_sync(&node, &session_id, step_deadline()))
+        .await?
 }
 
 /// Cancel an upload session
@@ -142,36 +79,148 @@ pub async fn upload_cancel(node: &CodexNode, session_id: &str) -> Result<()> {
     let node = node.clone();
     let session_id = session_id.to_string();
 
-    tokio::task::spawn_blocking(move || {
-        if session_id.is_empty() {
-            return Err(CodexError::invalid_parameter(
-                "session_id",
-                "Session ID cannot be empty",
-            ));
-        }
+    tokio::task::spawn_blocking(move || upload_cancel_sync(&node, &session_id)).await?
+}
 
-        let future = CallbackFuture::new();
+/// An open upload session
+///
+/// Created by [`UploadSession::start`]. Chunks are sent with
+/// [`upload_chunk`](UploadSession::upload_chunk) and the session is closed by
+/// either [`finalize`](UploadSession::finalize) or
+/// [`cancel`](UploadSession::cancel), both of which consume the handle. A
+/// session dropped without being finalized is cancelled, as is one whose
+/// finalize failed.
+///
+/// [`UploadOptions::timeout`] bounds the whole session, from `start` to
+/// `finalize`. A call still running when it passes fails with
+/// [`CodexError::Timeout`] and cancels the session; the handle cannot be used
+/// after that. A chunk the node was still reading stays alive until libcodex
+/// releases it.
+///
+/// Chunks are held back as needed to respect [`UploadOptions::rate_limit`]
+/// and the node-wide [`CodexNode::upload_limiter`].
+pub struct UploadSession {
+    node: CodexNode,
+    id: String,
+    bytes_uploaded: usize,
+    chunks: usize,
+    deadline: Option<Instant>,
+    throttle: Throttle,
+    closed: bool,
+}
 
-        let context_ptr = future.context_ptr() as *mut c_void;
+impl UploadSession {
+    /// Initialize a new upload session
+    pub async fn start(node: &CodexNode, options: &UploadOptions) -> Result<Self> {
+        let deadline = deadline_after(options.timeout);
+        let id = run_until(deadline, upload_init(node, options))
+            .await
+            .map_err(|e| e.with_timeout_progress("upload", Some(0), None))?;
+        Ok(Self {
+            node: node.clone(),
+            id,
+            bytes_uploaded: 0,
+            chunks: 0,
+            deadline,
+            throttle: Throttle::upload(node, options.rate_limit.as_ref()),
+            closed: false,
+        })
+    }
+
+    /// The session ID used by libcodex
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Bytes confirmed by the node so far
+    pub fn bytes_uploaded(&self) -> usize {
+        self.bytes_uploaded
+    }
+
+    /// Chunks confirmed by the node so far
+    pub fn chunks_uploaded(&self) -> usize {
+        self.chunks
+    }
+
+    /// Upload the next chunk of the content
+    pub async fn upload_chunk(&mut self, chunk: impl Into<Bytes>) -> Result<()> {
+        let chunk = chunk.into();
+        let len = chunk.len();
+
+        if self.closed {
+            return Err(CodexError::upload_error("Upload session is closed"));
+        }
 
-        let result = with_libcodex_lock(|| unsafe {
-            node.with_ctx(|ctx| {
-                let c_session_id = string_to_c_string(&session_id);
-                let result =
-                    codex_upload_cancel(ctx as *mut _, c_session_id, Some(c_callback), context_ptr);
+        let send = async {
+            self.throttle.acquire(len).await;
+            send_chunk(&self.node, &self.id, chunk).await
+        };
+        let sent = run_until(self.deadline, send).await;
+        if let Err(e @ CodexError::Timeout { .. }) = sent {
+            self.cancel_in_background();
+            return Err(e.with_timeout_progress("upload", Some(self.bytes_uploaded), None));
+        }
+        sent?;
 
-                free_c_string(c_session_id);
+        self.bytes_uploaded += len;
+        self.chunks += 1;
+        Ok(())
+    }
 
-                result
-            })
-        });
+    /// Finalize the session and return the CID of the uploaded content
+    pub async fn finalize(mut self) -> Result<String> {
+        if self.closed {
+            return Err(CodexError::upload_error("Upload session is closed"));
+        }
 
-        if result != 0 {
-            return Err(CodexError::upload_error("Failed to cancel upload"));
+        let bytes = self.bytes_uploaded;
+        let cid = run_until(self.deadline, upload_finalize(&self.node, &self.id))
+            .await
+            .map_err(|e| e.with_timeout_progress("upload", Some(bytes), Some(bytes)))?;
+        self.closed = true;
+        Ok(cid)
+    }
+
+    /// Cancel the session and wait for the node to confirm
+    pub async fn cancel(mut self) -> Result<()> {
+        self.closed = true;
+        upload_cancel(&self.node, &self.id).await
+    }
+
+    /// Give up ownership of the session and return its ID
+    ///
+    /// The session is left open; it is up to the caller to finalize or cancel
+    /// it with the free functions.
+    pub fn into_id(mut self) -> String {
+        self.closed = true;
+        std::mem::take(&mut self.id)
+    }
+
+    fn cancel_in_background(&mut self) {
+        self.closed = true;
+
+        // Fire and forget: the callback of a dropped future is ignored
+        let future = CallbackFuture::new();
+        if let Err(e) = start_upload_cancel(&self.node, &self.id, &future) {
+            log::warn!("Failed to cancel upload session {}: {}", self.id, e);
         }
+    }
+}
 
-        future.wait()?;
-        Ok(())
-    })
-    .await?
+impl std::fmt::Debug for UploadSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UploadSession")
+            .field("id", &self.id)
+            .field("bytes_uploaded", &self.bytes_uploaded)
+            .field("chunks", &self.chunks)
+            .finish()
+    }
+}
+
+impl Drop for UploadSession {
+    fn drop(&mut self) {
+        if !self.closed {
+            self.cancel_in_background();
+        }
+    }
 }
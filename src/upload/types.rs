@@ -1,13 +1,22 @@
 use crate::error::{CodexError, Result};
+use crate::throttle::RateLimiter;
+use crate::upload::compression::Compression;
+use crate::upload::mime::{mimetype_for_filename, sniff_mimetype, upload_name};
+use crate::upload::pipeline::DEFAULT_READ_AHEAD;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
 
+/// How content is handed to the node
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum UploadStrategy {
+    /// Read the source in process and send it through an upload session chunk by chunk
     Chunked,
+    /// Let the node read the file itself with `codex_upload_file`; files only
     Stream,
+    /// `Stream` for files unless an option needs the chunked path, `Chunked`
+    /// for readers; see [`UploadOptions::file_strategy`]
     Auto,
 }
 
@@ -24,6 +33,8 @@ pub struct UploadProgress {
     pub percentage: f64,
     pub current_chunk: Option<usize>,
     pub total_chunks: Option<usize>,
+    /// Upload speed in bytes per second (if available)
+    pub speed_bps: Option<f64>,
 }
 
 impl UploadProgress {
@@ -44,6 +55,7 @@ impl UploadProgress {
             percentage: percentage.min(1.0),
             current_chunk: None,
             total_chunks: None,
+            speed_bps: None,
         }
     }
 
@@ -58,6 +70,12 @@ impl UploadProgress {
         progress.total_chunks = Some(total_chunks);
         progress
     }
+
+    /// Set the upload speed
+    pub fn with_speed(mut self, speed_bps: f64) -> Self {
+        self.speed_bps = Some(speed_bps);
+        self
+    }
 }
 
 #[derive(Clone)]
@@ -67,11 +85,35 @@ pub struct UploadOptions {
     pub strategy: UploadStrategy,
     pub on_progress: Option<Arc<dyn Fn(UploadProgress) + Send + Sync>>,
     pub verify: bool,
+    /// Also read the content back from the node and compare it with what was sent
+    pub verify_content: bool,
+    #[deprecated(note = "libcodex has nowhere to record it; the value is ignored")]
     pub metadata: Option<serde_json::Value>,
+    /// Filename recorded in the manifest; defaults to the last component of `filepath`
+    pub filename: Option<String>,
+    /// Mimetype recorded in the manifest; defaults to the one implied by the
+    /// filename, then to the one sniffed from the content
+    ///
+    /// It is recorded by appending its extension to the filename, see
+    /// [`UploadOptions::mimetype`].
+    pub mimetype: Option<String>,
     pub timeout: Option<u64>,
+    /// Number of chunks read ahead of the node by streaming uploads, 0 disables read-ahead
+    pub read_ahead: usize,
+    /// Upper bound on memory held by read-ahead buffers, in bytes
+    pub max_buffered_bytes: Option<usize>,
+    /// Journal file recording progress so the upload can be resumed
+    pub journal: Option<PathBuf>,
+    /// Size of the independently finalized segments of a journaled upload
+    pub segment_size: Option<usize>,
+    /// Compression applied to the content before it is sent
+    pub compression: Compression,
+    /// Limit on the rate at which chunks are sent, on top of the node-wide limit
+    pub rate_limit: Option<RateLimiter>,
 }
 
 impl std::fmt::Debug for UploadOptions {
+    #[allow(deprecated)]
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("UploadOptions")
             .field("filepath", &self.filepath)
@@ -79,13 +121,23 @@ impl std::fmt::Debug for UploadOptions {
             .field("strategy", &self.strategy)
             .field("on_progress", &self.on_progress.is_some())
             .field("verify", &self.verify)
+            .field("verify_content", &self.verify_content)
             .field("metadata", &self.metadata)
+            .field("filename", &self.filename)
+            .field("mimetype", &self.mimetype)
             .field("timeout", &self.timeout)
+            .field("read_ahead", &self.read_ahead)
+            .field("max_buffered_bytes", &self.max_buffered_bytes)
+            .field("journal", &self.journal)
+            .field("segment_size", &self.segment_size)
+            .field("compression", &self.compression)
+            .field("rate_limit", &self.rate_limit)
             .finish()
     }
 }
 
 impl Default for UploadOptions {
+    #[allow(deprecated)]
     fn default() -> Self {
         Self {
             filepath: None,
@@ -93,8 +145,17 @@ impl Default for UploadOptions {
             strategy: UploadStrategy::Auto,
             on_progress: None,
             verify: true,
+            verify_content: false,
             metadata: None,
+            filename: None,
+            mimetype: None,
             timeout: Some(300),
+            read_ahead: DEFAULT_READ_AHEAD,
+            max_buffered_bytes: None,
+            journal: None,
+            segment_size: None,
+            compression: Compression::None,
+            rate_limit: None,
         }
     }
 }
@@ -132,16 +193,204 @@ impl UploadOptions {
         self
     }
 
+    pub fn verify_content(mut self, verify_content: bool) -> Self {
+        self.verify_content = verify_content;
+        self
+    }
+
+    #[deprecated(note = "libcodex has nowhere to record it; the value is ignored")]
+    #[allow(deprecated)]
     pub fn metadata(mut self, metadata: serde_json::Value) -> Self {
         self.metadata = Some(metadata);
         self
     }
 
+    /// Set the filename recorded in the manifest
+    ///
+    /// If a [`mimetype`](Self::mimetype) is set or sniffed that the extension
+    /// does not imply, the recorded filename gains that mimetype's extension.
+    pub fn filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+
+    /// Set the mimetype recorded in the manifest
+    ///
+    /// libcodex derives the mimetype from the filename extension, so if the
+    /// filename does not already imply this mimetype the matching extension is
+    /// appended to it: `notes.txt` uploaded as `text/csv` is recorded as
+    /// `notes.txt.csv`. Without a filename the content is recorded as
+    /// `data.<extension>`. Uploads fail with [`CodexError::InvalidParameter`]
+    /// for mimetypes that have no known extension.
+    pub fn mimetype(mut self, mimetype: impl Into<String>) -> Self {
+        self.mimetype = Some(mimetype.into());
+        self
+    }
+
     pub fn timeout(mut self, timeout: u64) -> Self {
         self.timeout = Some(timeout);
         self
     }
 
+    pub fn read_ahead(mut self, chunks: usize) -> Self {
+        self.read_ahead = chunks;
+        self
+    }
+
+    pub fn max_buffered_bytes(mut self, bytes: usize) -> Self {
+        self.max_buffered_bytes = Some(bytes);
+        self
+    }
+
+    pub fn journal<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.journal = Some(path.into());
+        self
+    }
+
+    /// Split a journaled upload into segments of `size` bytes
+    ///
+    /// Each segment is finalized as a dataset of its own, which the node keeps
+    /// across restarts, so [`resume_upload`](crate::upload::resume_upload)
+    /// can continue after the last finalized segment even when the process
+    /// crashed. The CID of the upload is that of a
+    /// [`SegmentManifest`](crate::upload::SegmentManifest) listing the
+    /// segments. `size` must be a multiple of the chunk size.
+    pub fn segment_size(mut self, size: usize) -> Self {
+        self.segment_size = Some(size);
+        self
+    }
+
+    /// Compress the content before it is sent
+    ///
+    /// The codec is recorded by appending its extension to the filename, so
+    /// `logs.json` is recorded as `logs.json.zst` and an upload without a
+    /// filename as `data.zst`.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Limit the rate at which this upload sends data
+    ///
+    /// The limiter may be shared with other transfers and its rate changed
+    /// while they run. The node-wide [`CodexNode::upload_limiter`](crate::CodexNode::upload_limiter)
+    /// applies as well.
+    pub fn rate_limit(mut self, limiter: RateLimiter) -> Self {
+        self.rate_limit = Some(limiter);
+        self
+    }
+
+    /// Read-ahead depth in chunks once `max_buffered_bytes` is taken into account
+    ///
+    /// One chunk is always held by the upload itself, so the bound allows
+    /// `max_buffered_bytes / chunk_size - 1` chunks of read-ahead.
+    pub fn effective_read_ahead(&self) -> usize {
+        let chunk_size = self.chunk_size.unwrap_or(1024 * 1024).max(1);
+        match self.max_buffered_bytes {
+            Some(max) => self.read_ahead.min((max / chunk_size).saturating_sub(1)),
+            None => self.read_ahead,
+        }
+    }
+
+    /// Filename that will be recorded in the manifest
+    pub fn recorded_filename(&self) -> Option<String> {
+        self.filename.clone().or_else(|| {
+            self.filepath
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .and_then(|name| name.to_str())
+                .map(str::to_string)
+        })
+    }
+
+    /// Name handed to libcodex when a session is opened
+    pub(crate) fn upload_name(&self) -> Result<String> {
+        let name = upload_name(
+            self.recorded_filename().as_deref(),
+            self.mimetype.as_deref(),
+        )?;
+        Ok(self
+            .compression
+            .recorded_name(Some(name.as_str()).filter(|name| !name.is_empty()))
+            .unwrap_or_default())
+    }
+
+    /// Reject compression for uploads that send content as it is given
+    pub(crate) fn check_uncompressed(&self, operation: &str) -> Result<()> {
+        if self.compression.is_enabled() {
+            return Err(CodexError::invalid_parameter(
+                "compression",
+                format!(
+                    "{} sends content as given; use upload_reader or upload_bytes to compress",
+                    operation
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether the mimetype has to be sniffed from the content
+    ///
+    /// True when there is neither an explicit mimetype nor a filename whose
+    /// extension implies one. Content without a filename is then recorded as
+    /// `data.<extension>`.
+    pub(crate) fn needs_sniff(&self) -> bool {
+        self.mimetype.is_none()
+            && self
+                .recorded_filename()
+                .and_then(|name| mimetype_for_filename(&name))
+                .is_none()
+    }
+
+    /// Fill in the mimetype sniffed from the first bytes of the content
+    pub(crate) fn sniffed(mut self, head: &[u8]) -> Self {
+        if self.needs_sniff() {
+            self.mimetype = sniff_mimetype(head).map(str::to_string);
+        }
+        self
+    }
+
+    /// Strategy `upload_file` uses with these options
+    ///
+    /// Journals, compression and rate limits are driven by the chunked path,
+    /// and native uploads record the name of the file on disk, so `Auto` only
+    /// streams files natively when none of these nor a filename or mimetype is
+    /// requested.
+    ///
+    /// The size of the file does not change the choice. The node reads a
+    /// natively streamed file in blocks of the chunk size either way, so
+    /// neither path needs more memory for large files, and the chunked path
+    /// only adds a copy of each chunk through the process. The size of a
+    /// source matters for readers, which are always chunked.
+    pub fn file_strategy(&self) -> UploadStrategy {
+        match self.strategy {
+            UploadStrategy::Auto
+                if self.journal.is_some()
+                    || self.filename.is_some()
+                    || self.mimetype.is_some()
+                    || self.compression.is_enabled()
+                    || self.rate_limit.is_some() =>
+            {
+                UploadStrategy::Chunked
+            }
+            UploadStrategy::Auto => UploadStrategy::Stream,
+            strategy => strategy,
+        }
+    }
+
+    /// Strategy used to upload from a reader with these options
+    ///
+    /// Readers can only be uploaded in chunks, so `Stream` is rejected.
+    pub fn reader_strategy(&self) -> Result<UploadStrategy> {
+        match self.strategy {
+            UploadStrategy::Stream => Err(CodexError::invalid_parameter(
+                "strategy",
+                "Stream uploads need a file path; use upload_file",
+            )),
+            _ => Ok(UploadStrategy::Chunked),
+        }
+    }
+
     pub fn validate(&self) -> Result<()> {
         if let Some(chunk_size) = self.chunk_size {
             if chunk_size == 0 {
@@ -161,6 +410,43 @@ impl UploadOptions {
             }
         }
 
+        if let Some(ref filename) = self.filename {
+            if filename.is_empty() || filename.contains(['/', '\\']) {
+                return Err(CodexError::invalid_parameter(
+                    "filename",
+                    "Filename must be a non-empty name without path separators",
+                ));
+            }
+        }
+
+        self.upload_name()?;
+
+        self.compression.validate()?;
+
+        if self.compression.is_enabled() && self.journal.is_some() {
+            return Err(CodexError::invalid_parameter(
+                "compression",
+                "Compressed uploads cannot be journaled",
+            ));
+        }
+
+        if let Some(segment_size) = self.segment_size {
+            let chunk_size = self.chunk_size.unwrap_or(1024 * 1024);
+            if segment_size == 0 || segment_size % chunk_size != 0 {
+                return Err(CodexError::invalid_parameter(
+                    "segment_size",
+                    "Segment size must be a non-zero multiple of the chunk size",
+                ));
+            }
+
+            if self.journal.is_none() {
+                return Err(CodexError::invalid_parameter(
+                    "segment_size",
+                    "Segmented uploads need a journal",
+                ));
+            }
+        }
+
         Ok(())
     }
 }
@@ -172,6 +458,10 @@ pub struct UploadResult {
     pub chunks: Option<usize>,
     pub duration_ms: u64,
     pub verified: bool,
+    /// Strategy the upload actually used; `Auto` if it was not recorded
+    pub strategy: UploadStrategy,
+    /// Compression applied to the content; `size` is the compressed size
+    pub compression: Compression,
 }
 
 impl UploadResult {
@@ -182,6 +472,8 @@ impl UploadResult {
             chunks: None,
             duration_ms: 0,
             verified: false,
+            strategy: UploadStrategy::Auto,
+            compression: Compression::None,
         }
     }
 
@@ -199,6 +491,16 @@ impl UploadResult {
         self.verified = verified;
         self
     }
+
+    pub fn strategy(mut self, strategy: UploadStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
 }
 
 #[cfg(test)]
@@ -215,6 +517,8 @@ mod tests {
         let chunked = UploadProgress::new_chunked(500, Some(1000), 2, 4);
         assert_eq!(chunked.current_chunk, Some(2));
         assert_eq!(chunked.total_chunks, Some(4));
+        assert_eq!(chunked.speed_bps, None);
+        assert_eq!(chunked.with_speed(250.0).speed_bps, Some(250.0));
     }
 
     #[test]
@@ -224,15 +528,195 @@ mod tests {
             .chunk_size(2048)
             .strategy(UploadStrategy::Chunked)
             .verify(false)
+            .verify_content(true)
             .timeout(600);
 
         assert_eq!(options.filepath, Some(PathBuf::from("/test/file.txt")));
         assert_eq!(options.chunk_size, Some(2048));
         assert_eq!(options.strategy, UploadStrategy::Chunked);
         assert_eq!(options.verify, false);
+        assert!(options.verify_content);
         assert_eq!(options.timeout, Some(600));
     }
 
+    #[test]
+    fn test_effective_read_ahead() {
+        let options = UploadOptions::new().chunk_size(1024);
+        assert_eq!(options.effective_read_ahead(), DEFAULT_READ_AHEAD);
+
+        let options = options.read_ahead(8).max_buffered_bytes(4 * 1024);
+        assert_eq!(options.effective_read_ahead(), 3);
+
+        let options = options.max_buffered_bytes(512);
+        assert_eq!(options.effective_read_ahead(), 0);
+
+        let options = UploadOptions::new().read_ahead(0);
+        assert_eq!(options.effective_read_ahead(), 0);
+    }
+
+    #[test]
+    fn test_file_strategy() {
+        let options = UploadOptions::new();
+        assert_eq!(options.file_strategy(), UploadStrategy::Stream);
+
+        let options = UploadOptions::new().on_progress(|_| {});
+        assert_eq!(options.file_strategy(), UploadStrategy::Stream);
+
+        let options = UploadOptions::new().journal("/tmp/upload.journal");
+        assert_eq!(options.file_strategy(), UploadStrategy::Chunked);
+
+        let options = UploadOptions::new()
+            .journal("/tmp/upload.journal")
+            .strategy(UploadStrategy::Stream);
+        assert_eq!(options.file_strategy(), UploadStrategy::Stream);
+
+        let options = UploadOptions::new().strategy(UploadStrategy::Chunked);
+        assert_eq!(options.file_strategy(), UploadStrategy::Chunked);
+    }
+
+    #[test]
+    fn test_named_uploads_are_chunked() {
+        let options = UploadOptions::new().filename("photo.png");
+        assert_eq!(options.file_strategy(), UploadStrategy::Chunked);
+
+        let options = UploadOptions::new().mimetype("image/png");
+        assert_eq!(options.file_strategy(), UploadStrategy::Chunked);
+    }
+
+    #[test]
+    fn test_upload_name() {
+        assert_eq!(UploadOptions::new().upload_name().unwrap(), "");
+        assert_eq!(
+            UploadOptions::new()
+                .filepath("/data/report.pdf")
+                .upload_name()
+                .unwrap(),
+            "report.pdf"
+        );
+        assert_eq!(
+            UploadOptions::new()
+                .filepath("/data/report.pdf")
+                .filename("summary.pdf")
+                .upload_name()
+                .unwrap(),
+            "summary.pdf"
+        );
+        assert_eq!(
+            UploadOptions::new()
+                .filename("IMG_0001")
+                .mimetype("image/jpeg")
+                .upload_name()
+                .unwrap(),
+            "IMG_0001.jpg"
+        );
+    }
+
+    #[test]
+    fn test_rate_limited_uploads_are_chunked() {
+        let options = UploadOptions::new().rate_limit(RateLimiter::new(1024));
+        assert_eq!(options.file_strategy(), UploadStrategy::Chunked);
+    }
+
+    #[test]
+    fn test_compressed_uploads() {
+        let options = UploadOptions::new().compression(Compression::zstd());
+        assert_eq!(options.file_strategy(), UploadStrategy::Chunked);
+        assert_eq!(options.upload_name().unwrap(), "data.zst");
+        assert_eq!(
+            options.clone().filename("logs.json").upload_name().unwrap(),
+            "logs.json.zst"
+        );
+        assert_eq!(
+            options
+                .clone()
+                .filename("IMG_0001")
+                .mimetype("image/jpeg")
+                .upload_name()
+                .unwrap(),
+            "IMG_0001.jpg.zst"
+        );
+
+        assert!(options.validate().is_ok());
+        assert!(options.check_uncompressed("upload_init").is_err());
+        assert!(UploadOptions::new()
+            .check_uncompressed("upload_init")
+            .is_ok());
+        assert!(options
+            .clone()
+            .journal("/tmp/upload.journal")
+            .validate()
+            .is_err());
+        assert!(UploadOptions::new()
+            .compression(Compression::Zstd { level: 100 })
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_sniffed_mimetype() {
+        let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+        let options = UploadOptions::new().filename("IMG_0001");
+        assert!(options.needs_sniff());
+        assert_eq!(options.sniffed(png).upload_name().unwrap(), "IMG_0001.png");
+
+        // An extension or an explicit mimetype wins over the content
+        let options = UploadOptions::new().filename("notes.txt");
+        assert!(!options.needs_sniff());
+        assert_eq!(options.sniffed(png).upload_name().unwrap(), "notes.txt");
+
+        // Without a filename the sniffed mimetype is recorded under `data`
+        let options = UploadOptions::new();
+        assert!(options.needs_sniff());
+        assert_eq!(
+            options.clone().sniffed(png).upload_name().unwrap(),
+            "data.png"
+        );
+        assert_eq!(options.sniffed(b"\x01\x02\0").upload_name().unwrap(), "");
+    }
+
+    #[test]
+    fn test_filename_and_mimetype_validation() {
+        assert!(UploadOptions::new().filename("a.txt").validate().is_ok());
+        assert!(UploadOptions::new().filename("").validate().is_err());
+        assert!(UploadOptions::new().filename("a/b.txt").validate().is_err());
+        assert!(UploadOptions::new()
+            .mimetype("image/png")
+            .validate()
+            .is_ok());
+        assert!(UploadOptions::new()
+            .filename("cat")
+            .mimetype("image/png")
+            .validate()
+            .is_ok());
+        assert!(UploadOptions::new()
+            .filename("cat")
+            .mimetype("application/x-unknown")
+            .validate()
+            .is_err());
+    }
+
+    #[test]
+    fn test_reader_strategy() {
+        assert_eq!(
+            UploadOptions::new().reader_strategy().unwrap(),
+            UploadStrategy::Chunked
+        );
+        assert_eq!(
+            UploadOptions::new()
+                .strategy(UploadStrategy::Chunked)
+                .reader_strategy()
+                .unwrap(),
+            UploadStrategy::Chunked
+        );
+        assert!(matches!(
+            UploadOptions::new()
+                .strategy(UploadStrategy::Stream)
+                .reader_strategy(),
+            Err(CodexError::InvalidParameter { .. })
+        ));
+    }
+
     #[test]
     fn test_upload_options_validation() {
         let mut options = UploadOptions::new();
@@ -246,17 +730,35 @@ mod tests {
         assert!(options.validate().is_err());
     }
 
+    #[test]
+    fn test_segment_size_validation() {
+        let options = UploadOptions::new()
+            .chunk_size(1024)
+            .journal("/tmp/upload.journal");
+        assert!(options.clone().segment_size(4096).validate().is_ok());
+        assert!(options.clone().segment_size(0).validate().is_err());
+        assert!(options.segment_size(1500).validate().is_err());
+
+        let unjournaled = UploadOptions::new().chunk_size(1024).segment_size(4096);
+        assert!(matches!(
+            unjournaled.validate(),
+            Err(CodexError::InvalidParameter { .. })
+        ));
+    }
+
     #[test]
     fn test_upload_result() {
         let result = UploadResult::new("QmExample".to_string(), 1024)
             .chunks(4)
             .duration_ms(5000)
-            .verified(true);
+            .verified(true)
+            .strategy(UploadStrategy::Stream);
 
         assert_eq!(result.cid, "QmExample");
         assert_eq!(result.size, 1024);
         assert_eq!(result.chunks, Some(4));
         assert_eq!(result.duration_ms, 5000);
         assert!(result.verified);
+        assert_eq!(result.strategy, UploadStrategy::Stream);
     }
 }
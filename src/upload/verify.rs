@@ -0,0 +1,188 @@
+//! Post-upload verification
+//!
+//! With [`UploadOptions::verify`](crate::upload::UploadOptions::verify) set, the
+//! high-level upload functions check the result of every upload before
+//! reporting it as verified: the content must exist on the node and its
+//! manifest must record the number of bytes sent and the block size the
+//! session was started with. With
+//! [`UploadOptions::verify_content`](crate::upload::UploadOptions::verify_content)
+//! the content is also streamed back from local storage and its SHA-256 is
+//! compared with the digest of the data that was sent.
+//!
+//! Failures are reported as [`CodexError::VerificationFailed`].
+
+use crate::download::{download_stream, DownloadStreamOptions};
+use crate::error::{CodexError, Result, VerificationFailure};
+use crate::node::lifecycle::CodexNode;
+use crate::storage::{exists, fetch};
+use crate::upload::types::{UploadOptions, UploadResult};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex};
+
+pub(crate) type ContentDigest = [u8; 32];
+
+/// What an upload is expected to have stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Expected {
+    pub size: usize,
+    pub block_size: usize,
+    pub digest: Option<ContentDigest>,
+}
+
+impl Expected {
+    pub fn new(options: &UploadOptions, size: usize) -> Self {
+        Self {
+            size,
+            block_size: options.chunk_size.unwrap_or(1024 * 1024),
+            digest: None,
+        }
+    }
+
+    pub fn digest(mut self, digest: Option<ContentDigest>) -> Self {
+        self.digest = digest;
+        self
+    }
+}
+
+/// Check that `cid` holds what was uploaded
+pub(crate) async fn verify_upload(node: &CodexNode, cid: &str, expected: &Expected) -> Result<()> {
+    let fail = |failure| Err(CodexError::verification_failed(cid, failure));
+
+    if !exists(node, cid).await? {
+        return fail(VerificationFailure::Missing);
+    }
+
+    let manifest = fetch(node, cid).await?;
+
+    if manifest.dataset_size != expected.size {
+        return fail(VerificationFailure::SizeMismatch {
+            expected: expected.size,
+            actual: manifest.dataset_size,
+        });
+    }
+
+    if manifest.block_size != expected.block_size {
+        return fail(VerificationFailure::BlockSizeMismatch {
+            expected: expected.block_size,
+            actual: manifest.block_size,
+        });
+    }
+
+    if let Some(digest) = expected.digest {
+        let hasher = ContentHasher::default();
+        let options = DownloadStreamOptions::new(cid)
+            .local(true)
+            .writer(hasher.clone());
+        download_stream(node, cid, options).await?;
+
+        if hasher.finish() != digest {
+            return fail(VerificationFailure::ContentMismatch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify `result` if `options` ask for it and mark it as verified
+///
+/// `digest` is the SHA-256 of the data sent, needed for content verification.
+pub(crate) async fn verify_result(
+    node: &CodexNode,
+    options: &UploadOptions,
+    result: UploadResult,
+    digest: Option<ContentDigest>,
+) -> Result<UploadResult> {
+    if !options.verify {
+        return Ok(result.verified(false));
+    }
+
+    let expected = Expected::new(options, result.size).digest(digest);
+    verify_upload(node, &result.cid, &expected).await?;
+    Ok(result.verified(true))
+}
+
+/// Whether the data sent has to be hashed for `options`
+pub(crate) fn wants_digest(options: &UploadOptions) -> bool {
+    options.verify && options.verify_content
+}
+
+/// Running SHA-256 that can be shared with a reader or writer thread
+#[derive(Clone, Default)]
+pub(crate) struct ContentHasher(Arc<Mutex<Sha256>>);
+
+impl ContentHasher {
+    pub fn update(&self, data: &[u8]) {
+        self.0.lock().unwrap().update(data);
+    }
+
+    pub fn finish(&self) -> ContentDigest {
+        self.0.lock().unwrap().clone().finalize().into()
+    }
+}
+
+impl Write for ContentHasher {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reader that hashes everything read through it
+pub(crate) struct HashingReader<R> {
+    inner: R,
+    hasher: ContentHasher,
+}
+
+impl<R> HashingReader<R> {
+    pub fn new(inner: R, hasher: ContentHasher) -> Self {
+        Self { inner, hasher }
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// SHA-256 of everything `reader` yields
+pub(crate) fn hash_reader<R: Read>(mut reader: R) -> std::io::Result<ContentDigest> {
+    let mut hasher = ContentHasher::default();
+    std::io::copy(&mut reader, &mut hasher)?;
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_hashing_reader_matches_hash_reader() {
+        let data: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+        let hasher = ContentHasher::default();
+        let mut reader = HashingReader::new(Cursor::new(data.clone()), hasher.clone());
+        let mut sink = Vec::new();
+        reader.read_to_end(&mut sink).unwrap();
+
+        assert_eq!(sink, data);
+        assert_eq!(hasher.finish(), hash_reader(Cursor::new(&data)).unwrap());
+        assert_eq!(hasher.finish(), <[u8; 32]>::from(Sha256::digest(&data)));
+    }
+
+    #[test]
+    fn test_expected_block_size() {
+        let expected = Expected::new(&UploadOptions::new().chunk_size(4096), 10);
+        assert_eq!(expected.block_size, 4096);
+        assert_eq!(expected.size, 10);
+        assert!(expected.digest.is_none());
+    }
+}
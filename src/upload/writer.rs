@@ -0,0 +1,313 @@
+//! Push-style uploads
+//!
+//! [`UploadWriter`] lets code that produces data incrementally (serializers,
+//! compressors, archive builders) write straight into an upload session. It
+//! implements both [`std::io::Write`] and [`tokio::io::AsyncWrite`], buffers
+//! writes up to the configured chunk size and uploads each full chunk as soon
+//! as it is complete.
+
+use crate::callback::{step_deadline, CallbackFuture};
+use crate::error::{CodexError, Result};
+use crate::node::lifecycle::CodexNode;
+use crate::throttle::{Acquire, Throttle};
+use crate::upload::async_reader::{upload_finalize_async, upload_init_async};
+use crate::upload::file::{
+    start_upload_cancel, start_upload_chunk, upload_chunk_sync, upload_finalize_sync,
+    upload_init_sync,
+};
+use crate::upload::types::{UploadOptions, UploadProgress};
+use bytes::Bytes;
+use std::future::Future;
+use std::io::Write;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::AsyncWrite;
+
+/// A chunk handed to libcodex whose callback has not fired yet
+///
+/// The chunk itself is held by the callback context until the node has
+/// consumed it, so it outlives the writer if need be.
+struct InFlight {
+    future: CallbackFuture,
+    len: usize,
+}
+
+/// An upload session that accepts data through `Write` or `AsyncWrite`
+///
+/// Writes are buffered until `chunk_size` bytes are available, then uploaded
+/// as one chunk. Call [`finish`](UploadWriter::finish) or
+/// [`finish_async`](UploadWriter::finish_async) to upload the remainder and get
+/// the CID. A writer dropped before finishing cancels its session.
+///
+/// `flush` waits for chunks already handed to the node but does not upload a
+/// partial chunk, so flushing never changes how the data is chunked.
+///
+/// Chunks are held back to respect [`UploadOptions::rate_limit`] and the
+/// node-wide [`CodexNode::upload_limiter`].
+pub struct UploadWriter {
+    node: CodexNode,
+    session_id: String,
+    chunk_size: usize,
+    buffer: Vec<u8>,
+    in_flight: Option<InFlight>,
+    throttle: Throttle,
+    acquiring: Option<Acquire>,
+    bytes_uploaded: usize,
+    chunks: usize,
+    on_progress: Option<Arc<dyn Fn(UploadProgress) + Send + Sync>>,
+    finished: bool,
+}
+
+impl UploadWriter {
+    /// Start a new upload session and return a writer for it
+    pub fn new(node: &CodexNode, options: UploadOptions) -> Result<Self> {
+        options.check_uncompressed("UploadWriter")?;
+        let session_id = upload_init_sync(node, &options, step_deadline())?;
+        Ok(Self::from_session(node, session_id, options))
+    }
+
+    /// Async version of [`UploadWriter::new`]
+    pub async fn new_async(node: &CodexNode, options: UploadOptions) -> Result<Self> {
+        options.check_uncompressed("UploadWriter")?;
+        let session_id = upload_init_async(node, &options).await?;
+        Ok(Self::from_session(node, session_id, options))
+    }
+
+    /// Wrap a session returned by [`upload_init`](crate::upload::upload_init)
+    ///
+    /// `options.chunk_size` should match the chunk size the session was
+    /// initialized with. The writer takes over the session: it is finalized by
+    /// `finish` or cancelled on drop.
+    pub fn from_session(node: &CodexNode, session_id: String, options: UploadOptions) -> Self {
+        let chunk_size = options.chunk_size.unwrap_or(1024 * 1024).max(1);
+        Self {
+            node: node.clone(),
+            session_id,
+            chunk_size,
+            buffer: Vec::with_capacity(chunk_size),
+            in_flight: None,
+            throttle: Throttle::upload(node, options.rate_limit.as_ref()),
+            acquiring: None,
+            bytes_uploaded: 0,
+            chunks: 0,
+            on_progress: options.on_progress,
+            finished: false,
+        }
+    }
+
+    /// The ID of the underlying upload session
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Bytes confirmed by the node so far
+    pub fn bytes_uploaded(&self) -> usize {
+        self.bytes_uploaded
+    }
+
+    /// Upload any buffered data, finalize the session and return the CID
+    pub fn finish(mut self) -> Result<String> {
+        self.wait_in_flight()?;
+
+        if !self.buffer.is_empty() {
+            let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+            let len = chunk.len();
+            self.throttle.acquire_blocking(len);
+            upload_chunk_sync(&self.node, &self.session_id, chunk, step_deadline())?;
+            self.chunk_done(len);
+        }
+
+        let cid = upload_finalize_sync(&self.node, &self.session_id, step_deadline())?;
+        self.finished = true;
+        Ok(cid)
+    }
+
+    /// Async version of [`UploadWriter::finish`]
+    pub async fn finish_async(mut self) -> Result<String> {
+        // The last chunk goes through `in_flight` like the others and is
+        // polled in place, so the writer still tracks it if this future is
+        // dropped before the node has consumed it
+        std::future::poll_fn(|cx| self.poll_in_flight(cx)).await?;
+
+        if !self.buffer.is_empty() {
+            std::future::poll_fn(|cx| self.poll_throttle(cx)).await;
+            let chunk = std::mem::take(&mut self.buffer);
+            self.send_in_background(chunk)?;
+            std::future::poll_fn(|cx| self.poll_in_flight(cx)).await?;
+        }
+
+        let cid = upload_finalize_async(&self.node, &self.session_id).await?;
+        self.finished = true;
+        Ok(cid)
+    }
+
+    fn chunk_done(&mut self, len: usize) {
+        self.bytes_uploaded += len;
+        self.chunks += 1;
+
+        if let Some(ref callback) = self.on_progress {
+            callback(UploadProgress::new_chunked(
+                self.bytes_uploaded,
+                None,
+                self.chunks,
+                self.chunks,
+            ));
+        }
+    }
+
+    /// Block until a chunk started by the async path has been stored
+    fn wait_in_flight(&mut self) -> Result<()> {
+        if let Some(in_flight) = self.in_flight.take() {
+            in_flight.future.wait()?;
+            self.chunk_done(in_flight.len);
+        }
+        Ok(())
+    }
+
+    fn poll_in_flight(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let Some(in_flight) = self.in_flight.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+
+        match Pin::new(&mut in_flight.future).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                let in_flight = self.in_flight.take().expect("in-flight chunk");
+                result?;
+                self.chunk_done(in_flight.len);
+                Poll::Ready(Ok(()))
+            }
+        }
+    }
+
+    /// Wait until the limiters admit the buffered data
+    fn poll_throttle(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let len = self.buffer.len();
+        let acquire = self.acquiring.get_or_insert_with(|| Acquire::new(len));
+        ready!(self.throttle.poll_acquire(cx, acquire));
+        self.acquiring = None;
+        Poll::Ready(())
+    }
+
+    /// Hand the buffered data to the node without waiting for it
+    fn start_chunk(&mut self) -> Result<()> {
+        let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.chunk_size));
+        self.send_in_background(chunk)
+    }
+
+    /// Hand `chunk` to the node and track it as the chunk in flight
+    fn send_in_background(&mut self, chunk: Vec<u8>) -> Result<()> {
+        let len = chunk.len();
+        let future = CallbackFuture::new();
+        start_upload_chunk(&self.node, &self.session_id, Bytes::from(chunk), &future)?;
+        self.in_flight = Some(InFlight { future, len });
+        Ok(())
+    }
+
+    fn ensure_open(&self) -> std::io::Result<()> {
+        if self.finished {
+            return Err(std::io::Error::other("Upload already finished"));
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn to_io_error(e: CodexError) -> std::io::Error {
+    match e {
+        CodexError::Io(e) => e,
+        other => std::io::Error::other(other),
+    }
+}
+
+impl Write for UploadWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.ensure_open()?;
+        self.wait_in_flight().map_err(to_io_error)?;
+
+        let n = buf.len().min(self.chunk_size - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+
+        if self.buffer.len() == self.chunk_size {
+            let chunk = std::mem::replace(&mut self.buffer, Vec::with_capacity(self.chunk_size));
+            let len = chunk.len();
+            self.throttle.acquire_blocking(len);
+            upload_chunk_sync(&self.node, &self.session_id, chunk.into(), step_deadline())
+                .map_err(to_io_error)?;
+            self.chunk_done(len);
+        }
+
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.wait_in_flight().map_err(to_io_error)
+    }
+}
+
+impl AsyncWrite for UploadWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        this.ensure_open()?;
+
+        if this.buffer.len() == this.chunk_size {
+            // The previous chunk is still being stored or the limiters hold
+            // this one back; wait before starting it
+            match this.poll_in_flight(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(result) => result.map_err(to_io_error)?,
+            }
+            ready!(this.poll_throttle(cx));
+            this.start_chunk().map_err(to_io_error)?;
+        }
+
+        let n = buf.len().min(this.chunk_size - this.buffer.len());
+        this.buffer.extend_from_slice(&buf[..n]);
+
+        // A chunk the limiters hold back is started by the next write
+        if this.buffer.len() == this.chunk_size
+            && this.in_flight.is_none()
+            && this.poll_throttle(cx).is_ready()
+        {
+            this.start_chunk().map_err(to_io_error)?;
+        }
+
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        match this.poll_in_flight(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => Poll::Ready(result.map_err(to_io_error)),
+        }
+    }
+
+    /// Waits for chunks in flight; call `finish_async` to finalize the upload
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl Drop for UploadWriter {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        // A chunk still in flight is held by its callback context, so the
+        // session can be cancelled right away
+        let future = CallbackFuture::new();
+        if let Err(e) = start_upload_cancel(&self.node, &self.session_id, &future) {
+            log::warn!(
+                "Failed to cancel unfinished upload session {}: {}",
+                self.session_id,
+                e
+            );
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! Async uploads integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Upload content from a tokio `AsyncRead` source
+//! - Check that it is stored like the same content uploaded from a file
+
+use codex_bindings::{
+    upload_async_reader, upload_reader, CodexConfig, CodexNode, LogLevel, UploadOptions,
+    UploadStrategy,
+};
+use std::io::Cursor;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_async_uploads() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Async Uploads Test");
+    println!("========================================");
+
+    let temp_dir = tempdir()?;
+    let content: Vec<u8> = (0..200 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8108);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Async Reader Upload ===");
+    let options = UploadOptions::new().chunk_size(64 * 1024);
+    let from_async =
+        upload_async_reader(&node, options.clone(), Cursor::new(content.clone())).await?;
+    assert_eq!(from_async.size, content.len());
+    assert_eq!(from_async.strategy, UploadStrategy::Chunked);
+    println!("  ✓ Uploaded {} bytes: {}", from_async.size, from_async.cid);
+
+    let from_sync = upload_reader(&node, options, Cursor::new(content)).await?;
+    assert_eq!(from_async.cid, from_sync.cid);
+    println!("  ✓ Same CID as a blocking reader upload");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nAsync uploads test completed successfully!");
+    Ok(())
+}
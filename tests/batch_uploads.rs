@@ -0,0 +1,122 @@
+//! Batch uploads integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Upload many files, readers and buffers with bounded concurrency
+//! - Follow progress across the whole batch
+//! - Collect per-item results, including failures
+
+use codex_bindings::{
+    BatchUploader, CodexConfig, CodexError, CodexNode, LogLevel, UploadOptions, UploadProgress,
+};
+use futures::StreamExt;
+use std::fs;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_batch_uploads() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Batch Uploads Test");
+    println!("========================================");
+
+    let temp_dir = tempdir()?;
+    let mut paths = Vec::new();
+    for i in 0..8 {
+        let path = temp_dir.path().join(format!("file_{}.txt", i));
+        fs::write(&path, format!("Batch file number {}\n", i).repeat(1000))?;
+        paths.push(path);
+    }
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8101);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Batch Upload ===");
+    let last_progress: Arc<Mutex<Option<UploadProgress>>> = Arc::new(Mutex::new(None));
+    let progress_sink = last_progress.clone();
+
+    let mut batch = BatchUploader::new(&node)
+        .options(UploadOptions::new().chunk_size(16 * 1024))
+        .concurrency(3)
+        .retries(1)
+        .retry_delay(Duration::from_millis(100))
+        .on_progress(move |progress| *progress_sink.lock().unwrap() = Some(progress));
+    for path in &paths {
+        batch = batch.add(path);
+    }
+    let batch = batch.add_bytes("notes.txt", "In-memory notes".repeat(100));
+
+    let total_bytes: usize = paths
+        .iter()
+        .map(|path| fs::metadata(path).map(|m| m.len() as usize))
+        .sum::<std::io::Result<usize>>()?
+        + "In-memory notes".len() * 100;
+
+    let results = batch.run().await?;
+    assert_eq!(results.len(), paths.len() + 1);
+    for (index, item) in results.iter().enumerate() {
+        assert_eq!(item.index, index);
+        assert!(
+            item.cid().is_some(),
+            "{} failed: {:?}",
+            item.name,
+            item.result
+        );
+        println!("  ✓ {} -> {}", item.name, item.cid().unwrap());
+    }
+
+    let progress = last_progress.lock().unwrap().clone().unwrap();
+    assert_eq!(progress.total_bytes, Some(total_bytes));
+    assert_eq!(progress.bytes_uploaded, total_bytes);
+    println!(
+        "  ✓ Batch progress reached {} bytes",
+        progress.bytes_uploaded
+    );
+
+    println!("\n=== Streamed Results With A Failing Item ===");
+    let mut stream = BatchUploader::new(&node)
+        .retry_delay(Duration::from_millis(100))
+        .add(&paths[0])
+        .add(temp_dir.path().join("missing.txt"))
+        .add_reader("stream.bin", Cursor::new(vec![7u8; 4096]))
+        .stream()?;
+
+    let mut completed = 0;
+    while let Some(item) = stream.next().await {
+        completed += 1;
+        match item.result {
+            Ok(ref result) => println!("  ✓ {} -> {}", item.name, result.cid),
+            Err(CodexError::InvalidParameter { ref message, .. }) => {
+                // Invalid input is not retried
+                assert!(item.name.ends_with("missing.txt"));
+                assert_eq!(item.attempts, 1);
+                println!("  ✓ {} rejected: {}", item.name, message);
+            }
+            Err(ref e) => panic!("unexpected failure for {}: {}", item.name, e),
+        }
+    }
+    assert_eq!(completed, 3);
+
+    println!("\n=== Invalid Batch ===");
+    assert!(BatchUploader::new(&node)
+        .concurrency(0)
+        .run()
+        .await
+        .is_err());
+    println!("  ✓ Zero concurrency rejected");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nBatch uploads test completed successfully!");
+    Ok(())
+}
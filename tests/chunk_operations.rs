@@ -1,8 +1,12 @@
+use bytes::Bytes;
+use codex_bindings::upload::upload_chunks;
 use codex_bindings::{
-    download_cancel, download_chunk, download_init, upload_cancel, upload_chunk, upload_finalize,
-    upload_init, CodexConfig, CodexNode, LogLevel, UploadOptions,
+    download_cancel, download_chunk, download_init, upload_async_reader, upload_bytes,
+    upload_cancel, upload_chunk, upload_finalize, upload_init, CodexConfig, CodexError, CodexNode,
+    DownloadSession, LogLevel, UploadOptions, UploadSession, UploadWriter,
 };
 use tempfile::tempdir;
+use tokio::io::AsyncWriteExt;
 
 #[tokio::test]
 async fn test_chunk_operations() -> Result<(), Box<dyn std::error::Error>> {
@@ -179,6 +183,115 @@ async fn test_chunk_operations() -> Result<(), Box<dyn std::error::Error>> {
     let small_cid = upload_finalize(&node, &small_session_id).await?;
     println!("✓ Small chunks upload finalized: {}", small_cid);
 
+    println!("\n=== Testing Bytes Chunks ===");
+    let buffer = Bytes::from([&test_data[..], &test_data2[..], &test_data3[..]].concat());
+    let bytes_options = UploadOptions::new().chunk_size(test_data.len());
+
+    let bytes_session_id = upload_init(&node, &bytes_options).await?;
+    let slices = (0..buffer.len())
+        .step_by(test_data.len())
+        .map(|start| buffer.slice(start..buffer.len().min(start + test_data.len())));
+    upload_chunks(&node, &bytes_session_id, slices).await?;
+    let sliced_cid = upload_finalize(&node, &bytes_session_id).await?;
+    println!("✓ Bytes slices upload finalized: {}", sliced_cid);
+
+    let bytes_result = upload_bytes(&node, buffer.clone(), bytes_options).await?;
+    assert_eq!(bytes_result.size, buffer.len());
+    assert_eq!(
+        bytes_result.chunks,
+        Some(buffer.len().div_ceil(test_data.len()))
+    );
+    assert_eq!(bytes_result.cid, sliced_cid);
+    println!(
+        "✓ upload_bytes matches the sliced upload: {}",
+        bytes_result.cid
+    );
+
+    println!("\n=== Testing Session Handles ===");
+    let mut session = UploadSession::start(&node, &UploadOptions::new().chunk_size(32)).await?;
+    session.upload_chunk(&test_data[..]).await?;
+    session.upload_chunk(&test_data2[..]).await?;
+    session.upload_chunk(&test_data3[..]).await?;
+    assert_eq!(session.chunks_uploaded(), 3);
+    assert_eq!(
+        session.bytes_uploaded(),
+        test_data.len() + test_data2.len() + test_data3.len()
+    );
+    let session_cid = session.finalize().await?;
+    println!("✓ Upload session finalized: {}", session_cid);
+
+    let cancelled = UploadSession::start(&node, &UploadOptions::new()).await?;
+    println!("✓ Upload session {} started", cancelled.id());
+    cancelled.cancel().await?;
+    println!("✓ Upload session cancelled");
+
+    let dropped = UploadSession::start(&node, &UploadOptions::new()).await?;
+    let dropped_id = dropped.id().to_string();
+    drop(dropped);
+    println!("✓ Dropped upload session {} cancelled", dropped_id);
+
+    let mut download =
+        DownloadSession::start(&node, &codex_bindings::DownloadOptions::new(&session_cid)).await?;
+    let first_chunk = download.download_chunk().await?;
+    assert_eq!(download.bytes_downloaded(), first_chunk.len());
+    println!(
+        "✓ Download session for {} received {} bytes",
+        download.cid(),
+        first_chunk.len()
+    );
+    download.cancel().await?;
+    println!("✓ Download session cancelled");
+
+    println!("\n=== Testing Upload Writer ===");
+    let writer_options = UploadOptions::new().chunk_size(16);
+
+    let mut writer = UploadWriter::new_async(&node, writer_options.clone()).await?;
+    for part in [&test_data[..], &test_data2[..], &test_data3[..]] {
+        writer.write_all(part).await?;
+    }
+    let async_writer_cid = writer.finish_async().await?;
+    println!("✓ Async writer upload finalized: {}", async_writer_cid);
+
+    let blocking_writer_cid = tokio::task::spawn_blocking({
+        let node = node.clone();
+        let data = [&test_data[..], &test_data2[..], &test_data3[..]].concat();
+        move || -> codex_bindings::Result<String> {
+            let mut writer = UploadWriter::new(&node, writer_options)?;
+            std::io::Write::write_all(&mut writer, &data)?;
+            writer.finish()
+        }
+    })
+    .await??;
+    println!(
+        "✓ Blocking writer upload finalized: {}",
+        blocking_writer_cid
+    );
+    assert_eq!(async_writer_cid, blocking_writer_cid);
+
+    let dropped_writer = UploadWriter::new_async(&node, UploadOptions::new()).await?;
+    let dropped_session = dropped_writer.session_id().to_string();
+    drop(dropped_writer);
+    println!("✓ Unfinished writer cancelled session {}", dropped_session);
+
+    println!("\n=== Testing Timeouts ===");
+    // The source sends two chunks and then stalls without closing
+    let (source, mut feeder) = tokio::io::duplex(256);
+    feeder.write_all(test_data).await?;
+    feeder.write_all(test_data).await?;
+    let stalled_options = UploadOptions::new().chunk_size(test_data.len()).timeout(1);
+    let started = std::time::Instant::now();
+    match upload_async_reader(&node, stalled_options, source).await {
+        Err(CodexError::Timeout {
+            bytes_transferred, ..
+        }) => {
+            assert!(bytes_transferred >= Some(test_data.len()));
+            println!("✓ Stalled upload timed out after {:?}", started.elapsed());
+        }
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+    assert!(started.elapsed() < std::time::Duration::from_secs(10));
+    drop(feeder);
+
     println!("\n=== Final Storage Information ===");
     let space_info = codex_bindings::space(&node).await?;
     println!("Storage usage:");
@@ -0,0 +1,96 @@
+//! Compressed transfers integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Compress a file while uploading it
+//! - Find the codec recorded in the manifest
+//! - Decompress it while downloading it
+
+use codex_bindings::{
+    download_stream, fetch, upload_file, CodexConfig, CodexNode, Compression,
+    DownloadStreamOptions, LogLevel, UploadOptions,
+};
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_compressed_transfers() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Compressed Transfers Test");
+    println!("===============================================");
+
+    let temp_dir = tempdir()?;
+    let source = temp_dir.path().join("events.json");
+    let content: String = (0..20_000)
+        .map(|i| format!("{{\"event\":{},\"level\":\"info\"}}\n", i % 100))
+        .collect();
+    fs::write(&source, &content)?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8100);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Compressed Upload ===");
+    let uploaded = upload_file(
+        &node,
+        UploadOptions::new()
+            .filepath(&source)
+            .compression(Compression::zstd()),
+    )
+    .await?;
+    assert!(uploaded.size < content.len() / 5);
+    assert_eq!(uploaded.compression, Compression::zstd());
+    println!(
+        "  ✓ Uploaded {} bytes as {} compressed bytes: {}",
+        content.len(),
+        uploaded.size,
+        uploaded.cid
+    );
+
+    println!("\n=== Codec In The Manifest ===");
+    let manifest = fetch(&node, &uploaded.cid).await?;
+    assert_eq!(manifest.filename, "events.json.zst");
+    println!(
+        "  ✓ Recorded as {} ({})",
+        manifest.filename, manifest.mimetype
+    );
+
+    println!("\n=== Decompressed Download ===");
+    let restored = temp_dir.path().join("restored.json");
+    let downloaded = download_stream(
+        &node,
+        &uploaded.cid,
+        DownloadStreamOptions::new(&uploaded.cid)
+            .filepath(&restored)
+            .decompress(true),
+    )
+    .await?;
+    assert_eq!(downloaded.size, content.len());
+    assert_eq!(downloaded.compression, Compression::zstd());
+    assert_eq!(fs::read_to_string(&restored)?, content);
+    println!("  ✓ Decompressed {} bytes", downloaded.size);
+
+    println!("\n=== Download As Stored ===");
+    let raw = temp_dir.path().join("raw.json.zst");
+    let stored = download_stream(
+        &node,
+        &uploaded.cid,
+        DownloadStreamOptions::new(&uploaded.cid).filepath(&raw),
+    )
+    .await?;
+    assert_eq!(stored.compression, Compression::None);
+    assert_eq!(fs::read(&raw)?.len(), uploaded.size);
+    println!("  ✓ Kept {} compressed bytes", stored.size);
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nCompressed transfers test completed successfully!");
+    Ok(())
+}
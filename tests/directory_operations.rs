@@ -0,0 +1,85 @@
+//! Directory operations integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to publish and fetch a whole directory tree:
+//! - Upload every file under a directory together with its manifest
+//! - Recreate the tree from the manifest CID
+//! - Restrict downloads with include and exclude globs
+
+use codex_bindings::{
+    download_dir, upload_dir, CodexConfig, CodexNode, DirectoryOptions, LogLevel,
+};
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_directory_operations() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Directory Operations Test");
+    println!("===============================================");
+
+    let temp_dir = tempdir()?;
+
+    let source = temp_dir.path().join("dataset");
+    fs::create_dir_all(source.join("images"))?;
+    fs::create_dir_all(source.join("tables/tmp"))?;
+    fs::write(source.join("README.md"), "# Dataset\n")?;
+    fs::write(source.join("images/a.png"), vec![7u8; 4096])?;
+    fs::write(source.join("tables/b.csv"), "x,y\n1,2\n")?;
+    fs::write(source.join("tables/tmp/scratch.csv"), "ignore me")?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8098);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Uploading Directory ===");
+    let options = DirectoryOptions::new().exclude("**/tmp/**").concurrency(2);
+    let uploaded = upload_dir(&node, &source, options).await?;
+    println!("  ✓ Directory manifest CID: {}", uploaded.cid);
+
+    let paths: Vec<&str> = uploaded
+        .manifest
+        .entries
+        .iter()
+        .map(|entry| entry.path.as_str())
+        .collect();
+    assert_eq!(paths, vec!["README.md", "images/a.png", "tables/b.csv"]);
+    assert_eq!(uploaded.manifest.total_size(), 10 + 4096 + 8);
+
+    println!("\n=== Downloading Directory ===");
+    let dest = temp_dir.path().join("restored");
+    let downloaded = download_dir(&node, &uploaded.cid, &dest, DirectoryOptions::new()).await?;
+    assert_eq!(downloaded.entries.len(), 3);
+    assert_eq!(downloaded.total_bytes, uploaded.manifest.total_size());
+    for path in paths {
+        assert_eq!(fs::read(source.join(path))?, fs::read(dest.join(path))?);
+    }
+    assert!(!dest.join("tables/tmp").exists());
+    println!("  ✓ Restored {} files", downloaded.entries.len());
+
+    println!("\n=== Filtered Download ===");
+    let csv_only = temp_dir.path().join("csv_only");
+    let filtered = download_dir(
+        &node,
+        &uploaded.cid,
+        &csv_only,
+        DirectoryOptions::new().include("*.csv"),
+    )
+    .await?;
+    assert_eq!(filtered.entries.len(), 1);
+    assert!(csv_only.join("tables/b.csv").exists());
+    assert!(!csv_only.join("README.md").exists());
+    println!("  ✓ Include glob restricted the download");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nDirectory operations test completed successfully!");
+    Ok(())
+}
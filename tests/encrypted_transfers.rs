@@ -0,0 +1,105 @@
+//! Encrypted transfers integration test for the Codex Rust bindings
+//!
+//! Runs with `--features encryption` and demonstrates how to:
+//! - Encrypt a file while uploading it
+//! - Verify and decrypt it while downloading it
+//! - Reject downloads with the wrong key
+
+#![cfg(feature = "encryption")]
+
+use codex_bindings::{
+    download_stream, CodexConfig, CodexError, CodexNode, DownloadStreamOptions, EncryptedDownload,
+    EncryptedUpload, EncryptionKey, LogLevel, UploadOptions,
+};
+use std::fs;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_encrypted_transfers() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Encrypted Transfers Test");
+    println!("==============================================");
+
+    let temp_dir = tempdir()?;
+    let source = temp_dir.path().join("report.pdf");
+    let content: Vec<u8> = (0..300_000).map(|i| (i % 251) as u8).collect();
+    fs::write(&source, &content)?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8099);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    let key = EncryptionKey::generate("team-2026");
+
+    println!("\n=== Encrypted Upload ===");
+    let upload = EncryptedUpload::new(key.clone()).segment_size(16 * 1024);
+    let uploaded = upload
+        .upload_file(
+            &node,
+            UploadOptions::new().filepath(&source).chunk_size(64 * 1024),
+        )
+        .await?;
+    assert!(uploaded.size > content.len());
+    println!(
+        "  ✓ Uploaded {} encrypted bytes: {}",
+        uploaded.size, uploaded.cid
+    );
+
+    println!("\n=== Stored Content Is Encrypted ===");
+    let raw = temp_dir.path().join("raw.bin");
+    download_stream(
+        &node,
+        &uploaded.cid,
+        DownloadStreamOptions::new(&uploaded.cid).filepath(&raw),
+    )
+    .await?;
+    let raw = fs::read(&raw)?;
+    assert!(raw.starts_with(b"CDXENC"));
+    assert!(!raw.windows(64).any(|window| window == &content[1000..1064]));
+    println!("  ✓ Plaintext does not appear in the stored content");
+
+    println!("\n=== Encrypted Download ===");
+    let restored = temp_dir.path().join("restored.pdf");
+    let download = EncryptedDownload::new(EncryptionKey::generate("old-key")).key(key.clone());
+    let downloaded = download
+        .download_stream(
+            &node,
+            &uploaded.cid,
+            DownloadStreamOptions::new(&uploaded.cid).filepath(&restored),
+        )
+        .await?;
+    assert_eq!(downloaded.size, content.len());
+    assert_eq!(fs::read(&restored)?, content);
+    println!("  ✓ Decrypted {} bytes", downloaded.size);
+
+    println!("\n=== Wrong Key ===");
+    let rejected = temp_dir.path().join("rejected.pdf");
+    let wrong = EncryptedDownload::new(EncryptionKey::new("team-2026", [0u8; 32]));
+    match wrong
+        .download_stream(
+            &node,
+            &uploaded.cid,
+            DownloadStreamOptions::new(&uploaded.cid).filepath(&rejected),
+        )
+        .await
+    {
+        Err(CodexError::EncryptionError { message }) => {
+            println!("  ✓ Rejected: {}", message);
+        }
+        other => panic!("expected an encryption error, got {:?}", other),
+    }
+    assert!(!rejected.exists());
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nEncrypted transfers test completed successfully!");
+    Ok(())
+}
@@ -1,16 +1,42 @@
 //! Integration tests for the Codex Rust bindings
 //!
 //! Available tests:
+//! - async_uploads: Uploads from a tokio AsyncRead source
 //! - basic_usage: Basic upload/download functionality
+//! - batch_uploads: Batch uploads with bounded concurrency, retry and combined progress
 //! - chunk_operations: Chunk-based upload and download
+//! - compressed_transfers: Compressed upload and decompressing download
 //! - debug_operations: Debug operations and logging
+//! - directory_operations: Directory upload and download with manifests
+//! - encrypted_transfers: Encrypted upload and download (`encryption` feature)
+//! - offline_cids: CIDs computed without a node, matching the uploaded ones
 //! - p2p_networking: P2P networking operations
+//! - reconfigure: Runtime reconfiguration of a running node
+//! - resumed_uploads: Uploads continued from their journal or restarted when the session is gone
 //! - storage_management: Storage management operations
+//! - streamed_downloads: Downloads read through AsyncRead or a Stream with backpressure
+//! - throttled_transfers: Rate limited uploads and downloads
 //! - two_node_network: Two-node network setup and data transfer
+//! - upload_sessions: Tracking of open upload sessions and cancellation of idle ones
+//! - upload_strategies: Native streaming and chunked upload strategies
+//! - upload_verification: Verification of uploads against the stored manifest
 
+pub mod async_uploads;
 pub mod basic_usage;
+pub mod batch_uploads;
 pub mod chunk_operations;
+pub mod compressed_transfers;
 pub mod debug_operations;
+pub mod directory_operations;
+pub mod encrypted_transfers;
+pub mod offline_cids;
 pub mod p2p_networking;
+pub mod reconfigure;
+pub mod resumed_uploads;
 pub mod storage_management;
+pub mod streamed_downloads;
+pub mod throttled_transfers;
 pub mod two_node_network;
+pub mod upload_sessions;
+pub mod upload_strategies;
+pub mod upload_verification;
@@ -0,0 +1,70 @@
+//! Offline CIDs integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Compute the CID of content without a node
+//! - Include the filename and mimetype an upload records
+
+use codex_bindings::{
+    compute_cid, compute_manifest, upload_reader, CidOptions, CodexConfig, CodexNode, LogLevel,
+    UploadOptions,
+};
+use std::io::Cursor;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_offline_cids() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Offline CIDs Test");
+    println!("=======================================");
+
+    let temp_dir = tempdir()?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8109);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Unnamed Content ===");
+    // The content contains NUL bytes and no known magic number, so no
+    // mimetype is sniffed and nothing but the content goes into the manifest
+    for (len, block_size) in [
+        (100, 1024),
+        (4096, 1024),
+        (5000, 1024),
+        (200_000, 64 * 1024),
+    ] {
+        let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let expected = compute_cid(Cursor::new(&data), block_size)?;
+        let options = UploadOptions::new().chunk_size(block_size);
+        let uploaded = upload_reader(&node, options, Cursor::new(data)).await?;
+        assert_eq!(
+            uploaded.cid, expected,
+            "{} bytes in {} byte blocks",
+            len, block_size
+        );
+    }
+    println!("  ✓ Offline CIDs match");
+
+    println!("\n=== Named Content ===");
+    let data = b"name,count\nalpha,1\n".to_vec();
+    let options = UploadOptions::new()
+        .filename("counts")
+        .mimetype("text/csv")
+        .chunk_size(1024);
+    let expected = compute_manifest(Cursor::new(&data), &CidOptions::try_from(&options)?)?;
+    let uploaded = upload_reader(&node, options, Cursor::new(data)).await?;
+    assert_eq!(uploaded.cid, expected.cid);
+    println!("  ✓ Offline CID matches for counts.csv");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nOffline CIDs test completed successfully!");
+    Ok(())
+}
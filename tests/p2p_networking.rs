@@ -5,7 +5,7 @@
 //! - Get peer information
 //! - Debug peer connections
 
-use codex_bindings::{CodexConfig, CodexNode, LogLevel};
+use codex_bindings::{CodexConfig, CodexNode, LogLevel, Multiaddr};
 use tempfile::tempdir;
 
 #[tokio::test]
@@ -56,10 +56,10 @@ async fn test_p2p_networking() -> Result<(), Box<dyn std::error::Error>> {
     // Test connecting to a peer (this will likely fail since it's a test peer)
     println!("\n=== Testing Peer Connection ===");
     let test_peer_id = "12D3KooWExamplePeer123456789";
-    let test_addresses = vec![
-        "/ip4/192.168.1.100/tcp/8080".to_string(),
-        "/ip4/192.168.1.100/udp/8080/quic".to_string(),
-        "/ip6/::1/tcp/8080".to_string(),
+    let test_addresses: Vec<Multiaddr> = vec![
+        "/ip4/192.168.1.100/tcp/8080".parse()?,
+        "/ip4/192.168.1.100/udp/8080/quic".parse()?,
+        "/ip6/::1/tcp/8080".parse()?,
     ];
 
     println!("Attempting to connect to peer: {}", test_peer_id);
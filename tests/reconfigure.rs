@@ -0,0 +1,89 @@
+//! Reconfiguration integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to change the configuration of a running node:
+//! - Apply live-changeable settings without a restart
+//! - Report settings that require a restart
+//! - Restart the node transparently on the same data directory
+
+use codex_bindings::{CodexConfig, CodexNode, LogLevel, RestartPolicy};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_reconfigure() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Reconfigure Test");
+    println!("======================================");
+
+    let temp_dir = tempdir()?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Info)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8105);
+
+    let mut node = CodexNode::new(config.clone())?;
+    node.start()?;
+    let peer_id = node.peer_id()?;
+    println!("Node started with peer ID: {}", peer_id);
+
+    // Unchanged configuration is a no-op
+    let report = node
+        .reconfigure_async(config.clone(), RestartPolicy::Never)
+        .await?;
+    assert!(report.is_empty());
+
+    // Log level is applied live
+    println!("\n=== Live Settings ===");
+    let report = node
+        .reconfigure_async(
+            config.clone().log_level(LogLevel::Debug),
+            RestartPolicy::Never,
+        )
+        .await?;
+    assert_eq!(report.applied.len(), 1);
+    assert!(report.restart_required.is_empty());
+    assert_eq!(node.config().log_level, Some(LogLevel::Debug));
+    println!("  ✓ Log level changed without restart");
+
+    // Other settings are reported, not applied, unless a restart is allowed
+    println!("\n=== Restart-Required Settings ===");
+    let new_config = node.config().max_peers(25).try_storage_quota("200MiB")?;
+    let report = node
+        .reconfigure_async(new_config.clone(), RestartPolicy::Never)
+        .await?;
+    assert!(!report.restarted);
+    assert_eq!(report.pending().len(), 2);
+    assert_eq!(node.config().max_peers, config.max_peers);
+    println!("  ✓ Pending changes: {:?}", report.pending());
+
+    let report = node
+        .reconfigure_async(new_config, RestartPolicy::IfRequired)
+        .await?;
+    assert!(report.restarted);
+    assert!(report.pending().is_empty());
+    assert!(node.is_started());
+    assert_eq!(node.config().max_peers, Some(25));
+    assert_eq!(node.peer_id()?, peer_id);
+    println!("  ✓ Node restarted with the same peer ID");
+
+    // The data directory can't be moved, not even by a restart
+    let moved = node.config().data_dir(temp_dir.path().join("elsewhere"));
+    assert!(node
+        .reconfigure_async(moved.clone(), RestartPolicy::Never)
+        .await
+        .is_err());
+    assert!(node
+        .reconfigure_async(moved, RestartPolicy::IfRequired)
+        .await
+        .is_err());
+    println!("  ✓ Data directory change rejected");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nReconfigure test completed successfully!");
+    Ok(())
+}
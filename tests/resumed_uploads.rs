@@ -0,0 +1,178 @@
+//! Resumed uploads integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Continue an interrupted upload from the offset recorded in its journal
+//! - Restart an upload from the beginning when its session is gone
+//! - Keep the finalized segments of a segmented upload across a crash
+
+use codex_bindings::{
+    download_segmented, download_stream, resume_upload, upload_chunk, upload_init, CodexConfig,
+    CodexNode, DownloadStreamOptions, LogLevel, UploadJournal, UploadOptions,
+};
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use tempfile::tempdir;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Write a journal recording `chunks` confirmed chunks of `session_id`
+fn write_journal(
+    path: &Path,
+    session_id: &str,
+    chunks: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut journal = UploadJournal::create(path, session_id, CHUNK_SIZE)?;
+    journal.offset = (chunks * CHUNK_SIZE) as u64;
+    journal.chunks = chunks;
+    fs::write(path, serde_json::to_vec(&journal)?)?;
+    Ok(())
+}
+
+/// Source that fails once `limit` bytes have been read, like a device losing power
+struct FailingReader {
+    inner: Cursor<Vec<u8>>,
+    limit: u64,
+}
+
+impl Read for FailingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.limit.saturating_sub(self.inner.position());
+        if left == 0 {
+            return Err(io::Error::other("power lost"));
+        }
+        let len = buf.len().min(left as usize);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl Seek for FailingReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+async fn download(
+    node: &CodexNode,
+    cid: &str,
+    path: &Path,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    download_stream(node, cid, DownloadStreamOptions::new(cid).filepath(path)).await?;
+    Ok(fs::read(path)?)
+}
+
+#[tokio::test]
+async fn test_resumed_uploads() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Resumed Uploads Test");
+    println!("==========================================");
+
+    let temp_dir = tempdir()?;
+    let content: Vec<u8> = (0..3 * CHUNK_SIZE).map(|i| (i % 251) as u8).collect();
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8106);
+
+    let mut node = CodexNode::new(config.clone())?;
+    node.start()?;
+
+    println!("\n=== Continuing From The Journal ===");
+    let journal_path = temp_dir.path().join("continued.codex-upload");
+    let options = UploadOptions::new()
+        .filename("continued.bin")
+        .chunk_size(CHUNK_SIZE);
+
+    // The first chunk reaches the node, then the upload is interrupted
+    let session_id = upload_init(&node, &options).await?;
+    upload_chunk(&node, &session_id, content[..CHUNK_SIZE].to_vec()).await?;
+    write_journal(&journal_path, &session_id, 1)?;
+
+    // Only a resume that skips the journaled chunk yields the original content
+    let mut source = content.clone();
+    source[..CHUNK_SIZE].fill(0);
+    let result = resume_upload(
+        &node,
+        options.clone().journal(&journal_path),
+        Cursor::new(source),
+    )
+    .await?;
+    assert_eq!(result.size, content.len());
+    assert!(!journal_path.exists());
+
+    let restored = download(&node, &result.cid, &temp_dir.path().join("continued.bin")).await?;
+    assert_eq!(restored, content);
+    println!(
+        "  ✓ Continued session {} from offset {}",
+        session_id, CHUNK_SIZE
+    );
+
+    println!("\n=== Restarting A Lost Session ===");
+    // As after a crash of the process hosting the node: the session is gone
+    let journal_path = temp_dir.path().join("restarted.codex-upload");
+    write_journal(&journal_path, "session-from-before-the-crash", 2)?;
+
+    let result = resume_upload(
+        &node,
+        options.clone().journal(&journal_path),
+        Cursor::new(content.clone()),
+    )
+    .await?;
+    assert_eq!(result.size, content.len());
+    assert!(!journal_path.exists());
+
+    let restored = download(&node, &result.cid, &temp_dir.path().join("restarted.bin")).await?;
+    assert_eq!(restored, content);
+    assert!(node.active_uploads().is_empty());
+    println!("  ✓ Restarted from zero in a new session");
+
+    println!("\n=== Resuming After A Crash ===");
+    let journal_path = temp_dir.path().join("segmented.codex-upload");
+    let options = UploadOptions::new()
+        .filename("segmented.bin")
+        .chunk_size(CHUNK_SIZE)
+        .segment_size(2 * CHUNK_SIZE)
+        .read_ahead(0)
+        .journal(&journal_path);
+    let content: Vec<u8> = (0..5 * CHUNK_SIZE).map(|i| (i % 241) as u8).collect();
+
+    // Power is lost after the first segment and one chunk of the second
+    let failing = FailingReader {
+        inner: Cursor::new(content.clone()),
+        limit: (3 * CHUNK_SIZE) as u64,
+    };
+    assert!(resume_upload(&node, options.clone(), failing)
+        .await
+        .is_err());
+    let journal = UploadJournal::load(&journal_path)?.expect("the journal is kept");
+    assert_eq!(journal.segments.len(), 1);
+    assert_eq!(journal.offset, (3 * CHUNK_SIZE) as u64);
+
+    // The node goes down with the process, taking the open session with it
+    node.stop()?;
+    node.destroy()?;
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    // Only a resume that keeps the stored segment yields the original content
+    let mut source = content.clone();
+    source[..2 * CHUNK_SIZE].fill(0);
+    let result = resume_upload(&node, options, Cursor::new(source)).await?;
+    assert_eq!(result.size, content.len());
+    assert!(!journal_path.exists());
+
+    let restored_path = temp_dir.path().join("segmented.bin");
+    download_segmented(&node, &result.cid, &restored_path).await?;
+    assert_eq!(fs::read(&restored_path)?, content);
+    println!("  ✓ Continued after the segment finalized before the crash");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nResumed uploads test completed successfully!");
+    Ok(())
+}
@@ -187,6 +187,36 @@ async fn test_storage_management() -> Result<(), Box<dyn std::error::Error>> {
         );
     }
 
+    // Content uploaded from memory keeps its filename and mimetype
+    println!("\n=== Named Uploads From Memory ===");
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01".to_vec();
+    let named_options = codex_bindings::UploadOptions::new().filename("pixel");
+    let named =
+        codex_bindings::upload_reader(&node, named_options, std::io::Cursor::new(png)).await?;
+    let named_manifest = codex_bindings::fetch(&node, &named.cid).await?;
+    println!(
+        "Recorded filename: {}, mimetype: {}",
+        named_manifest.filename, named_manifest.mimetype
+    );
+    assert_eq!(named_manifest.filename, "pixel.png");
+    assert_eq!(named_manifest.mimetype, "image/png");
+
+    let explicit_options = codex_bindings::UploadOptions::new()
+        .filename("report")
+        .mimetype("application/pdf");
+    let explicit = codex_bindings::upload_reader(
+        &node,
+        explicit_options,
+        std::io::Cursor::new(b"%PDF-1.7\n".to_vec()),
+    )
+    .await?;
+    let explicit_manifest = codex_bindings::fetch(&node, &explicit.cid).await?;
+    assert_eq!(explicit_manifest.filename, "report.pdf");
+    assert_eq!(explicit_manifest.mimetype, "application/pdf");
+
+    codex_bindings::delete(&node, &named.cid).await?;
+    codex_bindings::delete(&node, &explicit.cid).await?;
+
     // Get final storage information
     println!("\n=== Final Storage Information ===");
     let space_info = codex_bindings::space(&node).await?;
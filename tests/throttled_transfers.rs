@@ -0,0 +1,89 @@
+//! Throttled transfers integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Limit the rate of a single upload
+//! - Limit every download of a node and lift the limit at runtime
+//! - Share one limiter between operations
+
+use codex_bindings::{
+    download_stream, upload_reader, CodexConfig, CodexNode, DownloadStreamOptions, LogLevel,
+    RateLimiter, UploadOptions,
+};
+use std::fs;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_throttled_transfers() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Throttled Transfers Test");
+    println!("==============================================");
+
+    let temp_dir = tempdir()?;
+    let content: Vec<u8> = (0..300 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8102);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Rate Limited Upload ===");
+    // One second of burst, then 100 KB/s for the remaining 200 KB
+    let limiter = RateLimiter::new(100 * 1024);
+    let start = Instant::now();
+    let uploaded = upload_reader(
+        &node,
+        UploadOptions::new()
+            .chunk_size(32 * 1024)
+            .rate_limit(limiter.clone()),
+        Cursor::new(content.clone()),
+    )
+    .await?;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(1500), "took {:?}", elapsed);
+    println!("  ✓ Uploaded {} bytes in {:?}", uploaded.size, elapsed);
+
+    println!("\n=== Node-Wide Download Limit ===");
+    node.download_limiter().set_rate(100 * 1024);
+    let restored = temp_dir.path().join("restored.bin");
+    let start = Instant::now();
+    let downloaded = download_stream(
+        &node,
+        &uploaded.cid,
+        DownloadStreamOptions::new(&uploaded.cid)
+            .filepath(&restored)
+            .chunk_size(32 * 1024),
+    )
+    .await?;
+    let elapsed = start.elapsed();
+    assert_eq!(downloaded.size, content.len());
+    assert_eq!(fs::read(&restored)?, content);
+    assert!(elapsed >= Duration::from_millis(1500), "took {:?}", elapsed);
+    println!("  ✓ Downloaded {} bytes in {:?}", downloaded.size, elapsed);
+
+    println!("\n=== Lifting The Limit ===");
+    node.download_limiter().set_rate(0);
+    assert!(!node.download_limiter().is_limited());
+    let unlimited = temp_dir.path().join("unlimited.bin");
+    let downloaded = download_stream(
+        &node,
+        &uploaded.cid,
+        DownloadStreamOptions::new(&uploaded.cid).filepath(&unlimited),
+    )
+    .await?;
+    assert_eq!(fs::read(&unlimited)?, content);
+    println!("  ✓ Downloaded {} bytes without a limit", downloaded.size);
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nThrottled transfers test completed successfully!");
+    Ok(())
+}
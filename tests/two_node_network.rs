@@ -50,8 +50,8 @@ async fn test_two_node_network() -> Result<(), Box<dyn std::error::Error>> {
         .max_peers(50)
         .discovery_port(8092)
         .listen_addrs(vec![
-            "/ip4/127.0.0.1/tcp/0".to_string(),
-            "/ip4/0.0.0.0/tcp/0".to_string(),
+            "/ip4/127.0.0.1/tcp/0".parse()?,
+            "/ip4/0.0.0.0/tcp/0".parse()?,
         ]);
 
     let mut node1 = CodexNode::new(node1_config)?;
@@ -75,8 +75,8 @@ async fn test_two_node_network() -> Result<(), Box<dyn std::error::Error>> {
 
     // Manually set listen addresses since builder method doesn't exist
     node2_config.listen_addrs = vec![
-        "/ip4/127.0.0.1/tcp/0".to_string(), // Let the OS choose a port
-        "/ip4/0.0.0.0/tcp/0".to_string(),
+        "/ip4/127.0.0.1/tcp/0".parse()?, // Let the OS choose a port
+        "/ip4/0.0.0.0/tcp/0".parse()?,
     ];
 
     let mut node2 = CodexNode::new(node2_config)?;
@@ -0,0 +1,82 @@
+//! Upload sessions integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - List the upload sessions open on a node
+//! - Cancel sessions whose ID was lost
+//! - Run a background reaper for idle sessions
+
+use codex_bindings::{
+    upload_chunk, upload_finalize, upload_init, CodexConfig, CodexNode, LogLevel, UploadOptions,
+};
+use std::time::Duration;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_upload_sessions() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Upload Sessions Test");
+    println!("==========================================");
+
+    let temp_dir = tempdir()?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8103);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Active Uploads ===");
+    let options = UploadOptions::new().filename("tracked.txt");
+    let session_id = upload_init(&node, &options).await?;
+    upload_chunk(&node, &session_id, b"Hello, ".to_vec()).await?;
+    upload_chunk(&node, &session_id, b"sessions!".to_vec()).await?;
+
+    let active = node.active_uploads();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].session_id, session_id);
+    assert_eq!(active[0].bytes_sent, 16);
+    assert_eq!(active[0].chunks_sent, 2);
+    assert!(active[0].last_activity >= active[0].started_at);
+    println!(
+        "  ✓ Session {} sent {} bytes",
+        session_id, active[0].bytes_sent
+    );
+
+    let cid = upload_finalize(&node, &session_id).await?;
+    assert!(node.active_uploads().is_empty());
+    println!("  ✓ Finalized session is no longer listed: {}", cid);
+
+    println!("\n=== Cancelling Idle Sessions ===");
+    // The ID is dropped on purpose, as if the uploading task had panicked
+    let _ = upload_init(&node, &options).await?;
+    let busy = upload_init(&node, &options).await?;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    upload_chunk(&node, &busy, b"still here".to_vec()).await?;
+
+    let cancelled = node.cancel_idle_uploads(Duration::from_millis(200)).await;
+    assert_eq!(cancelled.len(), 1);
+    assert_ne!(cancelled[0], busy);
+    let active = node.active_uploads();
+    assert_eq!(active.len(), 1);
+    assert_eq!(active[0].session_id, busy);
+    println!("  ✓ Cancelled {} idle session", cancelled.len());
+
+    println!("\n=== Background Reaper ===");
+    let reaper = node.start_upload_reaper(Duration::from_millis(200), Duration::from_millis(50));
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(node.active_uploads().is_empty());
+    assert!(!reaper.is_finished());
+    reaper.stop();
+    println!("  ✓ Reaper cancelled the remaining session");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nUpload sessions test completed successfully!");
+    Ok(())
+}
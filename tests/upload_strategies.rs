@@ -0,0 +1,94 @@
+//! Upload strategies integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Let `upload_file` pick the native streaming strategy
+//! - Follow the progress the node reports for a native upload
+//! - Force chunked uploads and reject strategies a source cannot use
+
+use codex_bindings::{
+    upload_file, upload_reader, CodexConfig, CodexNode, LogLevel, UploadOptions, UploadStrategy,
+};
+use std::fs;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_upload_strategies() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Upload Strategies Test");
+    println!("============================================");
+
+    let temp_dir = tempdir()?;
+    let file_path = temp_dir.path().join("example.txt");
+    fs::write(
+        &file_path,
+        b"Hello, Codex! This is a test file for the Rust bindings.",
+    )?;
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8107);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Native File Upload ===");
+    let last_progress = Arc::new(Mutex::new(None));
+    let progress_sink = last_progress.clone();
+    let options = UploadOptions::new()
+        .filepath(&file_path)
+        .on_progress(move |progress| {
+            *progress_sink.lock().unwrap() = Some(progress);
+        });
+    let uploaded = upload_file(&node, options).await?;
+
+    // The node reads the file itself and reports progress as it goes
+    assert_eq!(uploaded.strategy, UploadStrategy::Stream);
+    let progress = last_progress
+        .lock()
+        .unwrap()
+        .take()
+        .expect("file uploads report progress");
+    assert_eq!(progress.bytes_uploaded, uploaded.size);
+    assert_eq!(progress.total_bytes, Some(uploaded.size));
+    assert_eq!(progress.current_chunk, progress.total_chunks);
+    println!(
+        "  ✓ Streamed {} bytes in {:?} chunks",
+        uploaded.size, progress.total_chunks
+    );
+
+    println!("\n=== Forced Strategies ===");
+    let forced = UploadOptions::new()
+        .filepath(&file_path)
+        .strategy(UploadStrategy::Chunked);
+    let chunked = upload_file(&node, forced).await?;
+    assert_eq!(chunked.strategy, UploadStrategy::Chunked);
+    assert_eq!(chunked.size, uploaded.size);
+    println!("  ✓ Chunked upload of the same file");
+
+    let stream_reader = UploadOptions::new().strategy(UploadStrategy::Stream);
+    assert!(upload_reader(&node, stream_reader, Cursor::new(vec![1u8]))
+        .await
+        .is_err());
+    println!("  ✓ Readers cannot be streamed natively");
+
+    // A native upload could not be resumed from a journal
+    let stream_journaled = UploadOptions::new()
+        .filepath(&file_path)
+        .journal(temp_dir.path().join("example.codex-upload"))
+        .strategy(UploadStrategy::Stream);
+    assert!(upload_file(&node, stream_journaled).await.is_err());
+    assert!(!temp_dir.path().join("example.codex-upload").exists());
+    println!("  ✓ Journaled files cannot be streamed natively");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nUpload strategies test completed successfully!");
+    Ok(())
+}
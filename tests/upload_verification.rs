@@ -0,0 +1,54 @@
+//! Upload verification integration test for the Codex Rust bindings
+//!
+//! This test demonstrates how to:
+//! - Check uploads against the manifest the node stored
+//! - Read the content back on request
+//! - Skip verification
+
+use codex_bindings::{upload_reader, CodexConfig, CodexNode, LogLevel, UploadOptions};
+use std::io::Cursor;
+use tempfile::tempdir;
+
+#[tokio::test]
+async fn test_upload_verification() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialize logging
+    let _ = env_logger::try_init();
+
+    println!("Codex Rust Bindings - Upload Verification Test");
+    println!("==============================================");
+
+    let temp_dir = tempdir()?;
+    let content = b"Hello, Codex! This is a test file for the Rust bindings.".to_vec();
+
+    let config = CodexConfig::new()
+        .log_level(LogLevel::Error)
+        .data_dir(temp_dir.path().join("codex_data"))
+        .storage_quota(100 * 1024 * 1024) // 100 MB
+        .discovery_port(8110);
+
+    let mut node = CodexNode::new(config)?;
+    node.start()?;
+
+    println!("\n=== Manifest Verification ===");
+    let checked = upload_reader(&node, UploadOptions::new(), Cursor::new(content.clone())).await?;
+    assert!(checked.verified);
+    println!("  ✓ Manifest of {} matches the upload", checked.cid);
+
+    println!("\n=== Content Verification ===");
+    let options = UploadOptions::new().verify_content(true);
+    let read_back = upload_reader(&node, options, Cursor::new(content.clone())).await?;
+    assert!(read_back.verified);
+    println!("  ✓ Content read back from the node matches");
+
+    println!("\n=== Verification Disabled ===");
+    let options = UploadOptions::new().verify(false);
+    let unchecked = upload_reader(&node, options, Cursor::new(content)).await?;
+    assert!(!unchecked.verified);
+    println!("  ✓ Upload reported as unverified");
+
+    node.stop()?;
+    node.destroy()?;
+
+    println!("\nUpload verification test completed successfully!");
+    Ok(())
+}